@@ -1,47 +1,238 @@
 //! A CSS parser that supports a tiny subset of CSS.
 
+mod calc;
+mod color;
+mod import;
+mod layer;
+mod media;
+mod serialize;
+mod shorthand;
+mod supports;
+mod tokenizer;
+
+pub use import::{FileLoader, ImportError, Loader};
+pub use layer::Layers;
+pub use serialize::{Format, ToCss};
+pub use tokenizer::tokenize;
+
 #[derive(Debug, PartialEq)]
 pub struct Stylesheet {
-    pub rules: Vec<Rule>,
+    pub origin: Origin,
+    pub rules: Vec<CssRule>,
 }
 
 #[derive(Debug, PartialEq)]
+pub enum CssRule {
+    Style(Rule),
+    /// `@media <queries> { <rules> }`: rules that only apply if one of the
+    /// queries matches the device.
+    /// spec: https://www.w3.org/TR/css-conditional-3/#at-media
+    Media(Vec<MediaQuery>, Vec<CssRule>),
+    /// `@import <url> <queries>;`: the rules of another stylesheet, which
+    /// apply where the `@import` is if one of the queries matches. The rules
+    /// are empty until loaded with `Stylesheet::load_imports`.
+    /// spec: https://www.w3.org/TR/css-cascade-4/#at-import
+    Import(String, Vec<MediaQuery>, Vec<CssRule>),
+    /// `@supports <condition> { <rules> }`: rules that only apply if the
+    /// condition is supported.
+    /// spec: https://www.w3.org/TR/css-conditional-3/#at-supports
+    Supports(SupportsCondition, Vec<CssRule>),
+    /// `@layer [<name>] { <rules> }`: rules in a cascade layer, which is a
+    /// new anonymous layer if it has no name.
+    /// spec: https://www.w3.org/TR/css-cascade-5/#layer-block
+    Layer(Option<String>, Vec<CssRule>),
+    /// `@layer <name>#;`: declares layers, establishing their order.
+    LayerStatement(Vec<String>),
+}
+
+/// Where a stylesheet comes from. Normal declarations of later origins win
+/// over earlier ones, and important declarations reverse this order.
+/// spec: https://www.w3.org/TR/css-cascade-4/#cascading-origins
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    UserAgent,
+    User,
+    Author,
+}
+
+/// A style rule.
+#[derive(Clone, Debug)]
 pub struct Rule {
     /// Selectors are sorted, most-specific first.
     pub selectors: Vec<Selector>,
     pub declarations: Vec<Declaration>,
+    /// Where the rule starts in the source of its stylesheet.
+    pub location: SourceLocation,
 }
 
-#[derive(Debug, PartialEq)]
+/// Rules with the same selectors and declarations are equal wherever they
+/// are in the source.
+impl PartialEq for Rule {
+    fn eq(&self, other: &Rule) -> bool {
+        (&self.selectors, &self.declarations) == (&other.selectors, &other.declarations)
+    }
+}
+
+/// A position in the source of a stylesheet. Lines and columns count from
+/// 1, and columns count characters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum Selector {
     /// Simple selectors.
     /// spec: https://www.w3.org/TR/CSS2/selector.html#selector-syntax
     Simple(SimpleSelector),
+    /// Simple selectors joined by combinators, e.g. `ul > li.a`: the first
+    /// simple selector, then each combinator with the one after it. The last
+    /// is the subject, which the matched element itself must match.
+    /// spec: https://www.w3.org/TR/selectors-4/#complex
+    Complex(SimpleSelector, Vec<(Combinator, SimpleSelector)>),
 }
 
-#[derive(Debug, PartialEq)]
+/// spec: https://www.w3.org/TR/selectors-4/#combinators
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Combinator {
+    /// `a b`: `b` is a descendant of `a`.
+    Descendant,
+    /// `a > b`: `b` is a child of `a`.
+    Child,
+    /// `a + b`: `b` comes right after its sibling `a`.
+    NextSibling,
+    /// `a ~ b`: `b` comes after its sibling `a`.
+    SubsequentSibling,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct SimpleSelector {
     pub tag_name: Option<String>,
     pub id: Option<String>,
     pub class: Vec<String>,
+    pub pseudo_element: Option<PseudoElement>,
 }
 
-#[derive(Debug, PartialEq)]
+/// spec: https://www.w3.org/TR/css-pseudo-4/#treelike
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PseudoElement {
+    Before,
+    After,
+    Marker,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Declaration {
     pub name: String,
     pub value: Value,
+    /// Set if the declaration is followed by `!important`.
+    pub important: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Value {
     Keyword(String),
     Length(f32, Unit),
+    /// A unitless number, e.g. the `1.5` of `line-height: 1.5`.
+    Number(f32),
     Colorvalue(Color),
+    /// A quoted string, e.g. `"Times New Roman"`.
+    Str(String),
+    /// A URL, given with `url()`.
+    /// spec: https://www.w3.org/TR/css-values-4/#urls
+    Url(String),
+    /// A delimiter between component values, `,` or `/`.
+    Delim(char),
+    /// Space-separated component values, of a property which takes more than
+    /// one, e.g. `font-family: Arial, sans-serif`.
+    List(Vec<Value>),
+    /// Generated content, the value of the `content` property.
+    /// spec: https://www.w3.org/TR/css-content-3/#content-property
+    Content(Vec<ContentItem>),
+    /// Counter names with integers, the value of `counter-reset`,
+    /// `counter-increment` and `counter-set`.
+    /// spec: https://www.w3.org/TR/css-lists-3/#counter-properties
+    CounterList(Vec<(String, i32)>),
+    /// The value of a custom property, kept as its source text.
+    /// spec: https://www.w3.org/TR/css-variables-1/#defining-variables
+    Raw(String),
+    /// A value containing `var()`, which is only parsed once the variables are
+    /// substituted at computed-value time: the declared property (possibly a
+    /// shorthand), and the source text of the value.
+    /// spec: https://www.w3.org/TR/css-variables-1/#pending-substitution-value
+    PendingSubstitution(String, String),
+    /// A math function which can't be simplified to a single length until
+    /// layout, e.g. `calc(100% - 2em)`.
+    Calc(Box<Calc>),
+}
+
+/// The expression tree of a math function.
+/// spec: https://www.w3.org/TR/css-values-4/#math
+#[derive(Clone, Debug, PartialEq)]
+pub enum Calc {
+    /// A `Value::Number` or a `Value::Length`.
+    Leaf(Value),
+    Sum(Box<Calc>, Box<Calc>),
+    Difference(Box<Calc>, Box<Calc>),
+    Product(Box<Calc>, Box<Calc>),
+    Quotient(Box<Calc>, Box<Calc>),
+    Min(Vec<Calc>),
+    Max(Vec<Calc>),
+    /// `clamp(min, value, max)`.
+    Clamp(Box<Calc>, Box<Calc>, Box<Calc>),
 }
 
 #[derive(Clone, Debug, PartialEq)]
+pub enum ContentItem {
+    /// A quoted string, e.g. `"abc"`.
+    String(String),
+    /// `attr(name)`: the value of an attribute of the originating element.
+    Attr(String),
+    /// `counter(name, style)`.
+    Counter(String, String),
+    /// `counters(name, separator, style)`.
+    Counters(String, String, String),
+}
+
+/// spec: https://www.w3.org/TR/css-values-4/#lengths
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Unit {
+    // Absolute lengths.
     Px,
+    Pt,
+    Pc,
+    In,
+    Cm,
+    Mm,
+    Q,
+    // Font-relative lengths.
+    Em,
+    Rem,
+    Ex,
+    Ch,
+    // Viewport-percentage lengths.
+    Vw,
+    Vh,
+    Vmin,
+    Vmax,
+    /// A percentage of another length, which depends on the property.
+    Percent,
+}
+
+/// The initial value of `font-size` (`medium`) in px.
+pub const DEFAULT_FONT_SIZE: f32 = 16.0;
+
+/// Sizes that relative lengths are resolved against.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LengthContext {
+    /// Font size of the element, for `em`, `ex` and `ch`.
+    pub font_size: f32,
+    /// Font size of the root element, for `rem`.
+    pub root_font_size: f32,
+    /// Size of the viewport, for `vw`, `vh`, `vmin` and `vmax`.
+    pub viewport_width: f32,
+    pub viewport_height: f32,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -49,6 +240,129 @@ pub struct Color {
     pub r: u8,
     pub g: u8,
     pub b: u8,
+    /// Alpha channel, where 0 is fully transparent and 255 is opaque.
+    pub a: u8,
+}
+
+/// A media query, e.g. `not screen and (width >= 600px)`. A query without a
+/// media type applies to all media.
+/// spec: https://www.w3.org/TR/mediaqueries-4/#media
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaQuery {
+    /// Set by the `not` keyword, which negates the whole query.
+    pub negated: bool,
+    pub media_type: Option<String>,
+    pub condition: Option<MediaCondition>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MediaCondition {
+    Feature(MediaFeature),
+    Not(Box<MediaCondition>),
+    And(Vec<MediaCondition>),
+    Or(Vec<MediaCondition>),
+    /// Something in parentheses which isn't understood. It is neither true
+    /// nor false, but unknown.
+    Unknown,
+}
+
+/// A test of a feature of the device, e.g. `(orientation: portrait)`.
+/// spec: https://www.w3.org/TR/mediaqueries-4/#mq-features
+#[derive(Clone, Debug, PartialEq)]
+pub enum MediaFeature {
+    /// `(name)`: true if the feature's value isn't zero or `none`.
+    Boolean(String),
+    /// `(name: value)`.
+    Plain(String, Value),
+    /// `(name >= value)`, `(value < name <= value)` or `(min-name: value)`:
+    /// the feature compared with each value. Resolutions and ratios are
+    /// numbers, in dppx and width over height.
+    Range(String, Vec<(Comparison, Value)>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+/// The condition of an `@supports` rule.
+/// spec: https://www.w3.org/TR/css-conditional-3/#typedef-supports-condition
+#[derive(Clone, Debug, PartialEq)]
+pub enum SupportsCondition {
+    /// `(name: value)`: true if the property is supported.
+    Declaration(String, String),
+    Not(Box<SupportsCondition>),
+    And(Vec<SupportsCondition>),
+    Or(Vec<SupportsCondition>),
+    /// Something which isn't understood, e.g. `selector(a > b)`, which is
+    /// false.
+    Unknown,
+}
+
+/// The output device that media queries are evaluated against.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Device {
+    /// The media type, e.g. `screen` or `print`.
+    pub media_type: String,
+    /// Size of the viewport in px.
+    pub width: f32,
+    pub height: f32,
+    /// Device pixels per px.
+    pub resolution: f32,
+    pub color_scheme: ColorScheme,
+}
+
+/// The color scheme the user prefers.
+/// spec: https://www.w3.org/TR/mediaqueries-5/#prefers-color-scheme
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorScheme {
+    Light,
+    Dark,
+}
+
+/// A token of CSS syntax.
+/// spec: https://www.w3.org/TR/css-syntax-3/#tokenization
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Ident(String),
+    /// A function name, e.g. `rgb(`, including its `(`.
+    Function(String),
+    /// `@` and a name, e.g. `@media`.
+    AtKeyword(String),
+    /// `#` and a name, e.g. `#main` or `#fff`. The flag is set if the name
+    /// is an identifier, as for an id selector.
+    Hash(String, bool),
+    /// A quoted string, with its escapes resolved.
+    String(String),
+    /// A string with an unescaped newline in it.
+    BadString,
+    /// An unquoted `url()`, e.g. `url(a.png)`. A quoted one is a function.
+    Url(String),
+    /// A `url()` with a quote, `(` or a non-printable character in it.
+    BadUrl,
+    Number(f32),
+    Percentage(f32),
+    Dimension(f32, String),
+    Whitespace,
+    /// `<!--`
+    Cdo,
+    /// `-->`
+    Cdc,
+    Colon,
+    Semicolon,
+    Comma,
+    OpenParen,
+    CloseParen,
+    OpenBracket,
+    CloseBracket,
+    OpenBrace,
+    CloseBrace,
+    /// Any other character.
+    Delim(char),
 }
 
 /// spec: https://www.w3.org/TR/selectors/#specificity
@@ -57,123 +371,705 @@ pub type Specificity = (usize, usize, usize);
 impl Selector {
     /// spec: https://www.w3.org/TR/selectors/#specificity
     pub fn specificity(&self) -> Specificity {
-        let Selector::Simple(ref simple) = *self;
-        let a = simple.id.iter().count();
-        let b = simple.class.len();
-        let c = simple.tag_name.iter().count();
-        (a, b, c)
+        self.parts()
+            .iter()
+            .fold((0, 0, 0), |(a, b, c), (_, simple)| {
+                (
+                    a + simple.id.iter().count(),
+                    b + simple.class.len(),
+                    c + simple.tag_name.iter().count() + simple.pseudo_element.iter().count(),
+                )
+            })
+    }
+
+    /// The pseudo-element this selector targets, if any.
+    pub fn pseudo_element(&self) -> Option<PseudoElement> {
+        self.subject().pseudo_element
+    }
+
+    /// The simple selector that the matched element itself must match.
+    pub fn subject(&self) -> &SimpleSelector {
+        match self {
+            Selector::Simple(simple) => simple,
+            Selector::Complex(first, rest) => rest.last().map_or(first, |(_, simple)| simple),
+        }
+    }
+
+    /// The simple selectors, each with the combinator before it. The first
+    /// has none, which is given as `Descendant`.
+    fn parts(&self) -> Vec<(Combinator, SimpleSelector)> {
+        match self {
+            Selector::Simple(simple) => vec![(Combinator::Descendant, simple.clone())],
+            Selector::Complex(first, rest) => {
+                let mut parts = vec![(Combinator::Descendant, first.clone())];
+                parts.extend(rest.iter().cloned());
+                parts
+            }
+        }
+    }
+
+    /// The inverse of `parts`.
+    fn from_parts(mut parts: Vec<(Combinator, SimpleSelector)>) -> Selector {
+        let (_, first) = parts.remove(0);
+        match parts.is_empty() {
+            true => Selector::Simple(first),
+            false => Selector::Complex(first, parts),
+        }
     }
 }
 
 impl Value {
-    /// Return the size of a length in px, or zero for non-lengths.
-    pub fn to_px(&self) -> f32 {
+    /// Return the size of a length or a math function in px, or zero for
+    /// other values. Percentages are relative to `percent_base`.
+    pub fn to_px(&self, context: &LengthContext, percent_base: f32) -> f32 {
         match *self {
-            Value::Length(f, Unit::Px) => f,
+            Value::Length(f, Unit::Percent) => f * percent_base / 100.0,
+            Value::Length(f, unit) => f * unit.px_per_unit(context),
+            Value::Calc(ref calc) => calc.to_px(context, percent_base),
             _ => 0.0,
         }
     }
 }
 
-/// Parse a whole CSS stylesheet.
+impl Unit {
+    /// Parse a unit name, e.g. `px`, case-insensitively.
+    fn from_name(name: &str) -> Option<Unit> {
+        let unit = match &*name.to_ascii_lowercase() {
+            "px" => Unit::Px,
+            "pt" => Unit::Pt,
+            "pc" => Unit::Pc,
+            "in" => Unit::In,
+            "cm" => Unit::Cm,
+            "mm" => Unit::Mm,
+            "q" => Unit::Q,
+            "em" => Unit::Em,
+            "rem" => Unit::Rem,
+            "ex" => Unit::Ex,
+            "ch" => Unit::Ch,
+            "vw" => Unit::Vw,
+            "vh" => Unit::Vh,
+            "vmin" => Unit::Vmin,
+            "vmax" => Unit::Vmax,
+            "%" => Unit::Percent,
+            _ => return None,
+        };
+        Some(unit)
+    }
+
+    /// The size of one of this unit in px. Must not be called for percentages,
+    /// which have no fixed size.
+    fn px_per_unit(self, context: &LengthContext) -> f32 {
+        const PX_PER_IN: f32 = 96.0;
+        const PX_PER_CM: f32 = PX_PER_IN / 2.54;
+        match self {
+            Unit::Px => 1.0,
+            Unit::Pt => PX_PER_IN / 72.0,
+            Unit::Pc => PX_PER_IN / 6.0,
+            Unit::In => PX_PER_IN,
+            Unit::Cm => PX_PER_CM,
+            Unit::Mm => PX_PER_CM / 10.0,
+            Unit::Q => PX_PER_CM / 40.0,
+            Unit::Em => context.font_size,
+            Unit::Rem => context.root_font_size,
+            // Without font metrics, both `ex` and `ch` are assumed to be 0.5em.
+            // spec: https://www.w3.org/TR/css-values-4/#ex
+            Unit::Ex | Unit::Ch => context.font_size / 2.0,
+            Unit::Vw => context.viewport_width / 100.0,
+            Unit::Vh => context.viewport_height / 100.0,
+            Unit::Vmin => context.viewport_width.min(context.viewport_height) / 100.0,
+            Unit::Vmax => context.viewport_width.max(context.viewport_height) / 100.0,
+            Unit::Percent => panic!("Percentages have no fixed size."),
+        }
+    }
+}
+
+impl Stylesheet {
+    /// Return the style rules that apply, in source order, each with the id
+    /// of its cascade layer in `layers`, which the layers of the stylesheet
+    /// are added to. The stylesheets of an origin share their layers.
+    ///
+    /// Rules in `@media` and `@import` rules whose queries don't match
+    /// `device` are left out, as are rules in `@supports` rules whose
+    /// conditions aren't met, given whether each declaration is supported.
+    pub fn style_rules<'a>(
+        &'a self,
+        device: &Device,
+        supports_declaration: &dyn Fn(&str, &str) -> bool,
+        layers: &mut Layers,
+    ) -> Vec<(usize, &'a Rule)> {
+        fn collect<'a>(
+            rules: &'a [CssRule],
+            layer: usize,
+            device: &Device,
+            supports_declaration: &dyn Fn(&str, &str) -> bool,
+            layers: &mut Layers,
+            style_rules: &mut Vec<(usize, &'a Rule)>,
+        ) {
+            for rule in rules {
+                match rule {
+                    CssRule::Style(rule) => style_rules.push((layer, rule)),
+                    CssRule::Media(queries, rules) | CssRule::Import(_, queries, rules) => {
+                        if media::matches(queries, device) {
+                            collect(
+                                rules,
+                                layer,
+                                device,
+                                supports_declaration,
+                                layers,
+                                style_rules,
+                            );
+                        }
+                    }
+                    CssRule::Supports(condition, rules) => {
+                        if condition.evaluate(supports_declaration) {
+                            collect(
+                                rules,
+                                layer,
+                                device,
+                                supports_declaration,
+                                layers,
+                                style_rules,
+                            );
+                        }
+                    }
+                    CssRule::Layer(name, rules) => {
+                        let layer = layers.declare(layer, name.as_deref());
+                        collect(
+                            rules,
+                            layer,
+                            device,
+                            supports_declaration,
+                            layers,
+                            style_rules,
+                        );
+                    }
+                    CssRule::LayerStatement(names) => {
+                        for name in names {
+                            layers.declare(layer, Some(name));
+                        }
+                    }
+                }
+            }
+        }
+        let mut style_rules = vec![];
+        collect(
+            &self.rules,
+            Layers::ROOT,
+            device,
+            supports_declaration,
+            layers,
+            &mut style_rules,
+        );
+        style_rules
+    }
+}
+
+impl Device {
+    /// A screen with a viewport of the given size, at one device pixel per px.
+    pub fn new(width: f32, height: f32) -> Device {
+        Device {
+            media_type: "screen".to_string(),
+            width,
+            height,
+            resolution: 1.0,
+            color_scheme: ColorScheme::Light,
+        }
+    }
+}
+
+impl LengthContext {
+    /// A context for a viewport of the given size, with the default font size.
+    pub fn new(viewport_width: f32, viewport_height: f32) -> LengthContext {
+        LengthContext {
+            font_size: DEFAULT_FONT_SIZE,
+            root_font_size: DEFAULT_FONT_SIZE,
+            viewport_width,
+            viewport_height,
+        }
+    }
+}
+
+/// Parse a whole CSS stylesheet of the author origin.
 pub fn parse(source: String) -> Stylesheet {
-    let mut parser = Parser {
-        pos: 0,
-        input: source,
-    };
+    let mut parser = Parser::new(tokenizer::preprocess(&source));
     parser.parse_rules()
 }
 
+/// Parse the declarations of a `style` attribute, e.g. `color: red; margin: 0`.
+/// Invalid declarations are skipped.
+/// spec: https://www.w3.org/TR/css-style-attr/#syntax
+pub fn parse_inline(source: &str) -> Vec<Declaration> {
+    let mut parser = Parser::new(
+        // Close the block so the parser knows where the declarations end.
+        format!("{}}}", tokenizer::preprocess(source)),
+    );
+    parser.parse_declarations()
+}
+
+/// Parse a comma-separated list of selectors, e.g. `ul > li, p.a`, sorted by
+/// specificity. An invalid or unsupported selector list has no selectors.
+pub fn parse_selectors(source: &str) -> Vec<Selector> {
+    let mut parser = Parser::new(
+        // Open a block so the parser knows where the selectors end.
+        format!("{} {{", tokenizer::preprocess(source.trim())),
+    );
+    parser.parse_selectors(None).unwrap_or_default()
+}
+
 struct Parser {
     pos: usize,
     input: String,
+    /// The last location returned by `location`, with its position, which
+    /// the next one is counted from.
+    last_location: (usize, SourceLocation),
+}
+
+impl Parser {
+    fn new(input: String) -> Parser {
+        Parser {
+            pos: 0,
+            input,
+            last_location: (0, SourceLocation { line: 1, column: 1 }),
+        }
+    }
+}
+
+/// A simple selector as parsed, in a selector which may be nested.
+struct NestedSelector {
+    /// The combinator before it, `Descendant` if none.
+    combinator: Combinator,
+    /// Set if it has `&`, which it is combined with.
+    nesting: bool,
+    selector: SimpleSelector,
+}
+
+/// Resolve a selector as parsed against the selectors of the style rule it is
+/// nested in, if any, replacing each `&` with each of them. At the top level,
+/// `&` matches any element.
+/// spec: https://www.w3.org/TR/css-nesting-1/#nest-selector
+fn resolve_nesting(mut parts: Vec<NestedSelector>, parents: Option<&[Selector]>) -> Vec<Selector> {
+    let Some(parents) = parents else {
+        let parts = parts
+            .into_iter()
+            .map(|part| (part.combinator, part.selector));
+        return vec![Selector::from_parts(parts.collect())];
+    };
+    if !parts.iter().any(|part| part.nesting) {
+        parts.insert(
+            0,
+            NestedSelector {
+                combinator: Combinator::Descendant,
+                nesting: true,
+                selector: SimpleSelector {
+                    tag_name: None,
+                    id: None,
+                    class: vec![],
+                    pseudo_element: None,
+                },
+            },
+        );
+    }
+
+    let mut resolved: Vec<Vec<(Combinator, SimpleSelector)>> = vec![vec![]];
+    for part in parts {
+        let replacements: Vec<Vec<(Combinator, SimpleSelector)>> = match part.nesting {
+            true => parents
+                .iter()
+                .map(|parent| {
+                    let mut parent_parts = parent.parts();
+                    parent_parts[0].0 = part.combinator;
+                    let (_, subject) = parent_parts.last_mut().unwrap();
+                    let selector = part.selector.clone();
+                    subject.tag_name = selector.tag_name.or(subject.tag_name.take());
+                    subject.id = selector.id.or(subject.id.take());
+                    subject.class.extend(selector.class);
+                    subject.pseudo_element = selector.pseudo_element.or(subject.pseudo_element);
+                    parent_parts
+                })
+                .collect(),
+            false => vec![vec![(part.combinator, part.selector)]],
+        };
+        resolved = resolved
+            .iter()
+            .flat_map(|prefix| {
+                replacements
+                    .iter()
+                    .map(move |replacement| [prefix.clone(), replacement.clone()].concat())
+            })
+            .collect();
+    }
+    resolved.into_iter().map(Selector::from_parts).collect()
 }
 
 impl Parser {
     fn parse_rules(&mut self) -> Stylesheet {
+        Stylesheet {
+            origin: Origin::Author,
+            rules: self.parse_rule_list(),
+        }
+    }
+
+    /// Parse rules up to the end of the input or of the enclosing block.
+    fn parse_rule_list(&mut self) -> Vec<CssRule> {
         let mut rules = vec![];
         loop {
             self.consume_whitespace();
-            if self.eof() {
+            if self.eof() || self.next_char() == '}' {
                 break;
             }
-            rules.push(self.parse_rule());
+            if self.next_char() == '@' {
+                match self.parse_at_rule(None) {
+                    // `@import` must come before all other rules.
+                    Some(CssRule::Import(..))
+                        if !rules.iter().all(|rule| matches!(rule, CssRule::Import(..))) => {}
+                    rule => rules.extend(rule),
+                }
+            } else {
+                rules.extend(self.parse_rule(None));
+            }
         }
-        Stylesheet { rules }
+        rules
     }
 
-    /// Parse a CSS rule set.
+    /// Parse an at-rule, nested in a style rule with the selectors `parents`
+    /// if any. Unknown or invalid at-rules are skipped, returning `None`, as
+    /// are conditional rules without a block.
     ///
-    /// <rule> := <selectors> "{" <declarations> "}"
-    fn parse_rule(&mut self) -> Rule {
-        let selectors = self.parse_selectors();
-        self.consume_char(); // "{"
+    /// <at-rule> := "@media" <media-query-list> <group-body>
+    ///            | "@supports" <supports-condition> <group-body>
+    ///            | "@layer" [<layer-name>] <group-body> | "@layer" <layer-name># ";"
+    ///            | "@import" <url> <media-query-list> ";"
+    fn parse_at_rule(&mut self, parents: Option<&[Selector]>) -> Option<CssRule> {
+        let name = match self.next_token() {
+            Some(Token::AtKeyword(name)) => name,
+            _ => String::new(),
+        };
+        match &*name.to_ascii_lowercase() {
+            "media" => {
+                let queries = self.parse_media_query_list();
+                let rules = self.parse_group_body(parents)?;
+                Some(CssRule::Media(queries, rules))
+            }
+            "supports" => {
+                let condition = self.parse_supports_prelude();
+                let rules = self.parse_group_body(parents)?;
+                Some(CssRule::Supports(condition, rules))
+            }
+            "layer" => {
+                let mut names = self.parse_layer_names();
+                self.consume_whitespace();
+                match (self.peek(), names.len()) {
+                    (None | Some(';'), 1..) if parents.is_none() => {
+                        self.skip_at_rule();
+                        Some(CssRule::LayerStatement(names))
+                    }
+                    (Some('{'), 0 | 1) => {
+                        let rules = self.parse_group_body(parents)?;
+                        Some(CssRule::Layer(names.pop(), rules))
+                    }
+                    _ => {
+                        self.skip_at_rule();
+                        None
+                    }
+                }
+            }
+            "import" if parents.is_none() => self.parse_import(),
+            _ => {
+                self.skip_at_rule();
+                None
+            }
+        }
+    }
+
+    /// Parse the block of a conditional rule or a layer. In a style rule, it
+    /// may have declarations as well as rules, which apply to the selectors
+    /// of the style rule. If there is no block, e.g. after an unbalanced
+    /// prelude, the rest of the at-rule is skipped, returning `None`.
+    ///
+    /// <group-body> := "{" <rule>* "}" | "{" (<declaration> | <rule> | <at-rule>)* "}"
+    fn parse_group_body(&mut self, parents: Option<&[Selector]>) -> Option<Vec<CssRule>> {
+        let Some(mut rules) = self.parse_block(|parser| match parents {
+            None => parser.parse_rule_list(),
+            Some(selectors) => {
+                parser.consume_whitespace();
+                let location = parser.location();
+                let (declarations, rules) = parser.parse_style_block(selectors);
+                let rule = (!declarations.is_empty()).then(|| {
+                    CssRule::Style(Rule {
+                        selectors: selectors.to_vec(),
+                        declarations,
+                        location,
+                    })
+                });
+                rule.into_iter().chain(rules).collect()
+            }
+        }) else {
+            self.skip_at_rule();
+            return None;
+        };
+        // `@import` is only allowed at the top level.
+        rules.retain(|rule| !matches!(rule, CssRule::Import(..)));
+        Some(rules)
+    }
+
+    /// Parse the contents of a `{}` block with `parse`. Return `None` if no
+    /// block starts at the next character. The end of the input closes the
+    /// block.
+    fn parse_block<T>(&mut self, parse: impl FnOnce(&mut Parser) -> T) -> Option<T> {
+        self.consume_whitespace();
+        if self.peek() != Some('{') {
+            return None;
+        }
+        self.consume_char();
+        let contents = parse(self);
         self.consume_whitespace();
+        match self.peek() {
+            Some('}') => {
+                self.consume_char();
+            }
+            None => {}
+            Some(c) => panic!("Expected }}, but got {}.", c),
+        }
+        Some(contents)
+    }
+
+    /// Consume the prelude of an at-rule, up to its block or its `;`. Return
+    /// its source text without comments.
+    fn consume_prelude(&mut self) -> String {
+        self.consume_component_values(|token| matches!(token, Token::OpenBrace | Token::Semicolon))
+    }
+
+    /// Skip the rest of an at-rule: up to a `;`, or the end of its block. A
+    /// `}` closing the enclosing block also ends it, and is left.
+    fn skip_at_rule(&mut self) {
+        self.consume_component_values(|token| {
+            matches!(
+                token,
+                Token::Semicolon | Token::OpenBrace | Token::CloseBrace
+            )
+        });
+        let end = self.pos;
+        match self.next_token() {
+            Some(Token::OpenBrace) => {
+                self.consume_component_values(|token| *token == Token::CloseBrace);
+                self.next_token();
+            }
+            Some(Token::CloseBrace) => self.pos = end,
+            _ => {}
+        }
+    }
+
+    /// Skip a style rule: its prelude, and its block if it has one. A `}`
+    /// closing the enclosing block ends it, and is left.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-qualified-rule
+    fn skip_rule(&mut self) {
+        self.consume_component_values(|token| {
+            matches!(token, Token::OpenBrace | Token::CloseBrace)
+        });
+        if self.input[self.pos..].starts_with('{') {
+            self.consume_char();
+            self.consume_component_values(|token| *token == Token::CloseBrace);
+            self.next_token();
+        }
+    }
 
-        let declarations = self.parse_declarations();
-        self.consume_char(); // "}"
+    /// Parse a CSS rule set, nested in a style rule with the selectors
+    /// `parents` if any. Rules nested in it are flattened: they follow it, with
+    /// their selectors resolved against its selectors. A rule with an invalid
+    /// or unsupported selector is skipped, with the rules nested in it.
+    /// spec: https://www.w3.org/TR/css-nesting-1/
+    ///
+    /// <rule> := <selectors> "{" (<declaration> | <rule> | <at-rule>)* "}"
+    fn parse_rule(&mut self, parents: Option<&[Selector]>) -> Vec<CssRule> {
+        let location = self.location();
+        let start = self.pos;
+        let parsed = self.parse_selectors(parents).and_then(|selectors| {
+            let block = self.parse_block(|parser| parser.parse_style_block(&selectors))?;
+            Some((selectors, block))
+        });
+        let Some((selectors, (declarations, nested))) = parsed else {
+            self.pos = start;
+            self.skip_rule();
+            return vec![];
+        };
         self.consume_whitespace();
 
-        Rule {
+        let rule = Rule {
             selectors,
             declarations,
+            location,
+        };
+        std::iter::once(CssRule::Style(rule))
+            .chain(nested)
+            .collect()
+    }
+
+    /// Parse the contents of the block of a style rule with `selectors`: its
+    /// declarations, and the rules nested in it.
+    fn parse_style_block(&mut self, selectors: &[Selector]) -> (Vec<Declaration>, Vec<CssRule>) {
+        let mut declarations = vec![];
+        let mut rules = vec![];
+        loop {
+            self.consume_whitespace();
+            if matches!(self.peek(), Some('}') | None) {
+                break;
+            }
+            if self.next_char() == '@' {
+                rules.extend(self.parse_at_rule(Some(selectors)));
+            } else if self.starts_nested_rule() {
+                rules.extend(self.parse_rule(Some(selectors)));
+            } else {
+                declarations.extend(self.parse_declaration());
+            }
+        }
+        (declarations, rules)
+    }
+
+    /// Return true if a nested style rule starts at the next character, rather
+    /// than a declaration: if a `{` comes before the end of a declaration.
+    fn starts_nested_rule(&mut self) -> bool {
+        if self.input[self.pos..].starts_with("--") {
+            return false;
         }
+        let start = self.pos;
+        self.consume_component_values(|token| {
+            matches!(
+                token,
+                Token::Semicolon | Token::OpenBrace | Token::CloseBrace
+            )
+        });
+        let nested = self.next_token() == Some(Token::OpenBrace);
+        self.pos = start;
+        nested
     }
 
     /// Parse a comma-separated list of selectors. Returned list is sorted by
-    /// specificity.
+    /// specificity. Returns `None` if any selector is invalid or unsupported,
+    /// as the whole list is then invalid.
+    /// spec: https://www.w3.org/TR/selectors-4/#invalid
+    ///
+    /// In a rule nested in a style rule with the selectors `parents`, `&`
+    /// stands for any of them, and is implied at the start of selectors
+    /// without it. Each selector is resolved into one for each parent.
     ///
     /// <selectors> := <selector> ("," <selector>)*
-    fn parse_selectors(&mut self) -> Vec<Selector> {
+    fn parse_selectors(&mut self, parents: Option<&[Selector]>) -> Option<Vec<Selector>> {
         let mut selectors = Vec::new();
         loop {
-            selectors.push(Selector::Simple(self.parse_simple_selector()));
+            let parts = self.parse_complex_selector()?;
+            selectors.extend(resolve_nesting(parts, parents));
             self.consume_whitespace();
-            match self.next_char() {
-                ',' => {
-                    self.consume_char();
-                    self.consume_whitespace();
-                }
-                '{' => {
+            let start = self.pos;
+            match self.next_token()? {
+                Token::Comma => self.consume_whitespace(),
+                Token::OpenBrace => {
                     // Start of declarations.
+                    self.pos = start;
                     break;
                 }
-                c => panic!("Unexpected character {} in selector list", c),
+                _ => return None,
             }
         }
         // Sort by specificities.
-        selectors.sort_by(|a, b| b.specificity().cmp(&a.specificity()));
-        selectors
+        selectors.sort_by_key(|s| std::cmp::Reverse(s.specificity()));
+        Some(selectors)
+    }
+
+    /// Parse a selector, e.g. `ul > li.a`, as its simple selectors, each with
+    /// the combinator before it and whether it has `&`. A relative selector,
+    /// e.g. `> li`, starts with a combinator.
+    ///
+    /// <selector> := [<combinator>] <simple-selector> ([<combinator>] <simple-selector>)*
+    /// <combinator> := " " | ">" | "+" | "~"
+    fn parse_complex_selector(&mut self) -> Option<Vec<NestedSelector>> {
+        let mut parts = vec![];
+        let mut combinator = Combinator::Descendant;
+        loop {
+            self.consume_whitespace();
+            let start = self.pos;
+            combinator = match self.next_token()? {
+                Token::Delim('>') => Combinator::Child,
+                Token::Delim('+') => Combinator::NextSibling,
+                Token::Delim('~') => Combinator::SubsequentSibling,
+                Token::Comma | Token::OpenBrace if !parts.is_empty() => {
+                    self.pos = start;
+                    break;
+                }
+                token => {
+                    let nesting = token == Token::Delim('&');
+                    if !nesting {
+                        self.pos = start;
+                    }
+                    let start = self.pos;
+                    let selector = self.parse_simple_selector()?;
+                    if !nesting && self.pos == start {
+                        return None;
+                    }
+                    parts.push(NestedSelector {
+                        combinator,
+                        nesting,
+                        selector,
+                    });
+                    Combinator::Descendant
+                }
+            };
+        }
+        Some(parts)
     }
 
-    /// Parse a simple selector, e.g., `type#id.class1.class2`.
-    fn parse_simple_selector(&mut self) -> SimpleSelector {
+    /// Parse a simple selector, e.g., `type#id.class1.class2::before`.
+    /// Returns `None` for pseudo-classes, unsupported pseudo-elements and a
+    /// `.` without a class name.
+    fn parse_simple_selector(&mut self) -> Option<SimpleSelector> {
         let mut selector = SimpleSelector {
             tag_name: None,
             id: None,
             class: vec![],
+            pseudo_element: None,
         };
-        while !self.eof() {
-            match self.next_char() {
-                '#' => {
-                    self.consume_char();
-                    selector.id = Some(self.parse_identifier());
-                }
-                '.' => {
-                    self.consume_char();
-                    selector.class.push(self.parse_identifier());
-                }
-                '*' => {
+        loop {
+            let start = self.pos;
+            match self.next_token() {
+                Some(Token::Hash(id, true)) => selector.id = Some(id),
+                Some(Token::Delim('.')) => match self.next_token() {
+                    Some(Token::Ident(class)) => selector.class.push(class),
+                    _ => return None,
+                },
+                Some(Token::Delim('*')) => {
                     // universal selector
-                    self.consume_char();
                 }
-                'a'..='z' | 'A'..='Z' => {
-                    selector.tag_name = Some(self.parse_identifier());
+                Some(Token::Colon) => {
+                    self.pos = start;
+                    selector.pseudo_element = Some(self.parse_pseudo_element()?);
+                }
+                Some(Token::Ident(tag_name)) => selector.tag_name = Some(tag_name),
+                _ => {
+                    self.pos = start;
+                    break;
                 }
-                _ => break,
             }
         }
-        selector
+        Some(selector)
+    }
+
+    /// Parse a pseudo-element, e.g. `::before`. The legacy single-colon
+    /// syntax (`:before`) is also accepted for `before` and `after`.
+    fn parse_pseudo_element(&mut self) -> Option<PseudoElement> {
+        assert!(self.next_token() == Some(Token::Colon));
+        let start = self.pos;
+        let legacy = self.next_token() != Some(Token::Colon);
+        if legacy {
+            self.pos = start;
+        }
+        let Some(Token::Ident(name)) = self.next_token() else {
+            return None;
+        };
+        match &*name.to_ascii_lowercase() {
+            "before" => Some(PseudoElement::Before),
+            "after" => Some(PseudoElement::After),
+            "marker" if !legacy => Some(PseudoElement::Marker),
+            _ => None,
+        }
     }
 
     /// Parse a list of declarations.
@@ -183,81 +1079,453 @@ impl Parser {
         let mut decls = vec![];
         loop {
             self.consume_whitespace();
-            if self.next_char() == '}' {
+            if matches!(self.peek(), Some('}') | None) {
                 break;
             }
-            decls.push(self.parse_declaration());
+            decls.extend(self.parse_declaration());
         }
         decls
     }
 
-    /// Parse a declaration.
+    /// Parse a declaration. A declaration of a shorthand property is expanded
+    /// into declarations of its longhands. An invalid declaration is skipped
+    /// up to the next `;` or the end of the block, and has no declarations.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-declaration
+    fn parse_declaration(&mut self) -> Vec<Declaration> {
+        let start = self.pos;
+        self.try_parse_declaration().unwrap_or_else(|| {
+            self.pos = start;
+            self.skip_declaration();
+            vec![]
+        })
+    }
+
+    /// Skip a declaration, up to and including its `;`. A `}` closing the
+    /// block also ends it, and is left.
+    fn skip_declaration(&mut self) {
+        self.consume_component_values(|token| {
+            matches!(token, Token::Semicolon | Token::CloseBrace)
+        });
+        if self.peek() == Some(';') {
+            self.consume_char();
+        }
+    }
+
+    /// Parse a declaration, or return `None` if it is invalid.
     ///
-    /// <decralation> := ident ":" <value> ";"
-    /// <value>       := <color> | <length> | ident
-    fn parse_declaration(&mut self) -> Declaration {
-        let name = self.parse_identifier();
-        match self.next_char() {
-            ':' => self.consume_char(),
-            c => panic!("Expected a colon, but got {}.", c),
+    /// <decralation> := ident ":" (<value> | <content> | <counters> | <raw>)
+    ///                  ["!" "important"] (";" | <end of block>)
+    fn try_parse_declaration(&mut self) -> Option<Vec<Declaration>> {
+        let Some(Token::Ident(name)) = self.next_token() else {
+            return None;
         };
         self.consume_whitespace();
+        if self.next_token() != Some(Token::Colon) {
+            return None;
+        }
+        self.consume_whitespace();
 
-        let value = match self.next_char() {
-            '#' => self.parse_color(),
-            '0'..='9' => self.parse_length(),
-            _ => Value::Keyword(self.parse_identifier()),
+        // Values with `var()` can't be parsed until the variables are known.
+        let start = self.pos;
+        let raw = self.parse_raw_value();
+        let is_custom = name.starts_with("--");
+        if !is_custom && !Parser::contains_var(&raw) {
+            self.pos = start;
+        }
+
+        let mut declarations = match &*name {
+            _ if is_custom => vec![Declaration {
+                value: match &*raw.to_ascii_lowercase() {
+                    keyword @ ("inherit" | "initial" | "unset" | "revert") => {
+                        Value::Keyword(keyword.to_string())
+                    }
+                    _ => Value::Raw(raw),
+                },
+                name,
+                important: false,
+            }],
+            _ if self.pos != start => shorthand::expand_pending(name, raw),
+            "content" => vec![Declaration {
+                name,
+                value: self.parse_content()?,
+                important: false,
+            }],
+            "counter-reset" | "counter-increment" | "counter-set" => vec![Declaration {
+                value: self.parse_counter_list(&name)?,
+                name,
+                important: false,
+            }],
+            _ => shorthand::expand(name, self.parse_values()?),
         };
-        match self.next_char() {
-            ';' => self.consume_char(),
-            c => panic!("Expected a semicolon, but got {}.", c),
+
+        if self.peek() == Some('!') {
+            self.consume_char();
+            self.consume_whitespace();
+            match self.next_token() {
+                Some(Token::Ident(name)) if name.eq_ignore_ascii_case("important") => {}
+                _ => return None,
+            }
+            self.consume_whitespace();
+            for declaration in &mut declarations {
+                declaration.important = true;
+            }
+        }
+
+        // The semicolon is optional after the last declaration of a block.
+        match self.peek() {
+            Some(';') => {
+                self.consume_char();
+            }
+            Some('}') | None => {}
+            Some(_) => return None,
         };
 
-        Declaration { name, value }
+        Some(declarations)
     }
 
-    /// Parse a color, e.g. `#aa2233`.
-    fn parse_color(&mut self) -> Value {
-        match self.next_char() {
-            '#' => self.consume_char(),
-            c => panic!("Expected #, but got {}.", c),
-        };
-        let (r, g, b) = (
-            u8::from_str_radix(&self.input[self.pos..(self.pos + 2)], 16).unwrap(),
-            u8::from_str_radix(&self.input[(self.pos + 2)..(self.pos + 4)], 16).unwrap(),
-            u8::from_str_radix(&self.input[(self.pos + 4)..(self.pos + 6)], 16).unwrap(),
-        );
-        self.pos += 6;
-        Value::Colorvalue(Color { r, g, b })
-    }
-
-    /// Parse a size, e.g. `24px`.
-    fn parse_length(&mut self) -> Value {
-        let num = self.parse_float();
-        let unit = {
-            if self.input[self.pos..].starts_with("px") {
-                self.pos += 2;
-                Unit::Px
+    /// Parse the component values of a declaration, or return `None` if any
+    /// is invalid.
+    ///
+    /// <value>     := <component>+
+    /// <component> := <color> | <length> | number | string | ident | "," | "/"
+    fn parse_values(&mut self) -> Option<Vec<Value>> {
+        let mut values = vec![];
+        loop {
+            self.consume_whitespace();
+            if self.at_value_end() {
+                break;
+            }
+            let value = match self.next_char() {
+                _ if self.starts_number() => self.parse_length()?,
+                '#' => self.parse_color()?,
+                '"' | '\'' => Value::Str(self.parse_string()?),
+                ',' | '/' => Value::Delim(self.consume_char()),
+                _ => self.parse_keyword()?,
+            };
+            values.push(value);
+        }
+        Some(values)
+    }
+
+    /// Consume the source text of a value up to the end of the declaration,
+    /// skipping over blocks, strings and comments. Surrounding whitespace is
+    /// trimmed.
+    ///
+    /// <raw> := <any value>*
+    fn parse_raw_value(&mut self) -> String {
+        let raw = self.consume_component_values(|token| {
+            matches!(
+                token,
+                Token::Semicolon | Token::Delim('!') | Token::CloseBrace
+            )
+        });
+        raw.trim().to_string()
+    }
+
+    /// Return true if `css` has a `var()` function, at any depth. One in a
+    /// string or a URL doesn't count.
+    fn contains_var(css: &str) -> bool {
+        let mut parser = Parser::new(css.to_string());
+        std::iter::from_fn(|| parser.next_token())
+            .any(|token| matches!(token, Token::Function(name) if name.eq_ignore_ascii_case("var")))
+    }
+
+    /// Parse the value of the `content` property, e.g. `"(" attr(title) ")"`.
+    ///
+    /// <content>      := "none" | "normal" | <content-item>+
+    /// <content-item> := string | "attr(" ident ")"
+    ///                 | "counter(" ident ["," ident] ")"
+    ///                 | "counters(" ident "," string ["," ident] ")"
+    fn parse_content(&mut self) -> Option<Value> {
+        let mut items = vec![];
+        loop {
+            self.consume_whitespace();
+            if self.at_value_end() {
+                break;
+            }
+            match self.next_char() {
+                '"' | '\'' => items.push(ContentItem::String(self.parse_string()?)),
+                _ => {
+                    let name = match self.next_token()? {
+                        Token::Function(name) => name,
+                        // `none` or `normal`
+                        Token::Ident(name) if items.is_empty() => {
+                            return Some(Value::Keyword(name));
+                        }
+                        _ => return None,
+                    };
+                    let item = match &*name {
+                        "attr" => ContentItem::Attr(self.parse_function_ident()),
+                        "counter" => {
+                            let counter = self.parse_function_ident();
+                            let style = self.parse_counter_style()?;
+                            ContentItem::Counter(counter, style)
+                        }
+                        "counters" => {
+                            let counter = self.parse_function_ident();
+                            self.consume_function_comma()?;
+                            let separator = self.parse_string()?;
+                            let style = self.parse_counter_style()?;
+                            ContentItem::Counters(counter, separator, style)
+                        }
+                        _ => return None,
+                    };
+                    self.consume_whitespace();
+                    if self.next_token()? != Token::CloseParen {
+                        return None;
+                    }
+                    items.push(item);
+                }
+            }
+        }
+        Some(Value::Content(items))
+    }
+
+    /// Parse the value of a counter property `name`, e.g. `chapter section 2`.
+    /// Counters without an integer get the default one: 1 for
+    /// `counter-increment`, and 0 for the others.
+    ///
+    /// <counters> := "none" | (ident [integer])+
+    fn parse_counter_list(&mut self, name: &str) -> Option<Value> {
+        let default = if name == "counter-increment" { 1 } else { 0 };
+        let mut counters = vec![];
+        loop {
+            self.consume_whitespace();
+            if self.at_value_end() {
+                break;
+            }
+            let counter = self.parse_identifier();
+            if counter.is_empty() {
+                return None;
+            }
+            if counter == "none" && counters.is_empty() {
+                return Some(Value::Keyword(counter));
+            }
+            self.consume_whitespace();
+            let value = if self.starts_number() {
+                self.parse_integer()?
             } else {
-                panic!("Unexpected unit, rather than \"px\".");
+                default
+            };
+            counters.push((counter, value));
+        }
+        Some(Value::CounterList(counters))
+    }
+
+    /// Parse an integer, e.g. `-2`, or return `None` for other numbers.
+    fn parse_integer(&mut self) -> Option<i32> {
+        let num = self.parse_number();
+        (num.fract() == 0.0).then_some(num as i32)
+    }
+
+    /// Parse an identifier argument of a function, skipping surrounding
+    /// whitespace.
+    fn parse_function_ident(&mut self) -> String {
+        self.consume_whitespace();
+        let ident = self.parse_identifier();
+        self.consume_whitespace();
+        ident
+    }
+
+    /// Consume a `,` separating function arguments, or return `None` if there
+    /// is none.
+    fn consume_function_comma(&mut self) -> Option<()> {
+        self.consume_whitespace();
+        if self.peek()? != ',' {
+            return None;
+        }
+        self.consume_char();
+        self.consume_whitespace();
+        Some(())
+    }
+
+    /// Parse the optional counter style argument of `counter()` and
+    /// `counters()`. Defaults to `decimal`.
+    fn parse_counter_style(&mut self) -> Option<String> {
+        self.consume_whitespace();
+        if self.peek() != Some(',') {
+            return Some("decimal".to_string());
+        }
+        self.consume_function_comma()?;
+        Some(self.parse_function_ident())
+    }
+
+    /// Parse a quoted string, e.g. `"abc"` or `'a\'b'`, with its escapes
+    /// resolved. A string with an unescaped newline is invalid.
+    fn parse_string(&mut self) -> Option<String> {
+        match self.next_token()? {
+            Token::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Parse a hex color, e.g. `#aa2233`.
+    fn parse_color(&mut self) -> Option<Value> {
+        Some(Value::Colorvalue(self.parse_hex_color()?))
+    }
+
+    /// Parse a keyword, a named color such as `red`, or a color function such
+    /// as `rgb(0 0 0)`. `currentColor` is kept as the keyword `currentcolor`,
+    /// since it depends on the `color` property.
+    fn parse_keyword(&mut self) -> Option<Value> {
+        let ident = match self.next_token()? {
+            Token::Ident(ident) => ident,
+            Token::Url(url) => return Some(Value::Url(url)),
+            Token::Function(name) if name.eq_ignore_ascii_case("url") => {
+                return Some(Value::Url(self.parse_url_arguments()?));
             }
+            Token::Function(name) if calc::is_math_function(&name) => {
+                return Some(Value::Calc(Box::new(self.parse_math_function(&name)?)));
+            }
+            Token::Function(name) => {
+                return Some(Value::Colorvalue(self.parse_color_function(&name)?));
+            }
+            _ => return None,
+        };
+        if ident.eq_ignore_ascii_case("currentcolor") {
+            return Some(Value::Keyword("currentcolor".to_string()));
+        }
+        Some(match color::named_color(&ident) {
+            Some(color) => Value::Colorvalue(color),
+            None => Value::Keyword(ident),
+        })
+    }
+
+    /// Parse a size, e.g. `24px`, `-1.5em` or `50%`. Other unitless numbers
+    /// than zero, which is treated as `0px`, are parsed as `Value::Number`.
+    /// Return `None` for unknown units.
+    fn parse_length(&mut self) -> Option<Value> {
+        let (num, name) = match self.next_token()? {
+            Token::Number(num) => (num, String::new()),
+            Token::Percentage(num) => (num, "%".to_string()),
+            Token::Dimension(num, unit) => (num, unit),
+            _ => return None,
+        };
+        let unit = match Unit::from_name(&name) {
+            Some(unit) => unit,
+            None if name.is_empty() && num == 0.0 => Unit::Px,
+            None if name.is_empty() => return Some(Value::Number(num)),
+            None => return None,
+        };
+        Some(Value::Length(num, unit))
+    }
+
+    /// Return true if the next characters start a number, e.g. `2`, `-1` or
+    /// `+.5`.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#starts-with-a-number
+    fn starts_number(&self) -> bool {
+        let mut chars = self.input[self.pos..].chars();
+        matches!(
+            (chars.next(), chars.next(), chars.next()),
+            (Some('+' | '-'), Some('0'..='9'), _)
+                | (Some('+' | '-'), Some('.'), Some('0'..='9'))
+                | (Some('.'), Some('0'..='9'), _)
+                | (Some('0'..='9'), _, _)
+        )
+    }
+
+    /// Parse a number, e.g. `-1.5e2`.
+    ///
+    /// <number> := ["+" | "-"] <digits> ["." <digits>] [("e" | "E") ["+" | "-"] <digits>]
+    ///           | ["+" | "-"] "." <digits> [("e" | "E") ["+" | "-"] <digits>]
+    ///
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-number
+    fn parse_number(&mut self) -> f32 {
+        assert!(self.starts_number(), "Expected a number.");
+        let mut repr = String::new();
+        if matches!(self.next_char(), '+' | '-') {
+            repr.push(self.consume_char());
+        }
+        repr += &self.consume_while(|c| c.is_ascii_digit());
+
+        let mut chars = self.input[self.pos..].chars();
+        if let (Some('.'), Some('0'..='9')) = (chars.next(), chars.next()) {
+            repr.push(self.consume_char());
+            repr += &self.consume_while(|c| c.is_ascii_digit());
+        }
+
+        // The exponent must have digits, so that e.g. the `e` of `1em` is
+        // left for the unit.
+        let mut chars = self.input[self.pos..].chars();
+        let exponent_len = match (chars.next(), chars.next(), chars.next()) {
+            (Some('e' | 'E'), Some('0'..='9'), _) => 1,
+            (Some('e' | 'E'), Some('+' | '-'), Some('0'..='9')) => 2,
+            _ => 0,
         };
-        Value::Length(num, unit)
+        if exponent_len > 0 {
+            repr += &self.input[self.pos..(self.pos + exponent_len)];
+            self.pos += exponent_len;
+            repr += &self.consume_while(|c| c.is_ascii_digit());
+        }
+
+        repr.parse().unwrap()
+    }
+
+    /// Parse an identifier, e.g. `margin-top`, `--main` or `\31 0`, with its
+    /// escapes resolved. Return an empty string if none starts at the next
+    /// character.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-an-ident-sequence
+    fn parse_identifier(&mut self) -> String {
+        match self.starts_identifier() {
+            true => self.consume_name(),
+            false => String::new(),
+        }
+    }
+
+    /// Consume `keyword` and the whitespace after it, case-insensitively.
+    /// Return false, consuming nothing, if the input doesn't start with it.
+    fn consume_keyword(&mut self, keyword: &str) -> bool {
+        let start = self.pos;
+        if self.parse_identifier().eq_ignore_ascii_case(keyword) {
+            self.consume_whitespace();
+            true
+        } else {
+            self.pos = start;
+            false
+        }
+    }
+
+    /// Parse a `(` and the input up to the matching `)`, returning what's
+    /// between them, trimmed. Return `None`, consuming nothing, if the input
+    /// doesn't start with `(`, or the `)` is missing.
+    fn parse_parenthesized(&mut self) -> Option<String> {
+        let start = self.pos;
+        if self.peek() != Some('(') {
+            return None;
+        }
+        self.consume_char();
+        let contents = self.consume_component_values(|token| *token == Token::CloseParen);
+        if self.next_token() != Some(Token::CloseParen) {
+            self.pos = start;
+            return None;
+        }
+        Some(contents.trim().to_string())
     }
 
-    fn parse_float(&mut self) -> f32 {
-        let s = self.consume_while(|c| match c {
-            '0'..='9' | '.' => true,
-            _ => false,
-        });
-        s.parse().unwrap()
+    /// Return the location of the next character in the source. It is
+    /// counted from the last location returned, so that locations in source
+    /// order take linear time overall.
+    fn location(&mut self) -> SourceLocation {
+        let (mut start, mut location) = self.last_location;
+        if self.pos < start {
+            (start, location) = (0, SourceLocation { line: 1, column: 1 });
+        }
+        for c in self.input[start..self.pos].chars() {
+            match c {
+                '\n' => {
+                    location.line += 1;
+                    location.column = 1;
+                }
+                _ => location.column += 1,
+            }
+        }
+        self.last_location = (self.pos, location);
+        location
     }
 
-    fn parse_identifier(&mut self) -> String {
-        self.consume_while(|c| match c {
-            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => true,
-            _ => false,
-        })
+    /// Return the next character, or `None` at the end of the input.
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
     }
 
     fn next_char(&self) -> char {
@@ -274,9 +1542,20 @@ impl Parser {
         self.pos >= self.input.len()
     }
 
-    /// Consume and discard zero or more whitespace characters.
+    /// Return true if the value of a declaration ends at the next character,
+    /// or at the end of the input.
+    fn at_value_end(&self) -> bool {
+        matches!(self.peek(), Some(';' | '!' | '}') | None)
+    }
+
+    /// Consume and discard zero or more whitespace characters and comments.
     fn consume_whitespace(&mut self) {
-        self.consume_while(char::is_whitespace);
+        loop {
+            self.consume_while(char::is_whitespace);
+            if !self.consume_comment() {
+                break;
+            }
+        }
     }
 
     /// Consume characters until `test` returns false.
@@ -296,6 +1575,18 @@ impl Parser {
 mod tests {
     use super::*;
 
+    /// Declarations of the four sides of a box property, e.g. `margin-top`.
+    pub(super) fn sides(property: &str, value: Value) -> Vec<Declaration> {
+        ["top", "right", "bottom", "left"]
+            .iter()
+            .map(|side| Declaration {
+                name: format!("{}-{}", property, side),
+                value: value.clone(),
+                important: false,
+            })
+            .collect()
+    }
+
     #[test]
     fn parse_css_stylesheet() {
         let input = r#"
@@ -313,66 +1604,230 @@ mod tests {
                         tag_name: Some("h1".to_string()),
                         id: None,
                         class: vec![],
+                        pseudo_element: None,
                     }),
                     Selector::Simple(SimpleSelector {
                         tag_name: Some("h2".to_string()),
                         id: None,
                         class: vec![],
+                        pseudo_element: None,
                     }),
                     Selector::Simple(SimpleSelector {
                         tag_name: Some("h3".to_string()),
                         id: None,
                         class: vec![],
+                        pseudo_element: None,
                     }),
                 ],
-                declarations: vec![
-                    Declaration {
-                        name: "margin".to_string(),
-                        value: Value::Keyword("auto".to_string()),
-                    },
-                    Declaration {
+                declarations: [
+                    sides("margin", Value::Keyword("auto".to_string())),
+                    vec![Declaration {
                         name: "color".to_string(),
-                        value: Value::Colorvalue(Color { r: 204, g: 0, b: 0 }),
-                    },
-                ],
+                        value: Value::Colorvalue(Color {
+                            r: 204,
+                            g: 0,
+                            b: 0,
+                            a: 255,
+                        }),
+                        important: false,
+                    }],
+                ]
+                .concat(),
+                location: SourceLocation {
+                    line: 2,
+                    column: 13,
+                },
             },
             Rule {
                 selectors: vec![Selector::Simple(SimpleSelector {
                     tag_name: Some("div".to_string()),
                     id: None,
                     class: vec!["note".to_string()],
+                    pseudo_element: None,
                 })],
-                declarations: vec![
-                    Declaration {
+                declarations: [
+                    vec![Declaration {
                         name: "margin-bottom".to_string(),
                         value: Value::Length(20.0, Unit::Px),
-                    },
-                    Declaration {
-                        name: "padding".to_string(),
-                        value: Value::Length(10.0, Unit::Px),
-                    },
-                ],
+                        important: false,
+                    }],
+                    sides("padding", Value::Length(10.0, Unit::Px)),
+                ]
+                .concat(),
+                location: SourceLocation {
+                    line: 3,
+                    column: 13,
+                },
             },
             Rule {
                 selectors: vec![Selector::Simple(SimpleSelector {
                     tag_name: None,
                     id: Some("answer".to_string()),
                     class: vec![],
+                    pseudo_element: None,
                 })],
                 declarations: vec![Declaration {
                     name: "display".to_string(),
                     value: Value::Keyword("none".to_string()),
+                    important: false,
                 }],
+                location: SourceLocation {
+                    line: 4,
+                    column: 13,
+                },
             },
         ];
 
         assert_eq!(
             stylesheet,
             Stylesheet {
-                rules: expected_rules
+                origin: Origin::Author,
+                rules: expected_rules.into_iter().map(CssRule::Style).collect(),
             },
         );
     }
+
+    #[test]
+    fn rule_locations() {
+        let stylesheet = parse(
+            "/* é */ a {}\r\n@media print {\n  p { b {} }\n}\n.c { @media print { color: red } }"
+                .to_string(),
+        );
+        let mut locations = vec![];
+        let mut rules: Vec<&CssRule> = stylesheet.rules.iter().collect();
+        while let Some(rule) = rules.pop() {
+            match rule {
+                CssRule::Style(rule) => locations.push((rule.location.line, rule.location.column)),
+                CssRule::Media(_, nested) => rules.extend(nested),
+                _ => unreachable!(),
+            }
+        }
+        locations.sort();
+        // Nested declarations start where the first of them does.
+        assert_eq!(locations, [(1, 9), (3, 3), (3, 7), (5, 1), (5, 21)]);
+
+        // Locations before the last one are counted from the start again.
+        let mut parser = Parser::new("a\nbé c".to_string());
+        parser.pos = 6;
+        assert_eq!(parser.location(), SourceLocation { line: 2, column: 4 });
+        parser.pos = 1;
+        assert_eq!(parser.location(), SourceLocation { line: 1, column: 2 });
+
+        // The location doesn't make rules different.
+        assert_eq!(parse("a {}".to_string()), parse("\n\n  a {}".to_string()));
+    }
+
+    #[test]
+    fn parse_selector_list() {
+        let selectors = parse_selectors(" p, ul > li.a ");
+        assert_eq!(selectors.len(), 2);
+        assert_eq!(selectors[0].specificity(), (0, 1, 2));
+        assert_eq!(selectors[1].subject().tag_name.as_deref(), Some("p"));
+
+        assert!(parse_selectors("a:hover").is_empty());
+    }
+
+    #[test]
+    fn skip_unsupported_selectors() {
+        let stylesheet = parse(
+            "a:hover, p { color: red; b { color: blue } }\n\
+             p ! {}\n\
+             div { p::first-line {} i {} }\n\
+             em {}"
+                .to_string(),
+        );
+        let selectors: Vec<String> = stylesheet
+            .rules
+            .iter()
+            .map(|rule| match rule {
+                CssRule::Style(rule) => rule.selectors[0].subject().tag_name.clone().unwrap(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(selectors, ["div", "i", "em"]);
+    }
+
+    #[test]
+    fn skip_at_rules_without_blocks() {
+        // A `;` ends the at-rule, and the rules after it are kept.
+        let stylesheet = parse("@media screen; p {}".to_string());
+        assert!(matches!(&stylesheet.rules[..], [CssRule::Style(_)]));
+
+        // At the end of the input, or after an unbalanced prelude which runs
+        // up to it, there is nothing left.
+        for css in [
+            "@media screen",
+            "@media",
+            "p {} @media ((width >= 1px) { div {} }",
+        ] {
+            let stylesheet = parse(css.to_string());
+            assert!(
+                stylesheet
+                    .rules
+                    .iter()
+                    .all(|rule| matches!(rule, CssRule::Style(_))),
+                "{}",
+                css
+            );
+        }
+        assert_eq!(parse("p {} @media".to_string()).rules.len(), 1);
+
+        for css in [
+            "@supports (display: block); p {}",
+            "p {} @supports",
+            "p {} @supports not",
+            "p {} @supports (display: block)",
+        ] {
+            let stylesheet = parse(css.to_string());
+            assert!(
+                matches!(&stylesheet.rules[..], [CssRule::Style(_)]),
+                "{}",
+                css
+            );
+        }
+
+        // Nested in a style rule, the declarations after it are kept.
+        let stylesheet = parse("p { @media screen; color: red }".to_string());
+        let [CssRule::Style(rule)] = &stylesheet.rules[..] else {
+            panic!("{:?}", stylesheet.rules);
+        };
+        assert_eq!(rule.declarations.len(), 1);
+        assert_eq!(rule.declarations[0].name, "color");
+    }
+}
+
+#[cfg(test)]
+mod value_tests {
+    use super::*;
+
+    #[test]
+    fn to_px() {
+        let context = LengthContext {
+            font_size: 20.0,
+            root_font_size: 10.0,
+            viewport_width: 800.0,
+            viewport_height: 600.0,
+        };
+        let px = |f, unit| Value::Length(f, unit).to_px(&context, 300.0);
+        assert_eq!(px(12.0, Unit::Px), 12.0);
+        assert_eq!(px(1.0, Unit::In), 96.0);
+        assert_eq!(px(72.0, Unit::Pt), 96.0);
+        assert_eq!(px(1.0, Unit::Pc), 16.0);
+        assert!((px(2.54, Unit::Cm) - 96.0).abs() < 1e-3);
+        assert!((px(40.0, Unit::Q) - px(1.0, Unit::Cm)).abs() < 1e-3);
+        assert_eq!(px(1.5, Unit::Em), 30.0);
+        assert_eq!(px(1.5, Unit::Rem), 15.0);
+        assert_eq!(px(2.0, Unit::Ch), 20.0);
+        assert_eq!(px(10.0, Unit::Vw), 80.0);
+        assert_eq!(px(10.0, Unit::Vh), 60.0);
+        assert_eq!(px(10.0, Unit::Vmin), 60.0);
+        assert_eq!(px(10.0, Unit::Vmax), 80.0);
+        assert_eq!(px(50.0, Unit::Percent), 150.0);
+        assert_eq!(
+            Value::Keyword("auto".to_string()).to_px(&context, 300.0),
+            0.0
+        );
+    }
 }
 
 #[cfg(test)]
@@ -385,6 +1840,7 @@ mod selector_tests {
             tag_name: None,
             id: Some("main".to_string()),
             class: vec![],
+            pseudo_element: None,
         });
         assert_eq!(selector.specificity(), (1, 0, 0));
 
@@ -392,51 +1848,61 @@ mod selector_tests {
             tag_name: Some("div".to_string()),
             id: Some("main".to_string()),
             class: vec!["someclass1".to_string(), "someclass2".to_string()],
+            pseudo_element: None,
         });
         assert_eq!(selector.specificity(), (1, 2, 1));
+
+        let selector = Selector::Simple(SimpleSelector {
+            tag_name: Some("p".to_string()),
+            id: None,
+            class: vec![],
+            pseudo_element: Some(PseudoElement::Before),
+        });
+        assert_eq!(selector.specificity(), (0, 0, 2));
     }
 }
 
 #[cfg(test)]
 mod parser_tests {
+    use super::tests::sides;
     use super::*;
 
+    fn margin_auto() -> Vec<Declaration> {
+        sides("margin", Value::Keyword("auto".to_string()))
+    }
+
     #[test]
     fn parse_rule() {
-        let mut parser = Parser {
-            pos: 0,
-            input: r#"div#main.class1.class2 { margin: auto; display: block; }"#.to_string(),
-        };
-        let rule = parser.parse_rule();
+        let mut parser =
+            Parser::new(r#"div#main.class1.class2 { margin: auto; display: block; }"#.to_string());
+        let rules = parser.parse_rule(None);
         assert_eq!(
-            rule,
-            Rule {
+            rules,
+            vec![CssRule::Style(Rule {
                 selectors: vec![Selector::Simple(SimpleSelector {
                     tag_name: Some("div".to_string()),
                     id: Some("main".to_string()),
                     class: vec!["class1".to_string(), "class2".to_string()],
+                    pseudo_element: None,
                 })],
-                declarations: vec![
-                    Declaration {
-                        name: "margin".to_string(),
-                        value: Value::Keyword("auto".to_string()),
-                    },
-                    Declaration {
+                declarations: [
+                    margin_auto(),
+                    vec![Declaration {
                         name: "display".to_string(),
                         value: Value::Keyword("block".to_string()),
-                    },
-                ],
-            },
+                        important: false,
+                    }],
+                ]
+                .concat(),
+                location: SourceLocation { line: 1, column: 1 },
+            })],
         );
     }
 
     #[test]
     fn parse_selectors() {
-        let mut parser = Parser {
-            pos: 0,
-            input: r#"h1, h2, div.class1.class2, p#id { ..."#.to_string(),
-        };
-        let selectors = parser.parse_selectors();
+        let mut parser = Parser::new(r#"h1, h2, div.class1.class2, p#id { ..."#.to_string());
+        let selectors = parser.parse_selectors(None).unwrap();
         assert_eq!(
             selectors,
             vec![
@@ -445,151 +1911,518 @@ mod parser_tests {
                     tag_name: Some("p".to_string()),
                     id: Some("id".to_string()),
                     class: vec![],
+                    pseudo_element: None,
                 }),
                 Selector::Simple(SimpleSelector {
                     tag_name: Some("div".to_string()),
                     id: None,
                     class: vec!["class1".to_string(), "class2".to_string()],
+                    pseudo_element: None,
                 }),
                 Selector::Simple(SimpleSelector {
                     tag_name: Some("h1".to_string()),
                     id: None,
                     class: vec![],
+                    pseudo_element: None,
                 }),
                 Selector::Simple(SimpleSelector {
                     tag_name: Some("h2".to_string()),
                     id: None,
                     class: vec![],
+                    pseudo_element: None,
                 }),
             ]
         );
         assert_eq!(parser.pos, 32);
     }
 
+    #[test]
+    fn parse_complex_selector() {
+        let mut parser = Parser::new("ul  li>.a + b ~ *::before {".to_string());
+        let simple = |tag_name: Option<&str>, class: &[&str]| SimpleSelector {
+            tag_name: tag_name.map(str::to_string),
+            id: None,
+            class: class.iter().map(|c| c.to_string()).collect(),
+            pseudo_element: None,
+        };
+        let selector = parser.parse_selectors(None).unwrap().remove(0);
+        assert_eq!(
+            selector,
+            Selector::Complex(
+                simple(Some("ul"), &[]),
+                vec![
+                    (Combinator::Descendant, simple(Some("li"), &[])),
+                    (Combinator::Child, simple(None, &["a"])),
+                    (Combinator::NextSibling, simple(Some("b"), &[])),
+                    (
+                        Combinator::SubsequentSibling,
+                        SimpleSelector {
+                            pseudo_element: Some(PseudoElement::Before),
+                            ..simple(None, &[])
+                        }
+                    ),
+                ],
+            )
+        );
+        assert_eq!(selector.specificity(), (0, 1, 4));
+        assert_eq!(selector.pseudo_element(), Some(PseudoElement::Before));
+    }
+
+    #[test]
+    fn parse_nesting() {
+        let nested = parse(
+            r#"
+            .a, #b {
+                color: red;
+                & .c { color: blue; }
+                > p { color: green; }
+                &.d, span & { color: white; }
+                @media print {
+                    color: black;
+                    .e { color: gray; }
+                }
+                font-size: 1px;
+            }
+            "#
+            .to_string(),
+        );
+        let flat = parse(
+            r#"
+            .a, #b { color: red; font-size: 1px; }
+            .a .c, #b .c { color: blue; }
+            .a > p, #b > p { color: green; }
+            .a.d, #b.d, span .a, span #b { color: white; }
+            @media print {
+                .a, #b { color: black; }
+                .a .e, #b .e { color: gray; }
+            }
+            "#
+            .to_string(),
+        );
+        assert_eq!(nested, flat);
+    }
+
+    #[test]
+    fn parse_conditional_rules() {
+        let stylesheet = parse(
+            r#"
+            @layer reset, theme.dark;
+            @layer theme { p {} }
+            @layer { @supports (display: block) { p {} } }
+            @layer a b { p {} }
+            @unknown foo { p {} }
+            "#
+            .to_string(),
+        );
+        let p = || {
+            let mut parser = Parser::new("p {}".to_string());
+            parser.parse_rule(None)
+        };
+        assert_eq!(
+            stylesheet.rules,
+            vec![
+                CssRule::LayerStatement(vec!["reset".to_string(), "theme.dark".to_string()]),
+                CssRule::Layer(Some("theme".to_string()), p()),
+                CssRule::Layer(
+                    None,
+                    vec![CssRule::Supports(
+                        SupportsCondition::Declaration("display".to_string(), "block".to_string()),
+                        p()
+                    )]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_comments_and_escapes() {
+        let stylesheet = parse(
+            r#"
+            /* p { color: red; } */
+            /*
+            @media print { p { color: blue } }
+            */
+            h1/**/, .a\:b /* ; } */ {
+                /* color: red; */
+                content: "/* \"x\" */" /* "; */ ;
+                margin: /**/ 1px/**/2px !important/**/;
+                --var: url(a;b.png) /* } */;
+                color /**/ : red;;
+            }
+            @media /* { */ screen { #été .\31 0 { color: rgb(0/**/ 0 0) } }
+            /* unterminated } "#
+                .to_string(),
+        );
+        let expected = parse(
+            r#"
+            h1, .a\3A b {
+                content: '/* "x" */';
+                margin: 1px 2px !important;
+                --var: url(a;b.png);
+                color: red;
+            }
+            @media screen { #\E9t\E9  .\00031 0 { color: rgb(0 0 0) } }
+            "#
+            .to_string(),
+        );
+        assert_eq!(stylesheet, expected);
+        let CssRule::Style(rule) = &stylesheet.rules[0] else {
+            panic!("Expected a style rule.");
+        };
+        assert_eq!(rule.selectors[0].subject().class, ["a:b"]);
+        assert_eq!(
+            rule.declarations[5].value,
+            Value::Raw("url(a;b.png)".to_string())
+        );
+        let CssRule::Media(_, rules) = &stylesheet.rules[1] else {
+            panic!("Expected a media rule.");
+        };
+        let CssRule::Style(rule) = &rules[0] else {
+            panic!("Expected a style rule.");
+        };
+        assert_eq!(rule.selectors[0].parts()[0].1.id.as_deref(), Some("été"));
+        assert_eq!(rule.selectors[0].subject().class, ["10"]);
+    }
+
     #[test]
     fn parse_simple_selector() {
         // id only
-        let mut parser = Parser {
-            pos: 0,
-            input: r#"#id"#.to_string(),
-        };
+        let mut parser = Parser::new(r#"#id"#.to_string());
         assert_eq!(
-            parser.parse_simple_selector(),
+            parser.parse_simple_selector().unwrap(),
             SimpleSelector {
                 tag_name: None,
                 id: Some("id".to_string()),
                 class: vec![],
+                pseudo_element: None,
             },
         );
 
         // classes only
-        let mut parser = Parser {
-            pos: 0,
-            input: r#".class1.class2"#.to_string(),
-        };
+        let mut parser = Parser::new(r#".class1.class2"#.to_string());
         assert_eq!(
-            parser.parse_simple_selector(),
+            parser.parse_simple_selector().unwrap(),
             SimpleSelector {
                 tag_name: None,
                 id: None,
                 class: vec!["class1".to_string(), "class2".to_string()],
+                pseudo_element: None,
             },
         );
 
         // id + classes
-        let mut parser = Parser {
-            pos: 0,
-            input: r#"#id.class1.class2"#.to_string(),
-        };
+        let mut parser = Parser::new(r#"#id.class1.class2"#.to_string());
         assert_eq!(
-            parser.parse_simple_selector(),
+            parser.parse_simple_selector().unwrap(),
             SimpleSelector {
                 tag_name: None,
                 id: Some("id".to_string()),
                 class: vec!["class1".to_string(), "class2".to_string()],
+                pseudo_element: None,
             },
         );
 
         // tag name only
-        let mut parser = Parser {
-            pos: 0,
-            input: r#"div"#.to_string(),
-        };
+        let mut parser = Parser::new(r#"div"#.to_string());
         assert_eq!(
-            parser.parse_simple_selector(),
+            parser.parse_simple_selector().unwrap(),
             SimpleSelector {
                 tag_name: Some("div".to_string()),
                 id: None,
                 class: vec![],
+                pseudo_element: None,
             },
         );
 
         // tag name + id + classes
-        let mut parser = Parser {
-            pos: 0,
-            input: r#"div#id.class1.class2"#.to_string(),
-        };
+        let mut parser = Parser::new(r#"div#id.class1.class2"#.to_string());
         assert_eq!(
-            parser.parse_simple_selector(),
+            parser.parse_simple_selector().unwrap(),
             SimpleSelector {
                 tag_name: Some("div".to_string()),
                 id: Some("id".to_string()),
                 class: vec!["class1".to_string(), "class2".to_string()],
+                pseudo_element: None,
+            },
+        );
+
+        // escaped and non-ASCII names
+        let mut parser = Parser::new(r#"\64iv#été.\31 0"#.to_string());
+        assert_eq!(
+            parser.parse_simple_selector().unwrap(),
+            SimpleSelector {
+                tag_name: Some("div".to_string()),
+                id: Some("été".to_string()),
+                class: vec!["10".to_string()],
+                pseudo_element: None,
+            },
+        );
+
+        // A class or an id must be an identifier.
+        for input in [".5", ". a", "p.", "#1", "p#1"] {
+            assert!(super::parse_selectors(input).is_empty(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn parse_pseudo_element() {
+        let mut parser = Parser::new(r#"p.note::before"#.to_string());
+        assert_eq!(
+            parser.parse_simple_selector().unwrap(),
+            SimpleSelector {
+                tag_name: Some("p".to_string()),
+                id: None,
+                class: vec!["note".to_string()],
+                pseudo_element: Some(PseudoElement::Before),
+            },
+        );
+
+        // legacy single-colon syntax
+        let mut parser = Parser::new(r#":after"#.to_string());
+        assert_eq!(
+            parser.parse_simple_selector().unwrap(),
+            SimpleSelector {
+                tag_name: None,
+                id: None,
+                class: vec![],
+                pseudo_element: Some(PseudoElement::After),
             },
         );
     }
 
+    #[test]
+    fn parse_content() {
+        let mut parser = Parser::new(
+            r#""(" attr(title) ') ' counter(item) counters(item, ".", upper-roman);"#.to_string(),
+        );
+        assert_eq!(
+            parser.parse_content().unwrap(),
+            Value::Content(vec![
+                ContentItem::String("(".to_string()),
+                ContentItem::Attr("title".to_string()),
+                ContentItem::String(") ".to_string()),
+                ContentItem::Counter("item".to_string(), "decimal".to_string()),
+                ContentItem::Counters(
+                    "item".to_string(),
+                    ".".to_string(),
+                    "upper-roman".to_string()
+                ),
+            ]),
+        );
+
+        let mut parser = Parser::new("none;".to_string());
+        assert_eq!(
+            parser.parse_content().unwrap(),
+            Value::Keyword("none".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_counter_list() {
+        let mut parser = Parser::new("chapter section -2 figure +3;".to_string());
+        assert_eq!(
+            parser.parse_counter_list("counter-increment").unwrap(),
+            Value::CounterList(vec![
+                ("chapter".to_string(), 1),
+                ("section".to_string(), -2),
+                ("figure".to_string(), 3),
+            ]),
+        );
+
+        let mut parser = Parser::new("chapter;".to_string());
+        assert_eq!(
+            parser.parse_counter_list("counter-reset").unwrap(),
+            Value::CounterList(vec![("chapter".to_string(), 0)]),
+        );
+
+        let mut parser = Parser::new("none;".to_string());
+        assert_eq!(
+            parser.parse_counter_list("counter-reset").unwrap(),
+            Value::Keyword("none".to_string()),
+        );
+    }
+
+    #[test]
+    fn parse_string() {
+        let mut parser = Parser::new(r#""a\"b" ..."#.to_string());
+        assert_eq!(parser.parse_string().unwrap(), "a\"b".to_string());
+        assert_eq!(parser.pos, 6);
+    }
+
     #[test]
     fn parse_declarations() {
-        let mut parser = Parser {
-            pos: 0,
-            input: "margin: auto; display: block; } ...".to_string(),
-        };
+        let mut parser = Parser::new("margin: auto; display: block; } ...".to_string());
         let decls = parser.parse_declarations();
+        let mut expected = margin_auto();
+        expected.push(Declaration {
+            name: "display".to_string(),
+            value: Value::Keyword("block".to_string()),
+            important: false,
+        });
+        assert_eq!(decls, expected);
+    }
+
+    #[test]
+    fn parse_var() {
+        let decls =
+            super::parse_inline("--Main: { a: [b;] } !important; --x: Unset; margin: var(--a) 0");
+        assert_eq!(decls[0].name, "--Main");
+        assert_eq!(decls[0].value, Value::Raw("{ a: [b;] }".to_string()));
+        assert!(decls[0].important);
+        assert_eq!(decls[1].value, Value::Keyword("unset".to_string()));
+        assert_eq!(decls[2].name, "margin-top");
+        assert_eq!(
+            decls[2].value,
+            Value::PendingSubstitution("margin".to_string(), "var(--a) 0".to_string())
+        );
+        assert_eq!(decls.len(), 6);
+
+        // `var(` in a string or a URL isn't a reference.
+        let decls = super::parse_inline(
+            r#"content: "var(x)"; background-image: url("var(x)"); width: VAR(--w)"#,
+        );
+        assert!(matches!(decls[0].value, Value::Content(_)));
+        assert_eq!(decls[1].value, Value::Url("var(x)".to_string()));
+        assert!(matches!(decls[2].value, Value::PendingSubstitution(..)));
+    }
+
+    #[test]
+    fn parse_important() {
+        let mut parser = Parser::new("margin: auto !IMPORTANT; ...".to_string());
+        let decls = parser.parse_declaration();
+        assert!(decls.len() == 4 && decls.iter().all(|d| d.important));
+    }
+
+    #[test]
+    fn parse_inline() {
+        let decls = super::parse_inline("color: red !important; display: block");
         assert_eq!(
             decls,
             vec![
                 Declaration {
-                    name: "margin".to_string(),
-                    value: Value::Keyword("auto".to_string()),
+                    name: "color".to_string(),
+                    value: Value::Colorvalue(Color {
+                        r: 255,
+                        g: 0,
+                        b: 0,
+                        a: 255,
+                    }),
+                    important: true,
                 },
                 Declaration {
                     name: "display".to_string(),
                     value: Value::Keyword("block".to_string()),
+                    important: false,
                 },
-            ],
+            ]
         );
+        assert_eq!(super::parse_inline(r"col\6f r: red")[0].name, "color");
+    }
+
+    #[test]
+    fn skip_invalid_declarations() {
+        let names = |css: &str| -> Vec<String> {
+            super::parse_inline(css)
+                .into_iter()
+                .map(|d| d.name)
+                .collect()
+        };
+        assert_eq!(
+            names("a; width: 1px; color: #ggg; height: 1px !x; top: 1qq; left: 0 { b; } }"),
+            ["width"]
+        );
+        assert_eq!(
+            names(": red; color: rgb(a b c); content: foo(); margin: calc(1px +); width: 2px"),
+            ["width"]
+        );
+        assert!(names("}{").is_empty());
+
+        // In a stylesheet, the rest of the block is kept, and so is the end of
+        // the input.
+        let stylesheet = parse("p { color: hsl(#); width: 1px } a { width: 2px".to_string());
+        assert_eq!(stylesheet.rules.len(), 2);
+        match &stylesheet.rules[1] {
+            CssRule::Style(rule) => assert_eq!(rule.declarations.len(), 1),
+            _ => unreachable!(),
+        }
     }
 
     #[test]
     fn parse_declaration() {
-        let mut parser = Parser {
-            pos: 0,
-            input: "margin: auto; ...".to_string(),
+        let mut parser = Parser::new("margin: auto; ...".to_string());
+        let decls = parser.parse_declaration();
+        assert_eq!(decls, margin_auto());
+
+        let mut parser = Parser::new("font-family: \"Times New Roman\", serif; ...".to_string());
+        assert_eq!(
+            parser.parse_declaration(),
+            vec![Declaration {
+                name: "font-family".to_string(),
+                value: Value::List(vec![
+                    Value::Str("Times New Roman".to_string()),
+                    Value::Delim(','),
+                    Value::Keyword("serif".to_string()),
+                ]),
+                important: false,
+            }],
+        );
+    }
+
+    #[test]
+    fn parse_values() {
+        let mut parser = Parser::new("1px solid #000000, 'a'/2;".to_string());
+        assert_eq!(
+            parser.parse_values().unwrap(),
+            vec![
+                Value::Length(1.0, Unit::Px),
+                Value::Keyword("solid".to_string()),
+                Value::Colorvalue(Color {
+                    r: 0,
+                    g: 0,
+                    b: 0,
+                    a: 255,
+                }),
+                Value::Delim(','),
+                Value::Str("a".to_string()),
+                Value::Delim('/'),
+                Value::Number(2.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn parse_urls() {
+        let values = |css: &str| -> Vec<Value> {
+            super::parse_inline(css)
+                .into_iter()
+                .map(|d| d.value)
+                .collect()
         };
-        let decl = parser.parse_declaration();
         assert_eq!(
-            decl,
-            Declaration {
-                name: "margin".to_string(),
-                value: Value::Keyword("auto".to_string()),
-            },
+            values("background-image: url(a.png); background-image: URL( 'b.png' )"),
+            [
+                Value::Url("a.png".to_string()),
+                Value::Url("b.png".to_string())
+            ]
         );
+        assert_eq!(
+            values("background: url(\"c.png\") no-repeat")[1],
+            Value::Url("c.png".to_string())
+        );
+        assert!(values("background-image: url(a b)").is_empty());
     }
 
     #[test]
     fn parse_color() {
-        let mut parser = Parser {
-            pos: 0,
-            input: "#aacc11;".to_string(),
-        };
-        let color = parser.parse_color();
+        let mut parser = Parser::new("#aacc11;".to_string());
+        let color = parser.parse_color().unwrap();
         assert_eq!(
             color,
             Value::Colorvalue(Color {
                 r: 170,
                 g: 204,
-                b: 17
+                b: 17,
+                a: 255
             }),
         );
         assert_eq!(parser.pos, 7);
@@ -597,30 +2430,105 @@ mod parser_tests {
 
     #[test]
     fn parse_length() {
-        let mut parser = Parser {
-            pos: 0,
-            input: "123px;".to_string(),
-        };
-        let length = parser.parse_length();
+        let mut parser = Parser::new("123px;".to_string());
+        let length = parser.parse_length().unwrap();
         assert_eq!(length, Value::Length(123.0, Unit::Px));
         assert_eq!(parser.pos, 5);
+
+        for (input, expected) in [
+            ("1.5em;", Value::Length(1.5, Unit::Em)),
+            ("2REM;", Value::Length(2.0, Unit::Rem)),
+            ("50%;", Value::Length(50.0, Unit::Percent)),
+            ("10vmin;", Value::Length(10.0, Unit::Vmin)),
+            ("3Q;", Value::Length(3.0, Unit::Q)),
+            ("0;", Value::Length(0.0, Unit::Px)),
+            ("-10px;", Value::Length(-10.0, Unit::Px)),
+            ("+4px;", Value::Length(4.0, Unit::Px)),
+            (".5em;", Value::Length(0.5, Unit::Em)),
+            ("1e2px;", Value::Length(100.0, Unit::Px)),
+            ("1.5;", Value::Number(1.5)),
+        ] {
+            let mut parser = Parser::new(input.to_string());
+            assert_eq!(parser.parse_length().unwrap(), expected);
+            assert_eq!(parser.next_char(), ';');
+        }
+    }
+
+    #[test]
+    fn parse_number() {
+        for (input, expected, rest) in [
+            ("12", 12.0, ""),
+            ("-12.5", -12.5, ""),
+            ("+.5", 0.5, ""),
+            ("1e2", 100.0, ""),
+            ("2.5E-1", 0.25, ""),
+            ("1.2.3", 1.2, ".3"),
+            ("1em", 1.0, "em"),
+            ("3e+px", 3.0, "e+px"),
+            ("4.", 4.0, "."),
+        ] {
+            let mut parser = Parser::new(input.to_string());
+            assert_eq!(parser.parse_number(), expected, "{}", input);
+            assert_eq!(&parser.input[parser.pos..], rest, "{}", input);
+        }
+    }
+
+    #[test]
+    fn starts_number() {
+        for (input, expected) in [
+            ("1", true),
+            ("-1", true),
+            ("+.1", true),
+            (".1", true),
+            ("-.", false),
+            ("-webkit-box", false),
+            (".a", false),
+            ("auto", false),
+        ] {
+            let parser = Parser::new(input.to_string());
+            assert_eq!(parser.starts_number(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn parse_keyword() {
+        for (input, expected) in [
+            ("auto;", Value::Keyword("auto".to_string())),
+            ("currentColor;", Value::Keyword("currentcolor".to_string())),
+            (
+                "Red;",
+                Value::Colorvalue(Color {
+                    r: 255,
+                    g: 0,
+                    b: 0,
+                    a: 255,
+                }),
+            ),
+            (
+                "rgb(0 0 255 / 0.5);",
+                Value::Colorvalue(Color {
+                    r: 0,
+                    g: 0,
+                    b: 255,
+                    a: 128,
+                }),
+            ),
+        ] {
+            let mut parser = Parser::new(input.to_string());
+            assert_eq!(parser.parse_keyword().unwrap(), expected, "{}", input);
+            assert_eq!(parser.next_char(), ';');
+        }
     }
 
     #[test]
     fn parse_identifier() {
-        let mut parser = Parser {
-            pos: 0,
-            input: "abc_ef...".to_string(),
-        };
+        let mut parser = Parser::new("abc_ef...".to_string());
         assert_eq!(parser.parse_identifier(), "abc_ef".to_string());
     }
 
     #[test]
     fn next_char() {
-        let mut parser = Parser {
-            pos: 0,
-            input: "abc".to_string(),
-        };
+        let mut parser = Parser::new("abc".to_string());
         assert_eq!(parser.next_char(), 'a');
 
         parser.pos = 1;
@@ -629,19 +2537,13 @@ mod parser_tests {
 
     #[test]
     fn consume_char() {
-        let mut parser = Parser {
-            pos: 0,
-            input: "abc".to_string(),
-        };
+        let mut parser = Parser::new("abc".to_string());
         assert_eq!(parser.consume_char(), 'a');
         assert_eq!(parser.pos, 1);
         assert_eq!(parser.consume_char(), 'b');
         assert_eq!(parser.pos, 2);
 
-        let mut parser = Parser {
-            pos: 0,
-            input: "あいう".to_string(),
-        };
+        let mut parser = Parser::new("あいう".to_string());
         assert_eq!(parser.consume_char(), 'あ');
         assert_eq!(parser.pos, 3);
         assert_eq!(parser.consume_char(), 'い');
@@ -652,7 +2554,7 @@ mod parser_tests {
     fn eof() {
         let mut parser = Parser {
             pos: 2,
-            input: "abc".to_string(),
+            ..Parser::new("abc".to_string())
         };
         assert!(!parser.eof());
 
@@ -0,0 +1,321 @@
+//! Math functions: `calc()`, `min()`, `max()` and `clamp()`.
+//! spec: https://www.w3.org/TR/css-values-4/#math
+
+use super::{Calc, LengthContext, Parser, Token, Unit, Value};
+
+/// Return true if `name` is the name of a math function.
+pub(super) fn is_math_function(name: &str) -> bool {
+    matches!(
+        &*name.to_ascii_lowercase(),
+        "calc" | "min" | "max" | "clamp"
+    )
+}
+
+impl Parser {
+    /// Parse the arguments of the math function `name`, after its `(`, up to
+    /// and including the `)`. Return `None` if `name` isn't a math function,
+    /// or its arguments are invalid.
+    ///
+    /// <math-function> := "calc(" <sum> ")" | ("min(" | "max(") <sum>#  ")"
+    ///                  | "clamp(" <sum> "," <sum> "," <sum> ")"
+    pub(super) fn parse_math_function(&mut self, name: &str) -> Option<Calc> {
+        let calc = match &*name.to_ascii_lowercase() {
+            "calc" => self.parse_calc_sum()?,
+            "min" => Calc::Min(self.parse_calc_arguments()?),
+            "max" => Calc::Max(self.parse_calc_arguments()?),
+            "clamp" => {
+                let [min, value, max] = <[Calc; 3]>::try_from(self.parse_calc_arguments()?).ok()?;
+                Calc::Clamp(Box::new(min), Box::new(value), Box::new(max))
+            }
+            _ => return None,
+        };
+        self.consume_whitespace();
+        match self.next_token()? {
+            Token::CloseParen => Some(calc),
+            _ => None,
+        }
+    }
+
+    /// Parse comma-separated calculations.
+    fn parse_calc_arguments(&mut self) -> Option<Vec<Calc>> {
+        let mut args = vec![self.parse_calc_sum()?];
+        while self.peek() == Some(',') {
+            self.consume_char();
+            args.push(self.parse_calc_sum()?);
+        }
+        Some(args)
+    }
+
+    /// <sum> := <product> (("+" | "-") <product>)*
+    fn parse_calc_sum(&mut self) -> Option<Calc> {
+        let mut sum = self.parse_calc_product()?;
+        loop {
+            let operator = match self.peek() {
+                Some(c @ ('+' | '-')) => c,
+                _ => return Some(sum),
+            };
+            self.consume_char();
+            let rhs = Box::new(self.parse_calc_product()?);
+            sum = match operator {
+                '+' => Calc::Sum(Box::new(sum), rhs),
+                _ => Calc::Difference(Box::new(sum), rhs),
+            };
+        }
+    }
+
+    /// <product> := <calc-value> (("*" | "/") <calc-value>)*
+    fn parse_calc_product(&mut self) -> Option<Calc> {
+        let mut product = self.parse_calc_value()?;
+        loop {
+            let operator = match self.peek() {
+                Some(c @ ('*' | '/')) => c,
+                _ => return Some(product),
+            };
+            self.consume_char();
+            let rhs = Box::new(self.parse_calc_value()?);
+            product = match operator {
+                '*' => Calc::Product(Box::new(product), rhs),
+                _ => Calc::Quotient(Box::new(product), rhs),
+            };
+        }
+    }
+
+    /// Parse an operand, skipping surrounding whitespace.
+    ///
+    /// <calc-value> := number | <length> | "(" <sum> ")" | <math-function>
+    fn parse_calc_value(&mut self) -> Option<Calc> {
+        self.consume_whitespace();
+        let value = if self.peek() == Some('(') {
+            self.consume_char();
+            let sum = self.parse_calc_sum()?;
+            match self.next_token()? {
+                Token::CloseParen => sum,
+                _ => return None,
+            }
+        } else if self.starts_number() {
+            Calc::Leaf(self.parse_length()?)
+        } else {
+            match self.next_token()? {
+                Token::Function(name) => self.parse_math_function(&name)?,
+                _ => return None,
+            }
+        };
+        self.consume_whitespace();
+        Some(value)
+    }
+}
+
+impl Calc {
+    /// Return the value of the expression in px. Numbers are unitless, and
+    /// percentages are relative to `percent_base`.
+    pub fn to_px(&self, context: &LengthContext, percent_base: f32) -> f32 {
+        let px = |calc: &Calc| calc.to_px(context, percent_base);
+        match self {
+            Calc::Leaf(Value::Number(n)) => *n,
+            Calc::Leaf(value) => value.to_px(context, percent_base),
+            Calc::Sum(a, b) => px(a) + px(b),
+            Calc::Difference(a, b) => px(a) - px(b),
+            Calc::Product(a, b) => px(a) * px(b),
+            Calc::Quotient(a, b) => px(a) / px(b),
+            Calc::Min(args) => args.iter().map(px).fold(f32::INFINITY, f32::min),
+            Calc::Max(args) => args.iter().map(px).fold(f32::NEG_INFINITY, f32::max),
+            Calc::Clamp(min, value, max) => px(value).min(px(max)).max(px(min)),
+        }
+    }
+
+    /// Return true if the expression has a length whose unit passes `test`.
+    pub fn has_unit(&self, test: &dyn Fn(Unit) -> bool) -> bool {
+        match self {
+            Calc::Leaf(Value::Length(_, unit)) => test(*unit),
+            Calc::Leaf(_) => false,
+            Calc::Sum(a, b)
+            | Calc::Difference(a, b)
+            | Calc::Product(a, b)
+            | Calc::Quotient(a, b) => a.has_unit(test) || b.has_unit(test),
+            Calc::Min(args) | Calc::Max(args) => args.iter().any(|arg| arg.has_unit(test)),
+            Calc::Clamp(min, value, max) => [min, value, max].iter().any(|arg| arg.has_unit(test)),
+        }
+    }
+
+    /// Resolve the lengths that don't depend on layout to px, i.e. all but
+    /// percentages and viewport units, and fold the operations whose operands
+    /// are then of the same unit.
+    /// spec: https://www.w3.org/TR/css-values-4/#calc-simplification
+    pub fn simplify(&self, context: &LengthContext) -> Calc {
+        let simplify = |calc: &Calc| calc.simplify(context);
+        let leaf = |n: f32, unit: Option<Unit>| {
+            Calc::Leaf(match unit {
+                Some(unit) => Value::Length(n, unit),
+                None => Value::Number(n),
+            })
+        };
+        match self {
+            Calc::Leaf(Value::Length(f, unit))
+                if !matches!(
+                    unit,
+                    Unit::Percent | Unit::Vw | Unit::Vh | Unit::Vmin | Unit::Vmax
+                ) =>
+            {
+                Calc::Leaf(Value::Length(f * unit.px_per_unit(context), Unit::Px))
+            }
+            Calc::Leaf(value) => Calc::Leaf(value.clone()),
+            Calc::Sum(a, b)
+            | Calc::Difference(a, b)
+            | Calc::Product(a, b)
+            | Calc::Quotient(a, b) => {
+                let (a, b) = (simplify(a), simplify(b));
+                let folded = match (self, a.as_leaf(), b.as_leaf()) {
+                    (Calc::Sum(..), Some((x, u)), Some((y, v))) if u == v => Some(leaf(x + y, u)),
+                    (Calc::Difference(..), Some((x, u)), Some((y, v))) if u == v => {
+                        Some(leaf(x - y, u))
+                    }
+                    (Calc::Product(..), Some((x, None)), Some((y, u)))
+                    | (Calc::Product(..), Some((y, u)), Some((x, None))) => Some(leaf(x * y, u)),
+                    (Calc::Quotient(..), Some((x, u)), Some((y, None))) => Some(leaf(x / y, u)),
+                    _ => None,
+                };
+                folded.unwrap_or_else(|| {
+                    let (a, b) = (Box::new(a), Box::new(b));
+                    match self {
+                        Calc::Sum(..) => Calc::Sum(a, b),
+                        Calc::Difference(..) => Calc::Difference(a, b),
+                        Calc::Product(..) => Calc::Product(a, b),
+                        _ => Calc::Quotient(a, b),
+                    }
+                })
+            }
+            Calc::Min(args) | Calc::Max(args) => {
+                let args: Vec<Calc> = args.iter().map(simplify).collect();
+                match (self, same_unit(&args)) {
+                    (Calc::Min(_), Some((values, unit))) => {
+                        leaf(values.into_iter().fold(f32::INFINITY, f32::min), unit)
+                    }
+                    (_, Some((values, unit))) => {
+                        leaf(values.into_iter().fold(f32::NEG_INFINITY, f32::max), unit)
+                    }
+                    (Calc::Min(_), None) => Calc::Min(args),
+                    (_, None) => Calc::Max(args),
+                }
+            }
+            Calc::Clamp(min, value, max) => {
+                let args = [simplify(min), simplify(value), simplify(max)];
+                match same_unit(&args) {
+                    Some((values, unit)) => leaf(values[1].min(values[2]).max(values[0]), unit),
+                    None => {
+                        let [min, value, max] = args.map(Box::new);
+                        Calc::Clamp(min, value, max)
+                    }
+                }
+            }
+        }
+    }
+
+    /// The number and unit of a leaf, where numbers have no unit.
+    fn as_leaf(&self) -> Option<(f32, Option<Unit>)> {
+        match *self {
+            Calc::Leaf(Value::Number(n)) => Some((n, None)),
+            Calc::Leaf(Value::Length(f, unit)) => Some((f, Some(unit))),
+            _ => None,
+        }
+    }
+}
+
+/// If every calculation in `args` is a leaf of the same unit, return their
+/// numbers and the unit.
+fn same_unit(args: &[Calc]) -> Option<(Vec<f32>, Option<Unit>)> {
+    let unit = args.first()?.as_leaf()?.1;
+    args.iter()
+        .map(|arg| match arg.as_leaf() {
+            Some((n, u)) if u == unit => Some(n),
+            _ => None,
+        })
+        .collect::<Option<Vec<f32>>>()
+        .map(|values| (values, unit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Calc {
+        let mut parser = Parser::new(input.to_string());
+        let Value::Calc(calc) = parser.parse_keyword().unwrap() else {
+            panic!("Expected a math function: {}", input);
+        };
+        *calc
+    }
+
+    fn px(n: f32) -> Box<Calc> {
+        Box::new(Calc::Leaf(Value::Length(n, Unit::Px)))
+    }
+
+    #[test]
+    fn parse_calc() {
+        assert_eq!(
+            parse("calc(1px + 2px * 3 - (4px - -5px) / 2);"),
+            Calc::Difference(
+                Box::new(Calc::Sum(
+                    px(1.0),
+                    Box::new(Calc::Product(
+                        px(2.0),
+                        Box::new(Calc::Leaf(Value::Number(3.0)))
+                    )),
+                )),
+                Box::new(Calc::Quotient(
+                    Box::new(Calc::Difference(px(4.0), px(-5.0))),
+                    Box::new(Calc::Leaf(Value::Number(2.0))),
+                )),
+            ),
+        );
+        assert_eq!(
+            parse("CLAMP(1px, min(2px,3px), 4px);"),
+            Calc::Clamp(
+                px(1.0),
+                Box::new(Calc::Min(vec![*px(2.0), *px(3.0)])),
+                px(4.0)
+            ),
+        );
+    }
+
+    #[test]
+    fn to_px() {
+        let context = LengthContext {
+            font_size: 10.0,
+            ..LengthContext::new(800.0, 600.0)
+        };
+        let to_px = |input| parse(input).to_px(&context, 200.0);
+        assert_eq!(to_px("calc(100% - 2em);"), 180.0);
+        assert_eq!(to_px("calc((1px + 2px) * 3);"), 9.0);
+        assert_eq!(to_px("min(50%, 10vw, 90px);"), 80.0);
+        assert_eq!(to_px("max(1em, 5px);"), 10.0);
+        assert_eq!(to_px("clamp(1rem, 2.5vw, 3rem);"), 20.0);
+        assert_eq!(to_px("clamp(20px, 1px, 30px);"), 20.0);
+    }
+
+    #[test]
+    fn simplify() {
+        let context = LengthContext {
+            font_size: 10.0,
+            ..LengthContext::new(0.0, 0.0)
+        };
+        let simplify = |input| parse(input).simplify(&context);
+        assert_eq!(simplify("calc(1em + 2px * 3);"), *px(16.0));
+        assert_eq!(simplify("calc(1in / 2);"), *px(48.0));
+        assert_eq!(simplify("max(1em, 5px, 2px);"), *px(10.0));
+        assert_eq!(
+            simplify("calc(100% - 1em);"),
+            Calc::Difference(
+                Box::new(Calc::Leaf(Value::Length(100.0, Unit::Percent))),
+                px(10.0)
+            ),
+        );
+        assert_eq!(
+            simplify("clamp(1em, 2vw, 3em);"),
+            Calc::Clamp(
+                px(10.0),
+                Box::new(Calc::Leaf(Value::Length(2.0, Unit::Vw))),
+                px(30.0)
+            ),
+        );
+    }
+}
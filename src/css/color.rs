@@ -0,0 +1,536 @@
+//! CSS color syntax: hex colors, color functions and named colors.
+//! spec: https://www.w3.org/TR/css-color-4/
+
+use super::{Color, Parser, Token};
+
+/// A component of a color function, e.g. the `50%` of `hsl(120 50% 50%)`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Component {
+    /// A number, or an angle in degrees.
+    Number(f32),
+    Percentage(f32),
+    /// The `none` keyword, a missing component.
+    None,
+}
+
+impl Component {
+    /// Return the value of the component, mapping `100%` to `percent_base`.
+    /// A missing component is 0.
+    fn resolve(self, percent_base: f32) -> f32 {
+        match self {
+            Component::Number(n) => n,
+            Component::Percentage(p) => p / 100.0 * percent_base,
+            Component::None => 0.0,
+        }
+    }
+}
+
+impl Color {
+    /// Create a color from sRGB components in the range [0, 1], clamping
+    /// out-of-gamut values.
+    fn from_srgb(r: f32, g: f32, b: f32, alpha: f32) -> Color {
+        let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color {
+            r: to_u8(r),
+            g: to_u8(g),
+            b: to_u8(b),
+            a: to_u8(alpha),
+        }
+    }
+}
+
+impl Parser {
+    /// Parse a hex color, e.g. `#ab3`, `#ab38` `#aabb33` or `#aabb3380`, or
+    /// return `None` if it is invalid.
+    /// spec: https://www.w3.org/TR/css-color-4/#hex-notation
+    pub(super) fn parse_hex_color(&mut self) -> Option<Color> {
+        let digits = match self.next_token()? {
+            Token::Hash(digits, _) if digits.chars().all(|c| c.is_ascii_hexdigit()) => digits,
+            _ => return None,
+        };
+        let digits: Vec<u8> = match digits.len() {
+            // Short forms duplicate each digit, e.g. `#ab3` is `#aabb33`.
+            3 | 4 => digits.chars().flat_map(|c| [c, c]).collect::<String>(),
+            6 | 8 => digits,
+            _ => return None,
+        }
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+        .collect();
+        Some(Color {
+            r: digits[0],
+            g: digits[1],
+            b: digits[2],
+            a: digits.get(3).copied().unwrap_or(255),
+        })
+    }
+
+    /// Parse the arguments of a color function `name`, after the opening
+    /// parenthesis, e.g. `0 128 255 / 50%)`. Both the legacy comma-separated
+    /// syntax and the modern space-separated one are accepted. Return `None`
+    /// for invalid arguments and unsupported functions.
+    /// spec: https://www.w3.org/TR/css-color-4/#color-syntax
+    pub(super) fn parse_color_function(&mut self, name: &str) -> Option<Color> {
+        let mut components = vec![];
+        let mut alpha = None;
+        loop {
+            self.consume_whitespace();
+            match self.peek()? {
+                ')' => {
+                    self.consume_char();
+                    break;
+                }
+                ',' => {
+                    self.consume_char();
+                }
+                '/' => {
+                    self.consume_char();
+                    self.consume_whitespace();
+                    alpha = Some(self.parse_color_component()?);
+                }
+                _ => components.push(self.parse_color_component()?),
+            }
+        }
+        // In the legacy syntax, e.g. `rgba(0, 0, 0, 0.5)`, alpha is the 4th
+        // component.
+        if components.len() == 4 && alpha.is_none() {
+            alpha = components.pop();
+        }
+        let [c0, c1, c2] = components[..] else {
+            return None;
+        };
+        let alpha = alpha.map_or(1.0, |a| a.resolve(1.0));
+
+        let [r, g, b] = match &*name.to_ascii_lowercase() {
+            "rgb" | "rgba" => [
+                c0.resolve(255.0) / 255.0,
+                c1.resolve(255.0) / 255.0,
+                c2.resolve(255.0) / 255.0,
+            ],
+            // Saturation, lightness, whiteness and blackness are percentages,
+            // but plain numbers are also allowed in the modern syntax.
+            "hsl" | "hsla" => hsl_to_srgb(
+                c0.resolve(0.0),
+                c1.resolve(100.0) / 100.0,
+                c2.resolve(100.0) / 100.0,
+            ),
+            "hwb" => hwb_to_srgb(
+                c0.resolve(0.0),
+                c1.resolve(100.0) / 100.0,
+                c2.resolve(100.0) / 100.0,
+            ),
+            "lab" => lab_to_srgb(c0.resolve(100.0), c1.resolve(125.0), c2.resolve(125.0)),
+            "lch" => {
+                let [l, a, b] = lch_to_lab(c0.resolve(100.0), c1.resolve(150.0), c2.resolve(0.0));
+                lab_to_srgb(l, a, b)
+            }
+            "oklab" => oklab_to_srgb(c0.resolve(1.0), c1.resolve(0.4), c2.resolve(0.4)),
+            "oklch" => {
+                let [l, a, b] = lch_to_lab(c0.resolve(1.0), c1.resolve(0.4), c2.resolve(0.0));
+                oklab_to_srgb(l, a, b)
+            }
+            _ => return None,
+        };
+        Some(Color::from_srgb(r, g, b, alpha))
+    }
+
+    /// Parse a component of a color function: a number, a percentage, an
+    /// angle or `none`.
+    fn parse_color_component(&mut self) -> Option<Component> {
+        let (num, unit) = match self.next_token()? {
+            Token::Number(num) => (num, String::new()),
+            Token::Percentage(num) => (num, "%".to_string()),
+            Token::Dimension(num, unit) => (num, unit),
+            Token::Ident(ident) if ident.eq_ignore_ascii_case("none") => {
+                return Some(Component::None);
+            }
+            _ => return None,
+        };
+        // Angles are normalized to degrees.
+        // spec: https://www.w3.org/TR/css-values-4/#angles
+        Some(match &*unit.to_ascii_lowercase() {
+            "" | "deg" => Component::Number(num),
+            "%" => Component::Percentage(num),
+            "rad" => Component::Number(num.to_degrees()),
+            "grad" => Component::Number(num * 0.9),
+            "turn" => Component::Number(num * 360.0),
+            _ => return None,
+        })
+    }
+}
+
+/// spec: https://www.w3.org/TR/css-color-4/#hsl-to-rgb
+fn hsl_to_srgb(hue: f32, saturation: f32, lightness: f32) -> [f32; 3] {
+    let hue = hue.rem_euclid(360.0);
+    let f = |n: f32| {
+        let k = (n + hue / 30.0) % 12.0;
+        let a = saturation * lightness.min(1.0 - lightness);
+        lightness - a * (k - 3.0).min(9.0 - k).clamp(-1.0, 1.0)
+    };
+    [f(0.0), f(8.0), f(4.0)]
+}
+
+/// spec: https://www.w3.org/TR/css-color-4/#hwb-to-rgb
+fn hwb_to_srgb(hue: f32, white: f32, black: f32) -> [f32; 3] {
+    if white + black >= 1.0 {
+        let gray = white / (white + black);
+        return [gray; 3];
+    }
+    hsl_to_srgb(hue, 1.0, 0.5).map(|v| v * (1.0 - white - black) + white)
+}
+
+/// Convert polar coordinates (lightness, chroma and hue in degrees) to
+/// rectangular ones, for both `lch()` and `oklch()`.
+fn lch_to_lab(lightness: f32, chroma: f32, hue: f32) -> [f32; 3] {
+    let hue = hue.to_radians();
+    [lightness, chroma * hue.cos(), chroma * hue.sin()]
+}
+
+/// Convert CIE Lab (with the D50 white point) to sRGB.
+/// spec: https://www.w3.org/TR/css-color-4/#color-conversion-code
+fn lab_to_srgb(l: f32, a: f32, b: f32) -> [f32; 3] {
+    const KAPPA: f32 = 24389.0 / 27.0;
+    const EPSILON: f32 = 216.0 / 24389.0;
+    const D50: [f32; 3] = [0.3457 / 0.3585, 1.0, (1.0 - 0.3457 - 0.3585) / 0.3585];
+
+    let f1 = (l + 16.0) / 116.0;
+    let f0 = a / 500.0 + f1;
+    let f2 = f1 - b / 200.0;
+    let x = if f0.powi(3) > EPSILON {
+        f0.powi(3)
+    } else {
+        (116.0 * f0 - 16.0) / KAPPA
+    };
+    let y = if l > KAPPA * EPSILON {
+        f1.powi(3)
+    } else {
+        l / KAPPA
+    };
+    let z = if f2.powi(3) > EPSILON {
+        f2.powi(3)
+    } else {
+        (116.0 * f2 - 16.0) / KAPPA
+    };
+    let xyz_d50 = [x * D50[0], y * D50[1], z * D50[2]];
+
+    // Bradford chromatic adaptation from D50 to D65.
+    let xyz_d65 = multiply(
+        [
+            [0.955_473_4, -0.023_098_455, 0.063_259_24],
+            [-0.028_369_71, 1.009_995_4, 0.021_041_441],
+            [0.012_314_015, -0.020_507_65, 1.330_365_9],
+        ],
+        xyz_d50,
+    );
+    let linear = multiply(
+        [
+            [3.240_97, -1.537_383_2, -0.498_610_76],
+            [-0.969_243_65, 1.875_967_5, 0.041_555_06],
+            [0.055_630_08, -0.203_976_96, 1.056_971_5],
+        ],
+        xyz_d65,
+    );
+    linear.map(gamma_encode)
+}
+
+/// Convert Oklab to sRGB.
+/// spec: https://www.w3.org/TR/css-color-4/#color-conversion-code
+fn oklab_to_srgb(l: f32, a: f32, b: f32) -> [f32; 3] {
+    let lms = multiply(
+        [
+            [1.0, 0.396_337_78, 0.215_803_76],
+            [1.0, -0.105_561_346, -0.063_854_17],
+            [1.0, -0.089_484_18, -1.291_485_5],
+        ],
+        [l, a, b],
+    )
+    .map(|v| v.powi(3));
+    let linear = multiply(
+        [
+            [4.076_741_7, -3.307_711_6, 0.230_969_94],
+            [-1.268_438, 2.609_757_4, -0.341_319_38],
+            [-0.004_196_086_3, -0.703_418_6, 1.707_614_7],
+        ],
+        lms,
+    );
+    linear.map(gamma_encode)
+}
+
+/// Apply the sRGB transfer function to a linear-light component.
+fn gamma_encode(v: f32) -> f32 {
+    if v.abs() > 0.003_130_8 {
+        v.signum() * (1.055 * v.abs().powf(1.0 / 2.4) - 0.055)
+    } else {
+        12.92 * v
+    }
+}
+
+fn multiply(m: [[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+/// Look up a named color, case-insensitively. `transparent` is included.
+/// spec: https://www.w3.org/TR/css-color-4/#named-colors
+pub(super) fn named_color(name: &str) -> Option<Color> {
+    let name = name.to_ascii_lowercase();
+    if name == "transparent" {
+        return Some(Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0,
+        });
+    }
+    let index = NAMED_COLORS
+        .binary_search_by_key(&&*name, |&(n, _)| n)
+        .ok()?;
+    let rgb = NAMED_COLORS[index].1;
+    Some(Color {
+        r: (rgb >> 16) as u8,
+        g: (rgb >> 8) as u8,
+        b: rgb as u8,
+        a: 255,
+    })
+}
+
+/// Named colors, sorted by name.
+const NAMED_COLORS: [(&str, u32); 148] = [
+    ("aliceblue", 0xf0f8ff),
+    ("antiquewhite", 0xfaebd7),
+    ("aqua", 0x00ffff),
+    ("aquamarine", 0x7fffd4),
+    ("azure", 0xf0ffff),
+    ("beige", 0xf5f5dc),
+    ("bisque", 0xffe4c4),
+    ("black", 0x000000),
+    ("blanchedalmond", 0xffebcd),
+    ("blue", 0x0000ff),
+    ("blueviolet", 0x8a2be2),
+    ("brown", 0xa52a2a),
+    ("burlywood", 0xdeb887),
+    ("cadetblue", 0x5f9ea0),
+    ("chartreuse", 0x7fff00),
+    ("chocolate", 0xd2691e),
+    ("coral", 0xff7f50),
+    ("cornflowerblue", 0x6495ed),
+    ("cornsilk", 0xfff8dc),
+    ("crimson", 0xdc143c),
+    ("cyan", 0x00ffff),
+    ("darkblue", 0x00008b),
+    ("darkcyan", 0x008b8b),
+    ("darkgoldenrod", 0xb8860b),
+    ("darkgray", 0xa9a9a9),
+    ("darkgreen", 0x006400),
+    ("darkgrey", 0xa9a9a9),
+    ("darkkhaki", 0xbdb76b),
+    ("darkmagenta", 0x8b008b),
+    ("darkolivegreen", 0x556b2f),
+    ("darkorange", 0xff8c00),
+    ("darkorchid", 0x9932cc),
+    ("darkred", 0x8b0000),
+    ("darksalmon", 0xe9967a),
+    ("darkseagreen", 0x8fbc8f),
+    ("darkslateblue", 0x483d8b),
+    ("darkslategray", 0x2f4f4f),
+    ("darkslategrey", 0x2f4f4f),
+    ("darkturquoise", 0x00ced1),
+    ("darkviolet", 0x9400d3),
+    ("deeppink", 0xff1493),
+    ("deepskyblue", 0x00bfff),
+    ("dimgray", 0x696969),
+    ("dimgrey", 0x696969),
+    ("dodgerblue", 0x1e90ff),
+    ("firebrick", 0xb22222),
+    ("floralwhite", 0xfffaf0),
+    ("forestgreen", 0x228b22),
+    ("fuchsia", 0xff00ff),
+    ("gainsboro", 0xdcdcdc),
+    ("ghostwhite", 0xf8f8ff),
+    ("gold", 0xffd700),
+    ("goldenrod", 0xdaa520),
+    ("gray", 0x808080),
+    ("green", 0x008000),
+    ("greenyellow", 0xadff2f),
+    ("grey", 0x808080),
+    ("honeydew", 0xf0fff0),
+    ("hotpink", 0xff69b4),
+    ("indianred", 0xcd5c5c),
+    ("indigo", 0x4b0082),
+    ("ivory", 0xfffff0),
+    ("khaki", 0xf0e68c),
+    ("lavender", 0xe6e6fa),
+    ("lavenderblush", 0xfff0f5),
+    ("lawngreen", 0x7cfc00),
+    ("lemonchiffon", 0xfffacd),
+    ("lightblue", 0xadd8e6),
+    ("lightcoral", 0xf08080),
+    ("lightcyan", 0xe0ffff),
+    ("lightgoldenrodyellow", 0xfafad2),
+    ("lightgray", 0xd3d3d3),
+    ("lightgreen", 0x90ee90),
+    ("lightgrey", 0xd3d3d3),
+    ("lightpink", 0xffb6c1),
+    ("lightsalmon", 0xffa07a),
+    ("lightseagreen", 0x20b2aa),
+    ("lightskyblue", 0x87cefa),
+    ("lightslategray", 0x778899),
+    ("lightslategrey", 0x778899),
+    ("lightsteelblue", 0xb0c4de),
+    ("lightyellow", 0xffffe0),
+    ("lime", 0x00ff00),
+    ("limegreen", 0x32cd32),
+    ("linen", 0xfaf0e6),
+    ("magenta", 0xff00ff),
+    ("maroon", 0x800000),
+    ("mediumaquamarine", 0x66cdaa),
+    ("mediumblue", 0x0000cd),
+    ("mediumorchid", 0xba55d3),
+    ("mediumpurple", 0x9370db),
+    ("mediumseagreen", 0x3cb371),
+    ("mediumslateblue", 0x7b68ee),
+    ("mediumspringgreen", 0x00fa9a),
+    ("mediumturquoise", 0x48d1cc),
+    ("mediumvioletred", 0xc71585),
+    ("midnightblue", 0x191970),
+    ("mintcream", 0xf5fffa),
+    ("mistyrose", 0xffe4e1),
+    ("moccasin", 0xffe4b5),
+    ("navajowhite", 0xffdead),
+    ("navy", 0x000080),
+    ("oldlace", 0xfdf5e6),
+    ("olive", 0x808000),
+    ("olivedrab", 0x6b8e23),
+    ("orange", 0xffa500),
+    ("orangered", 0xff4500),
+    ("orchid", 0xda70d6),
+    ("palegoldenrod", 0xeee8aa),
+    ("palegreen", 0x98fb98),
+    ("paleturquoise", 0xafeeee),
+    ("palevioletred", 0xdb7093),
+    ("papayawhip", 0xffefd5),
+    ("peachpuff", 0xffdab9),
+    ("peru", 0xcd853f),
+    ("pink", 0xffc0cb),
+    ("plum", 0xdda0dd),
+    ("powderblue", 0xb0e0e6),
+    ("purple", 0x800080),
+    ("rebeccapurple", 0x663399),
+    ("red", 0xff0000),
+    ("rosybrown", 0xbc8f8f),
+    ("royalblue", 0x4169e1),
+    ("saddlebrown", 0x8b4513),
+    ("salmon", 0xfa8072),
+    ("sandybrown", 0xf4a460),
+    ("seagreen", 0x2e8b57),
+    ("seashell", 0xfff5ee),
+    ("sienna", 0xa0522d),
+    ("silver", 0xc0c0c0),
+    ("skyblue", 0x87ceeb),
+    ("slateblue", 0x6a5acd),
+    ("slategray", 0x708090),
+    ("slategrey", 0x708090),
+    ("snow", 0xfffafa),
+    ("springgreen", 0x00ff7f),
+    ("steelblue", 0x4682b4),
+    ("tan", 0xd2b48c),
+    ("teal", 0x008080),
+    ("thistle", 0xd8bfd8),
+    ("tomato", 0xff6347),
+    ("turquoise", 0x40e0d0),
+    ("violet", 0xee82ee),
+    ("wheat", 0xf5deb3),
+    ("white", 0xffffff),
+    ("whitesmoke", 0xf5f5f5),
+    ("yellow", 0xffff00),
+    ("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_function(input: &str) -> Color {
+        let mut parser = Parser::new(input.to_string());
+        let name = parser.parse_identifier();
+        assert!(parser.consume_char() == '(');
+        let color = parser.parse_color_function(&name).unwrap();
+        assert!(parser.eof());
+        color
+    }
+
+    fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color { r, g, b, a }
+    }
+
+    /// Assert that each channel is off by at most 1, to allow rounding errors
+    /// of the conversions.
+    fn assert_close(actual: Color, expected: Color) {
+        let channels = |c: Color| [c.r, c.g, c.b, c.a];
+        for (a, e) in channels(actual).iter().zip(channels(expected)) {
+            assert!(a.abs_diff(e) <= 1, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn hex_colors() {
+        for (input, expected) in [
+            ("#f80", rgba(255, 136, 0, 255)),
+            ("#f808", rgba(255, 136, 0, 136)),
+            ("#FF8800", rgba(255, 136, 0, 255)),
+            ("#ff880080", rgba(255, 136, 0, 128)),
+        ] {
+            let mut parser = Parser::new(input.to_string());
+            assert_eq!(parser.parse_hex_color(), Some(expected), "{}", input);
+        }
+    }
+
+    #[test]
+    fn rgb() {
+        assert_eq!(parse_function("rgb(255, 0, 128)"), rgba(255, 0, 128, 255));
+        assert_eq!(
+            parse_function("rgba(255, 0, 128, 0.5)"),
+            rgba(255, 0, 128, 128)
+        );
+        assert_eq!(
+            parse_function("rgb(100% 0% 50% / 25%)"),
+            rgba(255, 0, 128, 64)
+        );
+        assert_eq!(parse_function("rgb(300 -5 none)"), rgba(255, 0, 0, 255));
+    }
+
+    #[test]
+    fn hsl_and_hwb() {
+        assert_eq!(parse_function("hsl(0, 100%, 50%)"), rgba(255, 0, 0, 255));
+        assert_eq!(parse_function("hsl(120deg 100% 25%)"), rgba(0, 128, 0, 255));
+        assert_eq!(
+            parse_function("hsla(0.5turn, 100%, 50%, 0)"),
+            rgba(0, 255, 255, 0)
+        );
+        assert_eq!(parse_function("hsl(-120 100 50)"), rgba(0, 0, 255, 255));
+        assert_eq!(parse_function("hwb(0 0% 0%)"), rgba(255, 0, 0, 255));
+        assert_eq!(parse_function("hwb(0 60% 60%)"), rgba(128, 128, 128, 255));
+    }
+
+    #[test]
+    fn lab_and_oklab() {
+        // sRGB red in each color space.
+        let red = rgba(255, 0, 0, 255);
+        assert_close(parse_function("lab(54.29% 80.8 69.89)"), red);
+        assert_close(parse_function("lch(54.29 106.84 40.85deg)"), red);
+        assert_close(parse_function("oklab(0.628 0.2249 0.1258)"), red);
+        assert_close(parse_function("oklch(62.8% 0.2577 29.23 / 1)"), red);
+
+        assert_close(parse_function("lab(100 0 0)"), rgba(255, 255, 255, 255));
+        assert_close(parse_function("oklch(0 0 none)"), rgba(0, 0, 0, 255));
+    }
+
+    #[test]
+    fn named_colors() {
+        assert!(NAMED_COLORS.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(named_color("rebeccapurple"), Some(rgba(102, 51, 153, 255)));
+        assert_eq!(named_color("DarkSlateGrey"), Some(rgba(47, 79, 79, 255)));
+        assert_eq!(named_color("transparent"), Some(rgba(0, 0, 0, 0)));
+        assert_eq!(named_color("auto"), None);
+    }
+}
@@ -0,0 +1,321 @@
+//! `@import` rules, and the loading of imported stylesheets.
+//! spec: https://www.w3.org/TR/css-cascade-4/#at-import
+
+use super::{CssRule, Parser, Stylesheet, Token};
+use std::{fmt, fs, io};
+
+/// Fetches the source of stylesheets by URL.
+pub trait Loader {
+    /// Return the source of the stylesheet at `url`.
+    fn load(&self, url: &str) -> io::Result<String>;
+}
+
+impl<F: Fn(&str) -> io::Result<String>> Loader for F {
+    fn load(&self, url: &str) -> io::Result<String> {
+        self(url)
+    }
+}
+
+/// Loads stylesheets from the filesystem, taking URLs as paths.
+pub struct FileLoader;
+
+impl Loader for FileLoader {
+    fn load(&self, url: &str) -> io::Result<String> {
+        fs::read_to_string(url)
+    }
+}
+
+/// An `@import` that couldn't be loaded. Its rules are left empty.
+#[derive(Debug)]
+pub enum ImportError {
+    /// The stylesheet at the URL failed to load.
+    Load(String, io::Error),
+    /// The URLs of stylesheets that import each other, in order, ending with
+    /// the one that would be imported again.
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Load(url, error) => write!(f, "Failed to import {}: {}", url, error),
+            ImportError::Cycle(urls) => write!(f, "Import cycle: {}", urls.join(" -> ")),
+        }
+    }
+}
+
+impl Stylesheet {
+    /// Load the stylesheets imported by this one, which is at `url`, and
+    /// those they import in turn, with `loader`. Relative URLs are resolved
+    /// against the URL of the importing stylesheet.
+    ///
+    /// Imports that fail to load or would form a cycle are skipped, and
+    /// returned as errors.
+    pub fn load_imports(&mut self, url: &str, loader: &dyn Loader) -> Vec<ImportError> {
+        let mut errors = vec![];
+        load_imports(
+            &mut self.rules,
+            &mut vec![url.to_string()],
+            loader,
+            &mut errors,
+        );
+        errors
+    }
+}
+
+/// Load the imports in `rules`, where `stack` holds the URLs of the
+/// stylesheets being loaded, each imported by the previous one.
+fn load_imports(
+    rules: &mut [CssRule],
+    stack: &mut Vec<String>,
+    loader: &dyn Loader,
+    errors: &mut Vec<ImportError>,
+) {
+    for rule in rules {
+        let CssRule::Import(url, _, imported) = rule else {
+            continue;
+        };
+        let url = resolve_url(stack.last().unwrap(), url);
+        if let Some(i) = stack.iter().position(|u| *u == url) {
+            let mut cycle = stack[i..].to_vec();
+            cycle.push(url);
+            errors.push(ImportError::Cycle(cycle));
+            continue;
+        }
+        match loader.load(&url) {
+            Ok(source) => {
+                *imported = super::parse(source).rules;
+                stack.push(url);
+                load_imports(imported, stack, loader, errors);
+                stack.pop();
+            }
+            Err(error) => errors.push(ImportError::Load(url, error)),
+        }
+    }
+}
+
+/// Resolve `url` against `base`, normalizing `.` and `..` segments so that
+/// the same stylesheet always has the same URL. URLs with a scheme and
+/// absolute paths are left as they are.
+fn resolve_url(base: &str, url: &str) -> String {
+    if url.starts_with('/') || url.contains("://") {
+        return url.to_string();
+    }
+    let directory = base.rfind('/').map_or("", |i| &base[..=i]);
+    let path = format!("{}{}", directory, url);
+    let mut segments: Vec<&str> = vec![];
+    for segment in path.split('/') {
+        match segment {
+            "." => {}
+            ".." if segments.last().is_some_and(|&s| s != "..") => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+impl Parser {
+    /// Parse an `@import` rule, after its name. Return `None` if it has no
+    /// URL, or a block instead of its `;`, skipping it.
+    ///
+    /// <import> := (<string> | "url(" (<string> | <unquoted-url>) ")") <media-query-list> ";"
+    pub(super) fn parse_import(&mut self) -> Option<CssRule> {
+        self.consume_whitespace();
+        let Some(url) = self.parse_url() else {
+            self.skip_at_rule();
+            return None;
+        };
+        let queries = self.parse_media_query_list();
+        match self.peek() {
+            None => {}
+            Some(';') => {
+                self.consume_char();
+            }
+            Some(_) => {
+                self.skip_at_rule();
+                return None;
+            }
+        }
+        Some(CssRule::Import(url, queries, vec![]))
+    }
+
+    /// Parse a URL given as a string or with `url()`, or return `None`.
+    fn parse_url(&mut self) -> Option<String> {
+        let start = self.pos;
+        match self.next_token() {
+            Some(Token::String(url) | Token::Url(url)) => Some(url),
+            Some(Token::Function(name)) if name.eq_ignore_ascii_case("url") => {
+                self.parse_url_arguments()
+            }
+            _ => {
+                self.pos = start;
+                None
+            }
+        }
+    }
+
+    /// Parse the quoted URL of a `url(` function, after its `(`, up to and
+    /// including the `)`. An unquoted URL is a single `Token::Url` instead.
+    pub(super) fn parse_url_arguments(&mut self) -> Option<String> {
+        self.consume_whitespace();
+        let url = self.parse_string()?;
+        self.consume_whitespace();
+        match self.next_token()? {
+            Token::CloseParen => Some(url),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{parse, Device, Layers, Rule};
+    use std::collections::HashMap;
+
+    /// A loader of the stylesheets in `files`, by URL.
+    fn loader(files: &[(&str, &str)]) -> impl Loader {
+        let files: HashMap<String, String> = files
+            .iter()
+            .map(|&(url, css)| (url.to_string(), css.to_string()))
+            .collect();
+        move |url: &str| {
+            files
+                .get(url)
+                .cloned()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, url))
+        }
+    }
+
+    /// The tag names of the selectors of the style rules that apply.
+    fn tags(stylesheet: &Stylesheet, width: f32) -> Vec<String> {
+        let device = Device::new(width, 600.0);
+        let tag = |(_, rule): (usize, &Rule)| rule.selectors[0].subject().tag_name.clone().unwrap();
+        stylesheet
+            .style_rules(&device, &|_, _| true, &mut Layers::default())
+            .into_iter()
+            .map(tag)
+            .collect()
+    }
+
+    #[test]
+    fn parse_import() {
+        let stylesheet = parse(
+            r#"
+            @import url(a.css);
+            @import url( "b.css" ) screen and (width > 600px);
+            @IMPORT 'c.css' print
+            ;
+            p {}
+            @import "ignored.css";
+            @media screen { @import "ignored.css"; }
+            @import nothing;
+            "#
+            .to_string(),
+        );
+        let imports: Vec<(&str, usize)> = stylesheet
+            .rules
+            .iter()
+            .filter_map(|rule| match rule {
+                CssRule::Import(url, queries, _) => Some((&**url, queries.len())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(imports, [("a.css", 0), ("b.css", 1), ("c.css", 1)]);
+        assert!(matches!(&stylesheet.rules[4], CssRule::Media(_, rules) if rules.is_empty()));
+        assert_eq!(stylesheet.rules.len(), 5);
+    }
+
+    #[test]
+    fn skip_malformed_imports() {
+        let stylesheet = parse(
+            r#"
+            @import "a.css" { }
+            @import url(b.css) screen {}
+            @import "c.css" (width > 1px) div { color: red }
+            @import "d.css";
+            p {}
+            "#
+            .to_string(),
+        );
+        assert!(matches!(
+            &stylesheet.rules[..],
+            [CssRule::Import(url, ..), CssRule::Style(_)] if url == "d.css"
+        ));
+    }
+
+    #[test]
+    fn load_imports() {
+        let mut stylesheet = parse(
+            r#"
+            @import "theme/base.css";
+            @import "theme/wide.css" (width >= 1024px);
+            main {}
+            "#
+            .to_string(),
+        );
+        let loader = loader(&[
+            ("theme/base.css", "@import './reset.css'; h1 {}"),
+            ("theme/reset.css", "@import '../missing.css'; html {}"),
+            ("theme/wide.css", "aside {}"),
+        ]);
+        let errors = stylesheet.load_imports("index.css", &loader);
+
+        // Imported rules come where they are imported, in cascade order.
+        assert_eq!(tags(&stylesheet, 800.0), ["html", "h1", "main"]);
+        assert_eq!(tags(&stylesheet, 1280.0), ["html", "h1", "aside", "main"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "Failed to import missing.css: missing.css"
+        );
+    }
+
+    #[test]
+    fn cycles() {
+        let mut stylesheet = parse("@import 'a.css'; p {}".to_string());
+        let loader = loader(&[
+            ("a.css", "@import 'b.css'; a {}"),
+            ("b.css", "@import './a.css'; @import 'index.css'; b {}"),
+        ]);
+        let errors = stylesheet.load_imports("index.css", &loader);
+
+        assert_eq!(tags(&stylesheet, 800.0), ["b", "a", "p"]);
+        let cycles: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            cycles,
+            [
+                "Import cycle: a.css -> b.css -> a.css",
+                "Import cycle: index.css -> a.css -> b.css -> index.css",
+            ]
+        );
+    }
+
+    #[test]
+    fn file_loader() {
+        let directory = std::env::temp_dir().join(format!("obe-import-{}", std::process::id()));
+        fs::create_dir_all(directory.join("theme")).unwrap();
+        fs::write(directory.join("theme/a.css"), "@import 'b.css'; a {}").unwrap();
+        fs::write(directory.join("theme/b.css"), "b {}").unwrap();
+
+        let mut stylesheet = parse("@import 'theme/a.css';".to_string());
+        let url = format!("{}/index.css", directory.display());
+        let errors = stylesheet.load_imports(&url, &FileLoader);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(tags(&stylesheet, 800.0), ["b", "a"]);
+    }
+
+    #[test]
+    fn resolve_url() {
+        assert_eq!(super::resolve_url("index.css", "a.css"), "a.css");
+        assert_eq!(super::resolve_url("x/y/z.css", "../a.css"), "x/a.css");
+        assert_eq!(super::resolve_url("x/z.css", "./b/../a.css"), "x/a.css");
+        assert_eq!(super::resolve_url("z.css", "../a.css"), "../a.css");
+        assert_eq!(super::resolve_url("/x/z.css", "a.css"), "/x/a.css");
+        assert_eq!(super::resolve_url("x/z.css", "/a.css"), "/a.css");
+    }
+}
@@ -0,0 +1,141 @@
+//! Cascade layers: parsing their names, and ordering them.
+//! spec: https://www.w3.org/TR/css-cascade-5/#layering
+
+use super::Parser;
+
+/// The cascade layers of an origin, as a tree of layers in the order they are
+/// first declared. Layers are identified by their index.
+#[derive(Debug)]
+pub struct Layers {
+    layers: Vec<Layer>,
+}
+
+#[derive(Debug)]
+struct Layer {
+    /// The name of the layer in its parent, `None` if it is anonymous.
+    name: Option<String>,
+    sublayers: Vec<usize>,
+}
+
+impl Default for Layers {
+    fn default() -> Layers {
+        Layers {
+            layers: vec![Layer {
+                name: None,
+                sublayers: vec![],
+            }],
+        }
+    }
+}
+
+impl Layers {
+    /// The implicit outer layer, which holds the rules that aren't in a layer.
+    pub const ROOT: usize = 0;
+
+    /// Return the layer `name` in layer `parent`, declaring it if it is new.
+    /// A dotted name, e.g. `a.b`, names a layer in a layer. Every anonymous
+    /// layer, with no name, is new.
+    pub fn declare(&mut self, parent: usize, name: Option<&str>) -> usize {
+        let Some(name) = name else {
+            return self.add(parent, None);
+        };
+        name.split('.').fold(parent, |parent, name| {
+            let existing = self.layers[parent]
+                .sublayers
+                .iter()
+                .find(|&&layer| self.layers[layer].name.as_deref() == Some(name));
+            match existing {
+                Some(&layer) => layer,
+                None => self.add(parent, Some(name.to_string())),
+            }
+        })
+    }
+
+    fn add(&mut self, parent: usize, name: Option<String>) -> usize {
+        self.layers.push(Layer {
+            name,
+            sublayers: vec![],
+        });
+        let layer = self.layers.len() - 1;
+        self.layers[parent].sublayers.push(layer);
+        layer
+    }
+
+    /// Return the rank of each layer, from the lowest precedence for normal
+    /// declarations. Layers are ranked in the order they are declared, with
+    /// the sublayers of a layer before the rules directly in it. So rules
+    /// that aren't in a layer win over those that are.
+    /// spec: https://www.w3.org/TR/css-cascade-5/#layer-ordering
+    pub fn order(&self) -> Vec<usize> {
+        fn visit(layers: &Layers, layer: usize, ranks: &mut Vec<usize>, next: &mut usize) {
+            for &sublayer in &layers.layers[layer].sublayers {
+                visit(layers, sublayer, ranks, next);
+            }
+            ranks[layer] = *next;
+            *next += 1;
+        }
+        let mut ranks = vec![0; self.layers.len()];
+        visit(self, Layers::ROOT, &mut ranks, &mut 0);
+        ranks
+    }
+}
+
+impl Parser {
+    /// Parse a comma-separated list of layer names, which is empty if there
+    /// are none.
+    ///
+    /// <layer-names> := <layer-name> ("," <layer-name>)*
+    /// <layer-name> := ident ("." ident)*
+    pub(super) fn parse_layer_names(&mut self) -> Vec<String> {
+        let mut names = vec![];
+        loop {
+            self.consume_whitespace();
+            let mut name = self.parse_identifier();
+            while !name.is_empty() && self.peek() == Some('.') {
+                self.consume_char();
+                name.push('.');
+                name += &self.parse_identifier();
+            }
+            if name.is_empty() {
+                break;
+            }
+            names.push(name);
+            self.consume_whitespace();
+            if self.peek() != Some(',') {
+                break;
+            }
+            self.consume_char();
+        }
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_layer_names() {
+        let mut parser = Parser::new("a, b.c ,d {".to_string());
+        assert_eq!(parser.parse_layer_names(), ["a", "b.c", "d"]);
+        assert_eq!(parser.peek(), Some('{'));
+    }
+
+    #[test]
+    fn order() {
+        let mut layers = Layers::default();
+        let reset = layers.declare(Layers::ROOT, Some("reset"));
+        let theme = layers.declare(Layers::ROOT, Some("theme"));
+        let dark = layers.declare(Layers::ROOT, Some("theme.dark"));
+        let anonymous = layers.declare(theme, None);
+        assert_eq!(layers.declare(Layers::ROOT, Some("reset")), reset);
+        assert_eq!(layers.declare(theme, Some("dark")), dark);
+        assert_ne!(layers.declare(theme, None), anonymous);
+
+        let order = layers.order();
+        assert!(order[reset] < order[dark]);
+        assert!(order[dark] < order[anonymous]);
+        assert!(order[anonymous] < order[theme]);
+        assert!(order[theme] < order[Layers::ROOT]);
+    }
+}
@@ -0,0 +1,424 @@
+//! Media queries: parsing them, and evaluating them against a device.
+//! spec: https://www.w3.org/TR/mediaqueries-4/
+
+use super::{
+    ColorScheme, Comparison, Device, LengthContext, MediaCondition, MediaFeature, MediaQuery,
+    Parser, Unit, Value,
+};
+
+/// Return true if a media query list matches `device`, i.e. it is empty or
+/// one of its queries matches.
+pub(super) fn matches(queries: &[MediaQuery], device: &Device) -> bool {
+    queries.is_empty() || queries.iter().any(|query| query.matches(device))
+}
+
+impl Parser {
+    /// Parse a comma-separated media query list, up to the `{` of the block
+    /// it applies to. A malformed query is replaced by `not all`.
+    pub(super) fn parse_media_query_list(&mut self) -> Vec<MediaQuery> {
+        let prelude = self.consume_prelude();
+        if prelude.trim().is_empty() {
+            return vec![];
+        }
+        prelude
+            .split(',')
+            .map(|query| {
+                let mut parser = Parser::new(query.trim().to_string());
+                parser
+                    .parse_media_query()
+                    .filter(|_| parser.eof())
+                    .unwrap_or(MediaQuery {
+                        negated: true,
+                        media_type: Some("all".to_string()),
+                        condition: None,
+                    })
+            })
+            .collect()
+    }
+
+    /// <media-query> := <media-condition>
+    ///                | ["not" | "only"] ident ["and" <media-condition-without-or>]
+    fn parse_media_query(&mut self) -> Option<MediaQuery> {
+        let start = self.pos;
+        if self.peek() == Some('(') || self.consume_keyword("not") && self.peek() == Some('(') {
+            self.pos = start;
+            return Some(MediaQuery {
+                negated: false,
+                media_type: None,
+                condition: Some(self.parse_media_condition(true)?),
+            });
+        }
+
+        self.pos = start;
+        let negated = self.consume_keyword("not");
+        if !negated {
+            self.consume_keyword("only");
+        }
+        let media_type = self.parse_identifier().to_ascii_lowercase();
+        if matches!(&*media_type, "" | "not" | "only" | "and" | "or" | "layer") {
+            return None;
+        }
+        self.consume_whitespace();
+        let condition = match self.consume_keyword("and") {
+            true => Some(self.parse_media_condition(false)?),
+            false => None,
+        };
+        Some(MediaQuery {
+            negated,
+            media_type: Some(media_type),
+            condition,
+        })
+    }
+
+    /// Parse conditions joined with `and`, or with `or` if `allow_or` is set.
+    ///
+    /// <media-condition> := "not" <media-in-parens>
+    ///                    | <media-in-parens> (("and" | "or") <media-in-parens>)*
+    fn parse_media_condition(&mut self, allow_or: bool) -> Option<MediaCondition> {
+        if self.consume_keyword("not") {
+            return Some(MediaCondition::Not(Box::new(self.parse_media_in_parens()?)));
+        }
+        let first = self.parse_media_in_parens()?;
+        let combinator = match () {
+            _ if self.consume_keyword("and") => "and",
+            _ if allow_or && self.consume_keyword("or") => "or",
+            _ => return Some(first),
+        };
+        let mut conditions = vec![first];
+        loop {
+            conditions.push(self.parse_media_in_parens()?);
+            if !self.consume_keyword(combinator) {
+                break;
+            }
+        }
+        Some(match combinator {
+            "and" => MediaCondition::And(conditions),
+            _ => MediaCondition::Or(conditions),
+        })
+    }
+
+    /// <media-in-parens> := "(" (<media-condition> | <media-feature>) ")"
+    ///                    | <general-enclosed>
+    fn parse_media_in_parens(&mut self) -> Option<MediaCondition> {
+        self.consume_whitespace();
+        let contents = self.parse_parenthesized()?;
+        self.consume_whitespace();
+
+        let mut parser = Parser::new(contents.clone());
+        let condition = if parser.peek() == Some('(') || parser.consume_keyword("not") {
+            parser.pos = 0;
+            parser.parse_media_condition(true).filter(|_| parser.eof())
+        } else {
+            parse_media_feature(&contents).map(MediaCondition::Feature)
+        };
+        Some(condition.unwrap_or(MediaCondition::Unknown))
+    }
+}
+
+/// Parse the contents of a feature's parentheses, e.g. `width >= 600px`.
+///
+/// <media-feature> := ident | ident ":" <value>
+///                  | ident <comparison> <value> | <value> <comparison> ident
+///                  | <value> <comparison> ident <comparison> <value>
+fn parse_media_feature(contents: &str) -> Option<MediaFeature> {
+    if let Some((name, value)) = contents.split_once(':') {
+        let name = name.trim().to_ascii_lowercase();
+        let value = parse_media_value(value)?;
+        let feature = match (name.strip_prefix("min-"), name.strip_prefix("max-")) {
+            (Some(name), _) => MediaFeature::Range(name.to_string(), vec![(Comparison::Ge, value)]),
+            (_, Some(name)) => MediaFeature::Range(name.to_string(), vec![(Comparison::Le, value)]),
+            _ => MediaFeature::Plain(name, value),
+        };
+        return Some(feature);
+    }
+
+    // Split the range syntax into operands and comparisons.
+    let mut operands = vec![];
+    let mut comparisons = vec![];
+    let mut rest = contents;
+    while let Some(i) = rest.find(['<', '>', '=']) {
+        operands.push(rest[..i].trim());
+        let (comparison, len) = match &rest[i..] {
+            s if s.starts_with("<=") => (Comparison::Le, 2),
+            s if s.starts_with(">=") => (Comparison::Ge, 2),
+            s if s.starts_with('<') => (Comparison::Lt, 1),
+            s if s.starts_with('>') => (Comparison::Gt, 1),
+            _ => (Comparison::Eq, 1),
+        };
+        comparisons.push(comparison);
+        rest = &rest[i + len..];
+    }
+    operands.push(rest.trim());
+
+    let is_name = |s: &str| s.starts_with(|c: char| c.is_ascii_alphabetic());
+    let value = |s: &str| parse_media_value(s);
+    match (&operands[..], &comparisons[..]) {
+        ([name], []) if is_name(name) => Some(MediaFeature::Boolean(name.to_ascii_lowercase())),
+        ([name, v], [c]) if is_name(name) => Some(MediaFeature::Range(
+            name.to_ascii_lowercase(),
+            vec![(*c, value(v)?)],
+        )),
+        ([v, name], [c]) if is_name(name) => Some(MediaFeature::Range(
+            name.to_ascii_lowercase(),
+            vec![(flip(*c), value(v)?)],
+        )),
+        ([v1, name, v2], [c1, c2]) if is_name(name) => Some(MediaFeature::Range(
+            name.to_ascii_lowercase(),
+            vec![(flip(*c1), value(v1)?), (*c2, value(v2)?)],
+        )),
+        _ => None,
+    }
+}
+
+/// Turn `value < feature` around into `feature > value`.
+pub(super) fn flip(comparison: Comparison) -> Comparison {
+    match comparison {
+        Comparison::Lt => Comparison::Gt,
+        Comparison::Le => Comparison::Ge,
+        Comparison::Eq => Comparison::Eq,
+        Comparison::Ge => Comparison::Le,
+        Comparison::Gt => Comparison::Lt,
+    }
+}
+
+/// Parse the value of a media feature: a keyword, a length, a number, a
+/// resolution in dppx or a ratio. Return `None` if it is invalid.
+fn parse_media_value(css: &str) -> Option<Value> {
+    let css = css.trim();
+    if let Some((numerator, denominator)) = css.split_once('/') {
+        let numerator: f32 = numerator.trim().parse().ok()?;
+        let denominator: f32 = denominator.trim().parse().ok()?;
+        return Some(Value::Number(numerator / denominator));
+    }
+
+    let mut parser = Parser::new(css.to_string());
+    if !parser.starts_number() {
+        let keyword = parser.parse_identifier().to_ascii_lowercase();
+        return Some(Value::Keyword(keyword)).filter(|_| parser.eof() && !css.is_empty());
+    }
+    let number = parser.parse_number();
+    let unit = parser.parse_identifier().to_ascii_lowercase();
+    if !parser.eof() {
+        return None;
+    }
+    // spec: https://www.w3.org/TR/css-values-4/#resolution
+    let value = match &*unit {
+        "" if number == 0.0 => Value::Length(0.0, Unit::Px),
+        "" => Value::Number(number),
+        "dppx" | "x" => Value::Number(number),
+        "dpi" => Value::Number(number / 96.0),
+        "dpcm" => Value::Number(number * 2.54 / 96.0),
+        _ => Value::Length(number, Unit::from_name(&unit)?),
+    };
+    Some(value)
+}
+
+impl MediaQuery {
+    /// Return true if the query matches `device`. An unknown result of the
+    /// condition counts as false, before the query is negated.
+    pub fn matches(&self, device: &Device) -> bool {
+        let type_matches = match self.media_type.as_deref() {
+            None | Some("all") => true,
+            Some(media_type) => media_type == device.media_type,
+        };
+        let condition_matches = match &self.condition {
+            Some(condition) => condition.evaluate(device) == Some(true),
+            None => true,
+        };
+        (type_matches && condition_matches) != self.negated
+    }
+}
+
+impl MediaCondition {
+    /// Evaluate the condition with three-valued logic, where `None` is
+    /// unknown.
+    /// spec: https://www.w3.org/TR/mediaqueries-4/#evaluating
+    pub fn evaluate(&self, device: &Device) -> Option<bool> {
+        match self {
+            MediaCondition::Feature(feature) => feature.evaluate(device),
+            MediaCondition::Not(condition) => condition.evaluate(device).map(|b| !b),
+            MediaCondition::And(conditions) => {
+                let results: Vec<_> = conditions.iter().map(|c| c.evaluate(device)).collect();
+                match () {
+                    _ if results.contains(&Some(false)) => Some(false),
+                    _ if results.contains(&None) => None,
+                    _ => Some(true),
+                }
+            }
+            MediaCondition::Or(conditions) => {
+                let results: Vec<_> = conditions.iter().map(|c| c.evaluate(device)).collect();
+                match () {
+                    _ if results.contains(&Some(true)) => Some(true),
+                    _ if results.contains(&None) => None,
+                    _ => Some(false),
+                }
+            }
+            MediaCondition::Unknown => None,
+        }
+    }
+}
+
+impl MediaFeature {
+    /// Evaluate the feature, or return `None` if it is unknown or its value
+    /// is invalid.
+    fn evaluate(&self, device: &Device) -> Option<bool> {
+        match self {
+            MediaFeature::Boolean(name) => match &**name {
+                "orientation" | "prefers-color-scheme" | "color" => Some(true),
+                "monochrome" | "grid" => Some(false),
+                _ => Some(range_feature(name, device)? != 0.0),
+            },
+            MediaFeature::Plain(name, Value::Keyword(keyword)) => match &**name {
+                "orientation" => {
+                    let portrait = device.height >= device.width;
+                    match &**keyword {
+                        "portrait" => Some(portrait),
+                        "landscape" => Some(!portrait),
+                        _ => None,
+                    }
+                }
+                "prefers-color-scheme" => match &**keyword {
+                    "light" => Some(device.color_scheme == ColorScheme::Light),
+                    "dark" => Some(device.color_scheme == ColorScheme::Dark),
+                    _ => None,
+                },
+                _ => None,
+            },
+            MediaFeature::Plain(name, value) => {
+                compare(range_feature(name, device)?, Comparison::Eq, value, device)
+            }
+            MediaFeature::Range(name, comparisons) => {
+                let feature = range_feature(name, device)?;
+                comparisons
+                    .iter()
+                    .try_fold(true, |result, (comparison, value)| {
+                        Some(result && compare(feature, *comparison, value, device)?)
+                    })
+            }
+        }
+    }
+}
+
+/// The value of a feature of the "range" type, in px, dppx or as a ratio.
+fn range_feature(name: &str, device: &Device) -> Option<f32> {
+    match name {
+        "width" => Some(device.width),
+        "height" => Some(device.height),
+        "aspect-ratio" => Some(device.width / device.height),
+        "resolution" => Some(device.resolution),
+        "color" => Some(8.0),
+        "monochrome" => Some(0.0),
+        _ => None,
+    }
+}
+
+/// Compare the value of a feature with `value`. Relative lengths are
+/// resolved against the initial font size and the viewport.
+fn compare(feature: f32, comparison: Comparison, value: &Value, device: &Device) -> Option<bool> {
+    let value = match *value {
+        Value::Length(_, Unit::Percent) => return None,
+        Value::Length(..) => value.to_px(&LengthContext::new(device.width, device.height), 0.0),
+        Value::Number(n) => n,
+        _ => return None,
+    };
+    Some(match comparison {
+        Comparison::Lt => feature < value,
+        Comparison::Le => feature <= value,
+        Comparison::Eq => feature == value,
+        Comparison::Ge => feature >= value,
+        Comparison::Gt => feature > value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(queries: &str) -> Vec<MediaQuery> {
+        let mut parser = Parser::new(format!("{} {{", queries));
+        parser.parse_media_query_list()
+    }
+
+    fn matches_at(queries: &str, width: f32) -> bool {
+        matches(&parse(queries), &Device::new(width, 800.0))
+    }
+
+    #[test]
+    fn parse_media_query() {
+        assert_eq!(
+            parse("only screen and (min-width: 600px), not print"),
+            vec![
+                MediaQuery {
+                    negated: false,
+                    media_type: Some("screen".to_string()),
+                    condition: Some(MediaCondition::Feature(MediaFeature::Range(
+                        "width".to_string(),
+                        vec![(Comparison::Ge, Value::Length(600.0, Unit::Px))],
+                    ))),
+                },
+                MediaQuery {
+                    negated: true,
+                    media_type: Some("print".to_string()),
+                    condition: None,
+                },
+            ],
+        );
+        assert_eq!(
+            parse("(400px < width <= 50em)")[0].condition,
+            Some(MediaCondition::Feature(MediaFeature::Range(
+                "width".to_string(),
+                vec![
+                    (Comparison::Gt, Value::Length(400.0, Unit::Px)),
+                    (Comparison::Le, Value::Length(50.0, Unit::Em)),
+                ],
+            ))),
+        );
+        assert_eq!(parse(""), vec![]);
+    }
+
+    #[test]
+    fn widths() {
+        let query = "screen and (width >= 600px) and (width < 1024px)";
+        assert!(!matches_at(query, 375.0));
+        assert!(matches_at(query, 768.0));
+        assert!(!matches_at(query, 1024.0));
+        assert!(matches_at("(600px <= width)", 600.0));
+        assert!(matches_at("(max-width: 37.5em)", 600.0));
+        assert!(matches_at("(width: 600px)", 600.0));
+        assert!(matches_at("(width)", 600.0));
+        assert!(matches_at("(600px < width <= 800px)", 800.0));
+    }
+
+    #[test]
+    fn features() {
+        let mut device = Device::new(1200.0, 800.0);
+        assert!(matches(&parse("(orientation: landscape)"), &device));
+        assert!(!matches(&parse("(orientation: portrait)"), &device));
+        assert!(matches(&parse("(prefers-color-scheme: light)"), &device));
+        assert!(matches(&parse("(aspect-ratio > 4/3)"), &device));
+        assert!(matches(&parse("(min-resolution: 96dpi)"), &device));
+        device.color_scheme = ColorScheme::Dark;
+        device.resolution = 2.0;
+        assert!(matches(&parse("(prefers-color-scheme: dark)"), &device));
+        assert!(matches(&parse("(resolution >= 2x)"), &device));
+        assert!(!matches(&parse("(resolution > 192dpi)"), &device));
+    }
+
+    #[test]
+    fn logic() {
+        assert!(matches_at("not print", 600.0));
+        assert!(!matches_at("not all and (width > 100px)", 600.0));
+        assert!(matches_at("(width < 100px) or (width > 500px)", 600.0));
+        assert!(matches_at("not (width < 100px)", 600.0));
+        assert!(matches_at("((width > 100px) and (height > 100px))", 600.0));
+        assert!(matches_at("print, screen", 600.0));
+        // Unknown features are neither true nor false.
+        assert!(!matches_at("(unknown-feature)", 600.0));
+        assert!(!matches_at("not (unknown-feature)", 600.0));
+        assert!(matches_at("(unknown-feature) or (width)", 600.0));
+        // Malformed queries match nothing, and `or` isn't allowed after a type.
+        assert!(!matches_at("screen and (width) or (height)", 600.0));
+        assert!(!matches_at("and", 600.0));
+        assert!(matches_at("screen and (width) and (height)", 600.0));
+    }
+}
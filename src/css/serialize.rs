@@ -0,0 +1,704 @@
+//! Serialization of stylesheets, rules, selectors and values back to CSS,
+//! minified or pretty-printed.
+//! spec: https://www.w3.org/TR/cssom-1/#serializing-css-values
+
+use super::media;
+use super::{
+    Calc, Color, Combinator, Comparison, ContentItem, CssRule, Declaration, MediaCondition,
+    MediaFeature, MediaQuery, PseudoElement, Selector, SimpleSelector, Stylesheet,
+    SupportsCondition, Unit, Value,
+};
+
+/// How serialized CSS is laid out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Without optional whitespace, e.g. `h1,p>a{color:#f00}`.
+    Minified,
+    /// One declaration per line, indented by two spaces for each level of
+    /// nesting, with the spacing of the CSSOM, e.g. `color: rgb(255, 0, 0);`.
+    Pretty,
+}
+
+/// Values that can be serialized back to CSS, which parses to an equal value.
+pub trait ToCss {
+    fn to_css(&self, format: Format) -> String;
+}
+
+/// The indentation of one level of nesting in pretty output.
+const INDENT: &str = "  ";
+
+impl ToCss for Stylesheet {
+    /// Serialize the rules. The origin isn't part of the CSS.
+    fn to_css(&self, format: Format) -> String {
+        match format {
+            Format::Minified => self.rules.iter().map(|rule| rule.to_css(format)).collect(),
+            Format::Pretty => self
+                .rules
+                .iter()
+                .map(|rule| rule.to_css(format) + "\n")
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+impl ToCss for CssRule {
+    /// Serialize the rule. Imported rules aren't serialized, only the
+    /// `@import` itself.
+    fn to_css(&self, format: Format) -> String {
+        let pretty = format == Format::Pretty;
+        let group = |prelude: String, rules: &[CssRule]| {
+            let mut css = prelude + if pretty { " {" } else { "{" };
+            for rule in rules {
+                match pretty {
+                    true => css += &format!("\n{}", indent(&rule.to_css(format))),
+                    false => css += &rule.to_css(format),
+                }
+            }
+            if pretty && !rules.is_empty() {
+                css.push('\n');
+            }
+            css + "}"
+        };
+        let queries = |queries: &[MediaQuery]| list(queries, format);
+        match self {
+            CssRule::Style(rule) => {
+                let selectors = list(&rule.selectors, format);
+                let declarations = declarations(&rule.declarations, format);
+                match (pretty, declarations.is_empty()) {
+                    (true, true) => format!("{} {{}}", selectors),
+                    (true, false) => {
+                        let lines: Vec<String> = declarations
+                            .iter()
+                            .map(|declaration| format!("{}{};", INDENT, declaration))
+                            .collect();
+                        format!("{} {{\n{}\n}}", selectors, lines.join("\n"))
+                    }
+                    (false, _) => format!("{}{{{}}}", selectors, declarations.join(";")),
+                }
+            }
+            CssRule::Media(media, rules) => match media.is_empty() {
+                true => group("@media".to_string(), rules),
+                false => group(format!("@media {}", queries(media)), rules),
+            },
+            CssRule::Import(url, media, _) => {
+                let url = match pretty {
+                    true => format!("url({})", serialize_string(url)),
+                    false => serialize_string(url),
+                };
+                match media.is_empty() {
+                    true => format!("@import {};", url),
+                    false => format!("@import {} {};", url, queries(media)),
+                }
+            }
+            CssRule::Supports(condition, rules) => {
+                group(format!("@supports {}", condition.to_css(format)), rules)
+            }
+            CssRule::Layer(name, rules) => match name {
+                Some(name) => group(format!("@layer {}", serialize_layer_name(name)), rules),
+                None => group("@layer".to_string(), rules),
+            },
+            CssRule::LayerStatement(names) => {
+                let separator = if pretty { ", " } else { "," };
+                let names: Vec<String> = names
+                    .iter()
+                    .map(|name| serialize_layer_name(name))
+                    .collect();
+                format!("@layer {};", names.join(separator))
+            }
+        }
+    }
+}
+
+/// Indent each line of `css` by one level.
+fn indent(css: &str) -> String {
+    let lines: Vec<String> = css
+        .lines()
+        .map(|line| format!("{}{}", INDENT, line))
+        .collect();
+    lines.join("\n")
+}
+
+/// Serialize `items`, separated by commas.
+fn list<T: ToCss>(items: &[T], format: Format) -> String {
+    let items: Vec<String> = items.iter().map(|item| item.to_css(format)).collect();
+    match format {
+        Format::Minified => items.join(","),
+        Format::Pretty => items.join(", "),
+    }
+}
+
+/// Serialize a dotted layer name, e.g. `theme.dark`.
+fn serialize_layer_name(name: &str) -> String {
+    let parts: Vec<String> = name.split('.').map(serialize_identifier).collect();
+    parts.join(".")
+}
+
+/// Serialize the declarations of a block. The longhands of a shorthand with
+/// `var()`, which all have the shorthand's value pending substitution, are
+/// serialized as the shorthand.
+fn declarations(declarations: &[Declaration], format: Format) -> Vec<String> {
+    let mut serialized = vec![];
+    for (i, declaration) in declarations.iter().enumerate() {
+        let Value::PendingSubstitution(shorthand, _) = &declaration.value else {
+            serialized.push(declaration.to_css(format));
+            continue;
+        };
+        let previous = i.checked_sub(1).map(|i| &declarations[i]);
+        if previous.is_some_and(|previous| {
+            previous.value == declaration.value && previous.important == declaration.important
+        }) {
+            continue;
+        }
+        let declaration = Declaration {
+            name: shorthand.clone(),
+            ..declaration.clone()
+        };
+        serialized.push(declaration.to_css(format));
+    }
+    serialized
+}
+
+impl ToCss for Declaration {
+    fn to_css(&self, format: Format) -> String {
+        let (colon, important) = match format {
+            Format::Minified => (":", "!important"),
+            Format::Pretty => (": ", " !important"),
+        };
+        let mut css = serialize_identifier(&self.name) + colon + &self.value.to_css(format);
+        if self.important {
+            css += important;
+        }
+        css
+    }
+}
+
+impl ToCss for Selector {
+    /// spec: https://www.w3.org/TR/cssom-1/#serialize-a-selector
+    fn to_css(&self, format: Format) -> String {
+        let mut parts = self.parts().into_iter();
+        let (_, first) = parts.next().unwrap();
+        let mut css = first.to_css(format);
+        for (combinator, simple) in parts {
+            css += match (combinator, format) {
+                (Combinator::Descendant, _) => " ",
+                (Combinator::Child, Format::Minified) => ">",
+                (Combinator::Child, Format::Pretty) => " > ",
+                (Combinator::NextSibling, Format::Minified) => "+",
+                (Combinator::NextSibling, Format::Pretty) => " + ",
+                (Combinator::SubsequentSibling, Format::Minified) => "~",
+                (Combinator::SubsequentSibling, Format::Pretty) => " ~ ",
+            };
+            css += &simple.to_css(format);
+        }
+        css
+    }
+}
+
+impl ToCss for SimpleSelector {
+    /// Serialize a compound selector, e.g. `p#a.b::before`. The universal
+    /// selector is only written if there's nothing else.
+    fn to_css(&self, _: Format) -> String {
+        let mut css = self
+            .tag_name
+            .as_deref()
+            .map_or_else(String::new, serialize_identifier);
+        if let Some(id) = &self.id {
+            css += &format!("#{}", serialize_identifier(id));
+        }
+        for class in &self.class {
+            css += &format!(".{}", serialize_identifier(class));
+        }
+        if css.is_empty() {
+            css.push('*');
+        }
+        match self.pseudo_element {
+            Some(PseudoElement::Before) => css += "::before",
+            Some(PseudoElement::After) => css += "::after",
+            Some(PseudoElement::Marker) => css += "::marker",
+            None => {}
+        }
+        css
+    }
+}
+
+impl ToCss for Value {
+    /// spec: https://www.w3.org/TR/cssom-1/#serialize-a-css-component-value
+    fn to_css(&self, format: Format) -> String {
+        match self {
+            Value::Keyword(keyword) => serialize_identifier(keyword),
+            Value::Length(n, unit) => serialize_number(*n) + unit.name(),
+            Value::Number(n) => serialize_number(*n),
+            Value::Colorvalue(color) => color.to_css(format),
+            Value::Str(s) => serialize_string(s),
+            Value::Url(url) => format!("url({})", serialize_string(url)),
+            Value::Delim(c) => c.to_string(),
+            Value::List(values) => {
+                let mut css = String::new();
+                for (i, value) in values.iter().enumerate() {
+                    // Commas have no space before them, and in minified
+                    // output, delimiters have none around them.
+                    let separator = match (format, value) {
+                        _ if i == 0 => "",
+                        (Format::Minified, Value::Delim(_)) => "",
+                        (Format::Minified, _) if matches!(values[i - 1], Value::Delim(_)) => "",
+                        (Format::Pretty, Value::Delim(',')) => "",
+                        _ => " ",
+                    };
+                    css += separator;
+                    css += &value.to_css(format);
+                }
+                css
+            }
+            Value::Content(items) => {
+                let items: Vec<String> = items.iter().map(|item| item.to_css(format)).collect();
+                items.join(" ")
+            }
+            Value::CounterList(counters) => {
+                let counters: Vec<String> = counters
+                    .iter()
+                    .map(|(name, value)| format!("{} {}", serialize_identifier(name), value))
+                    .collect();
+                counters.join(" ")
+            }
+            Value::Raw(css) | Value::PendingSubstitution(_, css) => css.clone(),
+            Value::Calc(calc) => match **calc {
+                Calc::Min(_) | Calc::Max(_) | Calc::Clamp(..) => calc.to_css(format),
+                _ => format!("calc({})", calc.to_css(format)),
+            },
+        }
+    }
+}
+
+impl ToCss for ContentItem {
+    /// The counter style is omitted if it's the default, `decimal`.
+    fn to_css(&self, format: Format) -> String {
+        let comma = match format {
+            Format::Minified => ",",
+            Format::Pretty => ", ",
+        };
+        let style = |style: &str| match style {
+            "decimal" => String::new(),
+            _ => format!("{}{}", comma, serialize_identifier(style)),
+        };
+        match self {
+            ContentItem::String(s) => serialize_string(s),
+            ContentItem::Attr(name) => format!("attr({})", serialize_identifier(name)),
+            ContentItem::Counter(name, counter_style) => {
+                format!(
+                    "counter({}{})",
+                    serialize_identifier(name),
+                    style(counter_style)
+                )
+            }
+            ContentItem::Counters(name, separator, counter_style) => format!(
+                "counters({}{}{}{})",
+                serialize_identifier(name),
+                comma,
+                serialize_string(separator),
+                style(counter_style)
+            ),
+        }
+    }
+}
+
+impl ToCss for Calc {
+    /// Serialize the expression, without the `calc()` around it at the top
+    /// level. Operands are parenthesized where precedence requires it.
+    /// spec: https://www.w3.org/TR/css-values-4/#serialize-a-math-function
+    fn to_css(&self, format: Format) -> String {
+        let arguments = |args: Vec<&Calc>| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_css(format)).collect();
+            match format {
+                Format::Minified => args.join(","),
+                Format::Pretty => args.join(", "),
+            }
+        };
+        let (a, b, operator) = match self {
+            Calc::Leaf(value) => return value.to_css(format),
+            Calc::Min(args) => return format!("min({})", arguments(args.iter().collect())),
+            Calc::Max(args) => return format!("max({})", arguments(args.iter().collect())),
+            Calc::Clamp(min, value, max) => {
+                return format!("clamp({})", arguments(vec![min, value, max]));
+            }
+            Calc::Sum(a, b) => (a, b, " + "),
+            Calc::Difference(a, b) => (a, b, " - "),
+            Calc::Product(a, b) => (a, b, if format == Format::Pretty { " * " } else { "*" }),
+            Calc::Quotient(a, b) => (a, b, if format == Format::Pretty { " / " } else { "/" }),
+        };
+        // The right operand of `-` and `/` is also parenthesized if it has
+        // the same precedence, e.g. `a - (b + c)`.
+        let precedence = self.precedence();
+        let operand = |calc: &Calc, right: bool| {
+            let parenthesize = calc.precedence() < precedence
+                || right
+                    && calc.precedence() == precedence
+                    && matches!(self, Calc::Difference(..) | Calc::Quotient(..));
+            match parenthesize {
+                true => format!("({})", calc.to_css(format)),
+                false => calc.to_css(format),
+            }
+        };
+        operand(a, false) + operator + &operand(b, true)
+    }
+}
+
+impl Calc {
+    /// How tightly an operation binds its operands. Leaves and functions
+    /// bind the tightest.
+    fn precedence(&self) -> u8 {
+        match self {
+            Calc::Sum(..) | Calc::Difference(..) => 1,
+            Calc::Product(..) | Calc::Quotient(..) => 2,
+            _ => 3,
+        }
+    }
+}
+
+impl ToCss for Color {
+    /// Pretty output is `rgb()` or `rgba()` as in the CSSOM, with the alpha
+    /// rounded to the fewest decimals that give the same byte. Minified
+    /// output is the shortest hex color.
+    /// spec: https://www.w3.org/TR/css-color-4/#serializing-sRGB-values
+    fn to_css(&self, format: Format) -> String {
+        let Color { r, g, b, a } = *self;
+        if format == Format::Minified {
+            let bytes: &[u8] = if a == 255 { &[r, g, b] } else { &[r, g, b, a] };
+            let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            // Each digit is doubled in the short form, e.g. `#f00`.
+            return match bytes.iter().all(|byte| byte >> 4 == byte & 0xf) {
+                true => format!("#{}", hex.chars().step_by(2).collect::<String>()),
+                false => format!("#{}", hex),
+            };
+        }
+        if a == 255 {
+            return format!("rgb({}, {}, {})", r, g, b);
+        }
+        let two_decimals = (a as f32 / 255.0 * 100.0).round() / 100.0;
+        let alpha = match (two_decimals * 255.0).round() as u8 == a {
+            true => two_decimals,
+            false => (a as f32 / 255.0 * 1000.0).round() / 1000.0,
+        };
+        format!("rgba({}, {}, {}, {})", r, g, b, serialize_number(alpha))
+    }
+}
+
+impl ToCss for MediaQuery {
+    /// spec: https://www.w3.org/TR/cssom-1/#serialize-a-media-query
+    fn to_css(&self, format: Format) -> String {
+        let condition = self
+            .condition
+            .as_ref()
+            .map(|condition| condition.to_css(format));
+        let media_type = self.media_type.as_deref().map(serialize_identifier);
+        let query = match (media_type, condition) {
+            (Some(media_type), Some(condition)) => format!("{} and {}", media_type, condition),
+            (Some(media_type), None) => media_type,
+            (None, Some(condition)) => condition,
+            (None, None) => "all".to_string(),
+        };
+        match self.negated {
+            true => format!("not {}", query),
+            false => query,
+        }
+    }
+}
+
+impl ToCss for MediaCondition {
+    /// A condition which isn't understood is serialized as `(?)`, which is
+    /// also not understood, since its source isn't kept.
+    fn to_css(&self, format: Format) -> String {
+        let in_parens = |condition: &MediaCondition| match condition {
+            MediaCondition::Feature(_) | MediaCondition::Unknown => condition.to_css(format),
+            _ => format!("({})", condition.to_css(format)),
+        };
+        let join = |conditions: &[MediaCondition], combinator: &str| {
+            let conditions: Vec<String> = conditions.iter().map(in_parens).collect();
+            conditions.join(combinator)
+        };
+        match self {
+            MediaCondition::Feature(feature) => feature.to_css(format),
+            MediaCondition::Not(condition) => format!("not {}", in_parens(condition)),
+            MediaCondition::And(conditions) => join(conditions, " and "),
+            MediaCondition::Or(conditions) => join(conditions, " or "),
+            MediaCondition::Unknown => "(?)".to_string(),
+        }
+    }
+}
+
+impl ToCss for MediaFeature {
+    /// Ranges are serialized with the range syntax, e.g. `min-width: 1px` as
+    /// `(width >= 1px)`.
+    fn to_css(&self, format: Format) -> String {
+        let (colon, space) = match format {
+            Format::Minified => (":", ""),
+            Format::Pretty => (": ", " "),
+        };
+        let feature = match self {
+            MediaFeature::Boolean(name) => serialize_identifier(name),
+            MediaFeature::Plain(name, value) => {
+                format!(
+                    "{}{}{}",
+                    serialize_identifier(name),
+                    colon,
+                    value.to_css(format)
+                )
+            }
+            MediaFeature::Range(name, comparisons) => {
+                let name = serialize_identifier(name);
+                let operator = |comparison| match comparison {
+                    Comparison::Lt => "<",
+                    Comparison::Le => "<=",
+                    Comparison::Eq => "=",
+                    Comparison::Ge => ">=",
+                    Comparison::Gt => ">",
+                };
+                match &comparisons[..] {
+                    [(comparison, value)] => format!(
+                        "{}{space}{}{space}{}",
+                        name,
+                        operator(*comparison),
+                        value.to_css(format)
+                    ),
+                    // `value < name <= value`
+                    [(c1, v1), (c2, v2)] => format!(
+                        "{}{space}{}{space}{}{space}{}{space}{}",
+                        v1.to_css(format),
+                        operator(media::flip(*c1)),
+                        name,
+                        operator(*c2),
+                        v2.to_css(format)
+                    ),
+                    _ => panic!("Expected 1 or 2 comparisons in a range."),
+                }
+            }
+        };
+        format!("({})", feature)
+    }
+}
+
+impl ToCss for SupportsCondition {
+    /// A condition which isn't understood is serialized as `(?)`, as for media
+    /// conditions.
+    fn to_css(&self, format: Format) -> String {
+        let in_parens = |condition: &SupportsCondition| match condition {
+            SupportsCondition::Declaration(..) | SupportsCondition::Unknown => {
+                condition.to_css(format)
+            }
+            _ => format!("({})", condition.to_css(format)),
+        };
+        let join = |conditions: &[SupportsCondition], combinator: &str| {
+            let conditions: Vec<String> = conditions.iter().map(in_parens).collect();
+            conditions.join(combinator)
+        };
+        match self {
+            SupportsCondition::Declaration(name, value) => {
+                let colon = if format == Format::Pretty { ": " } else { ":" };
+                format!("({}{}{})", serialize_identifier(name), colon, value)
+            }
+            SupportsCondition::Not(condition) => format!("not {}", in_parens(condition)),
+            SupportsCondition::And(conditions) => join(conditions, " and "),
+            SupportsCondition::Or(conditions) => join(conditions, " or "),
+            SupportsCondition::Unknown => "(?)".to_string(),
+        }
+    }
+}
+
+impl Unit {
+    /// The name of the unit, e.g. `px`.
+    pub(super) fn name(self) -> &'static str {
+        match self {
+            Unit::Px => "px",
+            Unit::Pt => "pt",
+            Unit::Pc => "pc",
+            Unit::In => "in",
+            Unit::Cm => "cm",
+            Unit::Mm => "mm",
+            Unit::Q => "q",
+            Unit::Em => "em",
+            Unit::Rem => "rem",
+            Unit::Ex => "ex",
+            Unit::Ch => "ch",
+            Unit::Vw => "vw",
+            Unit::Vh => "vh",
+            Unit::Vmin => "vmin",
+            Unit::Vmax => "vmax",
+            Unit::Percent => "%",
+        }
+    }
+}
+
+/// Serialize a number in its shortest form, e.g. `1.5` or `-2`.
+/// spec: https://www.w3.org/TR/cssom-1/#serialize-a-css-component-value
+fn serialize_number(n: f32) -> String {
+    match n == 0.0 {
+        // Without the sign of -0.
+        true => "0".to_string(),
+        false => n.to_string(),
+    }
+}
+
+/// Serialize an identifier, escaping the characters which can't appear
+/// unescaped, e.g. `10` as `\31 0`.
+/// spec: https://www.w3.org/TR/cssom-1/#serialize-an-identifier
+pub(super) fn serialize_identifier(ident: &str) -> String {
+    let chars: Vec<char> = ident.chars().collect();
+    let mut css = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        let starts_with_digit = i == 0 || i == 1 && chars[0] == '-';
+        match c {
+            '\0' => css.push('\u{fffd}'),
+            '\x01'..='\x1f' | '\x7f' => css += &format!("\\{:x} ", c as u32),
+            '0'..='9' if starts_with_digit => css += &format!("\\{:x} ", c as u32),
+            '-' if i == 0 && chars.len() == 1 => css += "\\-",
+            c if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') || !c.is_ascii() => {
+                css.push(c)
+            }
+            c => {
+                css.push('\\');
+                css.push(c);
+            }
+        }
+    }
+    css
+}
+
+/// Serialize a string in double quotes, escaping quotes and backslashes.
+/// spec: https://www.w3.org/TR/cssom-1/#serialize-a-string
+pub(super) fn serialize_string(s: &str) -> String {
+    let mut css = String::from('"');
+    for c in s.chars() {
+        match c {
+            '\0' => css.push('\u{fffd}'),
+            '\x01'..='\x1f' | '\x7f' => css += &format!("\\{:x} ", c as u32),
+            '"' | '\\' => {
+                css.push('\\');
+                css.push(c);
+            }
+            c => css.push(c),
+        }
+    }
+    css.push('"');
+    css
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::parse;
+
+    const CSS: &str = r#"
+        @import "base.css" screen and (min-width: 600px);
+        @layer reset, theme.dark;
+        h1, ul > li.a\:b + p ~ #x::before {
+            color: rgba(255, 0, 0, 0.5);
+            margin: 1px auto;
+            font-family: "Times \"New\" Roman", serif;
+            content: "[" counter(item) "." counters(item, ".", lower-roman) attr(title) "]";
+            counter-increment: item 2 other -1;
+            width: calc(100% - (2em + 3px) * 2);
+            height: clamp(1rem, 2.5vw, 3rem) !important;
+            --main: { a: b };
+            padding: var(--pad) 0;
+        }
+        @media not print and (400px < width <= 50em), (orientation: portrait) {
+            @layer {
+                \31 0 .été { grid-area: 1 / 2; }
+            }
+        }
+        @supports (display: flow-root) and (not (foo: bar)) {
+            p { line-height: 1.5; background: url("a b.png") #0000; }
+        }
+        main { & > a { color: currentColor } }
+    "#;
+
+    #[test]
+    fn round_trip() {
+        let stylesheet = parse(CSS.to_string());
+        for format in [Format::Minified, Format::Pretty] {
+            let css = stylesheet.to_css(format);
+            assert_eq!(parse(css.clone()), stylesheet, "{}", css);
+            // Serialization is stable.
+            assert_eq!(parse(css.clone()).to_css(format), css);
+        }
+    }
+
+    #[test]
+    fn pretty() {
+        let stylesheet = parse(
+            r#"
+            @import 'a.css';
+            @media (min-width: 600px) { @layer x { a, p > b { color: #f00; margin: var(--m) !important } } }
+            p {}
+            "#
+            .to_string(),
+        );
+        assert_eq!(
+            stylesheet.to_css(Format::Pretty),
+            r#"@import url("a.css");
+
+@media (width >= 600px) {
+  @layer x {
+    p > b, a {
+      color: rgb(255, 0, 0);
+      margin: var(--m) !important;
+    }
+  }
+}
+
+p {}
+"#
+        );
+    }
+
+    #[test]
+    fn minified() {
+        let stylesheet = parse(
+            r#"
+            @media screen and (min-width: 600px) { a, p > b { color: #f00; margin: 0 1em } }
+            @supports not (display: grid) { .a { font-family: a, "b c"; width: calc(2px * 3 + 1%) } }
+            @layer a, b;
+            "#
+            .to_string(),
+        );
+        assert_eq!(
+            stylesheet.to_css(Format::Minified),
+            concat!(
+                "@media screen and (width>=600px){p>b,a{color:#f00;margin-top:0px;",
+                "margin-right:1em;margin-bottom:0px;margin-left:1em}}",
+                r#"@supports not (display:grid){.a{font-family:a,"b c";width:calc(2px*3 + 1%)}}"#,
+                "@layer a,b;",
+            )
+        );
+    }
+
+    #[test]
+    fn serialize_values() {
+        let value = |css: &str| {
+            let declarations = crate::css::parse_inline(&format!("width: {}", css));
+            declarations[0].value.to_css(Format::Pretty)
+        };
+        assert_eq!(
+            value("calc((1px + 2px) * 3 - (4px - -5px))"),
+            "calc((1px + 2px) * 3 - (4px - -5px))"
+        );
+        assert_eq!(value("calc(1px / (2 * 3))"), "calc(1px / (2 * 3))");
+        assert_eq!(value("calc(1px + 2px + 3px)"), "calc(1px + 2px + 3px)");
+        assert_eq!(value("MIN(1px,2px)"), "min(1px, 2px)");
+        assert_eq!(value("-0.50em"), "-0.5em");
+        assert_eq!(value("rgba(0 0 0 / 0.1)"), "rgba(0, 0, 0, 0.1)");
+        assert_eq!(value("rgba(0 0 0 / 0.502)"), "rgba(0, 0, 0, 0.5)");
+        assert_eq!(value("rgba(0 0 0 / 0.498)"), "rgba(0, 0, 0, 0.498)");
+    }
+
+    #[test]
+    fn serialize_identifiers_and_strings() {
+        assert_eq!(serialize_identifier("a:b"), r"a\:b");
+        assert_eq!(serialize_identifier("10"), r"\31 0");
+        assert_eq!(serialize_identifier("-1"), r"-\31 ");
+        assert_eq!(serialize_identifier("-"), r"\-");
+        assert_eq!(serialize_identifier("--été_2"), "--été_2");
+        assert_eq!(serialize_identifier("a\nb"), r"a\a b");
+        assert_eq!(serialize_string("a\"b\\c\n"), r#""a\"b\\c\a ""#);
+    }
+}
@@ -0,0 +1,645 @@
+//! Expansion of shorthand properties into their longhands, done at parse time
+//! so that the later steps only ever see longhands.
+//! spec: https://www.w3.org/TR/css-cascade-4/#shorthand
+
+use super::{Color, Declaration, Unit, Value};
+
+const SIDES: [&str; 4] = ["top", "right", "bottom", "left"];
+
+/// Keywords valid for every property.
+/// spec: https://www.w3.org/TR/css-cascade-4/#defaulting-keywords
+const CSS_WIDE_KEYWORDS: [&str; 5] = ["inherit", "initial", "unset", "revert", "revert-layer"];
+
+/// Turn the component values of a declaration of property `name` into
+/// declarations of longhands. A declaration of a longhand is kept as it is,
+/// with a single component unwrapped from the list. Invalid declarations are
+/// dropped.
+pub(super) fn expand(name: String, components: Vec<Value>) -> Vec<Declaration> {
+    let Some(longhands) = longhands(&name) else {
+        let value = match components.len() {
+            0 => return vec![],
+            1 => components.into_iter().next().unwrap(),
+            _ => Value::List(components),
+        };
+        return vec![Declaration {
+            name,
+            value,
+            important: false,
+        }];
+    };
+
+    // A CSS-wide keyword sets every longhand to itself.
+    if let [Value::Keyword(keyword)] = &components[..] {
+        if CSS_WIDE_KEYWORDS.contains(&&**keyword) {
+            return longhands
+                .into_iter()
+                .map(|name| Declaration {
+                    name,
+                    value: components[0].clone(),
+                    important: false,
+                })
+                .collect();
+        }
+    }
+
+    let values = match &*name {
+        "margin" | "padding" | "inset" | "border-width" | "border-style" | "border-color" => {
+            four_sides(&components)
+        }
+        "border" | "border-top" | "border-right" | "border-bottom" | "border-left" => {
+            // The same width, style and color for each side.
+            border(&components)
+                .map(|side| side.iter().cycle().take(longhands.len()).cloned().collect())
+        }
+        "background" => background(&components),
+        "font" => font(&components),
+        "flex" => flex(&components),
+        "grid-area" => grid_area(&components),
+        _ => unreachable!(),
+    };
+    match values {
+        Some(values) => longhands
+            .into_iter()
+            .zip(values)
+            .map(|(name, value)| Declaration {
+                name,
+                value,
+                important: false,
+            })
+            .collect(),
+        None => vec![],
+    }
+}
+
+/// Turn a declaration of property `name` whose value `css` contains `var()`
+/// into declarations of its longhands, which all get the same
+/// pending-substitution value.
+/// spec: https://www.w3.org/TR/css-variables-1/#variables-in-shorthands
+pub(super) fn expand_pending(name: String, css: String) -> Vec<Declaration> {
+    longhands(&name)
+        .unwrap_or_else(|| vec![name.clone()])
+        .into_iter()
+        .map(|longhand| Declaration {
+            name: longhand,
+            value: Value::PendingSubstitution(name.clone(), css.clone()),
+            important: false,
+        })
+        .collect()
+}
+
+/// The longhands of shorthand property `name`, in the order its expansion
+/// yields values for them, or `None` if it's not a shorthand.
+fn longhands(name: &str) -> Option<Vec<String>> {
+    let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+    let sides = |format: &dyn Fn(&str) -> String| SIDES.iter().map(|side| format(side)).collect();
+    let longhands = match name {
+        "margin" | "padding" => sides(&|side| format!("{}-{}", name, side)),
+        "inset" => names(&SIDES),
+        "border-width" | "border-style" | "border-color" => {
+            let property = name.trim_start_matches("border-");
+            sides(&|side| format!("border-{}-{}", side, property))
+        }
+        "border" => SIDES
+            .iter()
+            .flat_map(|side| border_side_longhands(side))
+            .collect(),
+        "border-top" | "border-right" | "border-bottom" | "border-left" => {
+            border_side_longhands(name.trim_start_matches("border-")).to_vec()
+        }
+        "background" => names(&[
+            "background-color",
+            "background-image",
+            "background-repeat",
+            "background-attachment",
+            "background-position",
+        ]),
+        "font" => names(&[
+            "font-style",
+            "font-variant",
+            "font-weight",
+            "font-stretch",
+            "font-size",
+            "line-height",
+            "font-family",
+        ]),
+        "flex" => names(&["flex-grow", "flex-shrink", "flex-basis"]),
+        "grid-area" => names(&[
+            "grid-row-start",
+            "grid-column-start",
+            "grid-row-end",
+            "grid-column-end",
+        ]),
+        _ => return None,
+    };
+    Some(longhands)
+}
+
+fn border_side_longhands(side: &str) -> [String; 3] {
+    ["width", "style", "color"].map(|property| format!("border-{}-{}", side, property))
+}
+
+/// Expand 1 to 4 values to the top, right, bottom and left sides, e.g.
+/// `10px 20px` to `10px 20px 10px 20px`.
+fn four_sides(components: &[Value]) -> Option<Vec<Value>> {
+    let sides = match components {
+        [all] => [all, all, all, all],
+        [vertical, horizontal] => [vertical, horizontal, vertical, horizontal],
+        [top, horizontal, bottom] => [top, horizontal, bottom, horizontal],
+        [top, right, bottom, left] => [top, right, bottom, left],
+        _ => return None,
+    };
+    Some(sides.into_iter().cloned().collect())
+}
+
+/// `<line-width> || <line-style> || <color>`, to width, style and color.
+/// spec: https://www.w3.org/TR/css-backgrounds-3/#propdef-border
+fn border(components: &[Value]) -> Option<Vec<Value>> {
+    let mut width = None;
+    let mut style = None;
+    let mut color = None;
+    for component in components {
+        let slot = if is_line_width(component) {
+            &mut width
+        } else if is_line_style(component) {
+            &mut style
+        } else if is_color(component) {
+            &mut color
+        } else {
+            return None;
+        };
+        if slot.replace(component.clone()).is_some() {
+            return None;
+        }
+    }
+    Some(vec![
+        width.unwrap_or_else(|| keyword("medium")),
+        style.unwrap_or_else(|| keyword("none")),
+        color.unwrap_or_else(|| keyword("currentcolor")),
+    ])
+}
+
+/// `<color> || <image> || <repeat> || <attachment> || <position>`, for a
+/// single background layer.
+/// spec: https://www.w3.org/TR/css-backgrounds-3/#propdef-background
+fn background(components: &[Value]) -> Option<Vec<Value>> {
+    let mut color = None;
+    let mut image = None;
+    let mut repeat = None;
+    let mut attachment = None;
+    let mut position = vec![];
+    for component in components {
+        let slot = match component {
+            _ if is_color(component) => &mut color,
+            Value::Keyword(k) if k == "none" => &mut image,
+            Value::Url(_) => &mut image,
+            Value::Keyword(k)
+                if matches!(
+                    &**k,
+                    "repeat" | "repeat-x" | "repeat-y" | "no-repeat" | "space" | "round"
+                ) =>
+            {
+                &mut repeat
+            }
+            Value::Keyword(k) if matches!(&**k, "scroll" | "fixed" | "local") => &mut attachment,
+            Value::Length(..) | Value::Calc(_) => {
+                position.push(component.clone());
+                continue;
+            }
+            Value::Keyword(k) if matches!(&**k, "left" | "right" | "top" | "bottom" | "center") => {
+                position.push(component.clone());
+                continue;
+            }
+            _ => return None,
+        };
+        if slot.replace(component.clone()).is_some() {
+            return None;
+        }
+    }
+    let position = match position.len() {
+        0 => Value::List(vec![
+            Value::Length(0.0, Unit::Percent),
+            Value::Length(0.0, Unit::Percent),
+        ]),
+        1 => position.remove(0),
+        _ => Value::List(position),
+    };
+    Some(vec![
+        color.unwrap_or(Value::Colorvalue(Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0,
+        })),
+        image.unwrap_or_else(|| keyword("none")),
+        repeat.unwrap_or_else(|| keyword("repeat")),
+        attachment.unwrap_or_else(|| keyword("scroll")),
+        position,
+    ])
+}
+
+/// `[<style> || <variant> || <weight> || <stretch>]? <size> [/ <line-height>]?
+/// <family>`
+/// spec: https://www.w3.org/TR/css-fonts-4/#font-prop
+fn font(components: &[Value]) -> Option<Vec<Value>> {
+    let mut style = None;
+    let mut variant = None;
+    let mut weight = None;
+    let mut stretch = None;
+    let mut rest = components;
+    let size = loop {
+        let (component, tail) = rest.split_first()?;
+        rest = tail;
+        if is_font_size(component) {
+            break component.clone();
+        }
+        let slot = match component {
+            // `normal` is valid for any of them, and leaves it as the default.
+            Value::Keyword(k) if k == "normal" => continue,
+            Value::Keyword(k) if matches!(&**k, "italic" | "oblique") => &mut style,
+            Value::Keyword(k) if k == "small-caps" => &mut variant,
+            Value::Keyword(k) if matches!(&**k, "bold" | "bolder" | "lighter") => &mut weight,
+            Value::Number(n) if (1.0..=1000.0).contains(n) => &mut weight,
+            Value::Keyword(k) if k.ends_with("condensed") || k.ends_with("expanded") => {
+                &mut stretch
+            }
+            _ => return None,
+        };
+        if slot.replace(component.clone()).is_some() {
+            return None;
+        }
+    };
+
+    let line_height = match rest {
+        [Value::Delim('/'), line_height, tail @ ..] => {
+            rest = tail;
+            line_height.clone()
+        }
+        _ => keyword("normal"),
+    };
+    let family = match rest {
+        [] => return None,
+        [family] => family.clone(),
+        families => Value::List(families.to_vec()),
+    };
+    Some(vec![
+        style.unwrap_or_else(|| keyword("normal")),
+        variant.unwrap_or_else(|| keyword("normal")),
+        weight.unwrap_or_else(|| keyword("normal")),
+        stretch.unwrap_or_else(|| keyword("normal")),
+        size,
+        line_height,
+        family,
+    ])
+}
+
+/// `none | [<grow> <shrink>? || <basis>]`
+/// spec: https://www.w3.org/TR/css-flexbox-1/#flex-property
+fn flex(components: &[Value]) -> Option<Vec<Value>> {
+    match components {
+        [Value::Keyword(k)] if k == "none" => {
+            return Some(vec![
+                Value::Number(0.0),
+                Value::Number(0.0),
+                keyword("auto"),
+            ])
+        }
+        [Value::Keyword(k)] if k == "auto" => {
+            return Some(vec![
+                Value::Number(1.0),
+                Value::Number(1.0),
+                keyword("auto"),
+            ])
+        }
+        _ => {}
+    }
+
+    let mut factors = vec![];
+    let mut basis = None;
+    for component in components {
+        match component {
+            // The factors can't be split by the basis.
+            Value::Number(n) if basis.is_none() || factors.is_empty() => factors.push(*n),
+            // A unitless zero is a flex factor, unless both factors are
+            // already given.
+            Value::Length(n, Unit::Px) if *n == 0.0 && factors.len() < 2 && basis.is_none() => {
+                factors.push(0.0)
+            }
+            Value::Length(..) | Value::Calc(_) | Value::Keyword(_) if basis.is_none() => {
+                basis = Some(component.clone())
+            }
+            _ => return None,
+        }
+    }
+    if factors.len() > 2 {
+        return None;
+    }
+    Some(vec![
+        Value::Number(factors.first().copied().unwrap_or(1.0)),
+        Value::Number(factors.get(1).copied().unwrap_or(1.0)),
+        // The basis is 0 when omitted from the shorthand, unlike its initial
+        // value `auto`.
+        basis.unwrap_or(Value::Length(0.0, Unit::Px)),
+    ])
+}
+
+/// `<grid-line> [/ <grid-line>]{0,3}`, to row-start, column-start, row-end and
+/// column-end. An omitted line copies the opposite one if it's a custom
+/// identifier, or is `auto`.
+/// spec: https://www.w3.org/TR/css-grid-1/#propdef-grid-area
+fn grid_area(components: &[Value]) -> Option<Vec<Value>> {
+    let mut lines: Vec<Value> = components
+        .split(|c| *c == Value::Delim('/'))
+        .map(|line| match line {
+            [] => None,
+            [value] => Some(value.clone()),
+            values => Some(Value::List(values.to_vec())),
+        })
+        .collect::<Option<_>>()?;
+    if lines.len() > 4 {
+        return None;
+    }
+    while lines.len() < 4 {
+        // column-start and row-end copy row-start, and column-end copies
+        // column-start.
+        let opposite = lines[lines.len().saturating_sub(2)].clone();
+        let is_custom_ident = matches!(&opposite, Value::Keyword(k) if k != "auto" && k != "span");
+        lines.push(if is_custom_ident {
+            opposite
+        } else {
+            keyword("auto")
+        });
+    }
+    Some(lines)
+}
+
+fn keyword(name: &str) -> Value {
+    Value::Keyword(name.to_string())
+}
+
+fn is_color(value: &Value) -> bool {
+    matches!(value, Value::Colorvalue(_)) || *value == keyword("currentcolor")
+}
+
+fn is_line_width(value: &Value) -> bool {
+    match value {
+        Value::Length(..) | Value::Calc(_) => true,
+        Value::Keyword(k) => matches!(&**k, "thin" | "medium" | "thick"),
+        _ => false,
+    }
+}
+
+fn is_line_style(value: &Value) -> bool {
+    match value {
+        Value::Keyword(k) => matches!(
+            &**k,
+            "none"
+                | "hidden"
+                | "dotted"
+                | "dashed"
+                | "solid"
+                | "double"
+                | "groove"
+                | "ridge"
+                | "inset"
+                | "outset"
+        ),
+        _ => false,
+    }
+}
+
+fn is_font_size(value: &Value) -> bool {
+    match value {
+        Value::Length(..) | Value::Calc(_) => true,
+        Value::Keyword(k) => matches!(
+            &**k,
+            "xx-small"
+                | "x-small"
+                | "small"
+                | "medium"
+                | "large"
+                | "x-large"
+                | "xx-large"
+                | "xxx-large"
+                | "larger"
+                | "smaller"
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn px(n: f32) -> Value {
+        Value::Length(n, Unit::Px)
+    }
+
+    /// Expand `name` with `components`, returning (longhand, value) pairs.
+    fn expanded(name: &str, components: Vec<Value>) -> Vec<(String, Value)> {
+        expand(name.to_string(), components)
+            .into_iter()
+            .map(|d| (d.name, d.value))
+            .collect()
+    }
+
+    fn pairs(pairs: &[(&str, Value)]) -> Vec<(String, Value)> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn longhand() {
+        assert_eq!(
+            expanded("width", vec![px(1.0)]),
+            pairs(&[("width", px(1.0))])
+        );
+        assert_eq!(
+            expanded(
+                "font-family",
+                vec![keyword("a"), Value::Delim(','), keyword("b")]
+            ),
+            pairs(&[(
+                "font-family",
+                Value::List(vec![keyword("a"), Value::Delim(','), keyword("b")])
+            )])
+        );
+    }
+
+    #[test]
+    fn box_sides() {
+        assert_eq!(
+            expanded("margin", vec![px(10.0), px(20.0), px(5.0)]),
+            pairs(&[
+                ("margin-top", px(10.0)),
+                ("margin-right", px(20.0)),
+                ("margin-bottom", px(5.0)),
+                ("margin-left", px(20.0)),
+            ])
+        );
+        assert_eq!(
+            expanded("border-style", vec![keyword("solid"), keyword("none")]),
+            pairs(&[
+                ("border-top-style", keyword("solid")),
+                ("border-right-style", keyword("none")),
+                ("border-bottom-style", keyword("solid")),
+                ("border-left-style", keyword("none")),
+            ])
+        );
+        assert_eq!(
+            expanded("inset", vec![keyword("auto")]),
+            pairs(&[
+                ("top", keyword("auto")),
+                ("right", keyword("auto")),
+                ("bottom", keyword("auto")),
+                ("left", keyword("auto")),
+            ])
+        );
+        assert_eq!(expanded("padding", vec![px(1.0); 5]), vec![]);
+    }
+
+    #[test]
+    fn border() {
+        let black = Value::Colorvalue(Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        });
+        let declarations = expanded("border", vec![px(1.0), keyword("solid"), black.clone()]);
+        assert_eq!(declarations.len(), 12);
+        assert_eq!(
+            declarations[9..],
+            pairs(&[
+                ("border-left-width", px(1.0)),
+                ("border-left-style", keyword("solid")),
+                ("border-left-color", black.clone()),
+            ])
+        );
+
+        assert_eq!(
+            expanded("border-top", vec![keyword("dashed")]),
+            pairs(&[
+                ("border-top-width", keyword("medium")),
+                ("border-top-style", keyword("dashed")),
+                ("border-top-color", keyword("currentcolor")),
+            ])
+        );
+        assert_eq!(expanded("border", vec![px(1.0), px(2.0)]), vec![]);
+    }
+
+    #[test]
+    fn font() {
+        assert_eq!(
+            expanded(
+                "font",
+                vec![
+                    keyword("italic"),
+                    Value::Number(700.0),
+                    px(12.0),
+                    Value::Delim('/'),
+                    Value::Number(1.5),
+                    Value::Str("Times New Roman".to_string()),
+                    Value::Delim(','),
+                    keyword("serif"),
+                ]
+            ),
+            pairs(&[
+                ("font-style", keyword("italic")),
+                ("font-variant", keyword("normal")),
+                ("font-weight", Value::Number(700.0)),
+                ("font-stretch", keyword("normal")),
+                ("font-size", px(12.0)),
+                ("line-height", Value::Number(1.5)),
+                (
+                    "font-family",
+                    Value::List(vec![
+                        Value::Str("Times New Roman".to_string()),
+                        Value::Delim(','),
+                        keyword("serif"),
+                    ])
+                ),
+            ])
+        );
+        // The family is mandatory.
+        assert_eq!(expanded("font", vec![px(12.0)]), vec![]);
+    }
+
+    #[test]
+    fn flex() {
+        let flex = |components| {
+            expanded("flex", components)
+                .into_iter()
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            flex(vec![keyword("none")]),
+            vec![Value::Number(0.0), Value::Number(0.0), keyword("auto")]
+        );
+        assert_eq!(
+            flex(vec![Value::Number(2.0)]),
+            vec![Value::Number(2.0), Value::Number(1.0), px(0.0)]
+        );
+        assert_eq!(
+            flex(vec![px(0.0)]),
+            vec![Value::Number(0.0), Value::Number(1.0), px(0.0)]
+        );
+        assert_eq!(
+            flex(vec![Value::Number(2.0), Value::Number(3.0), px(10.0)]),
+            vec![Value::Number(2.0), Value::Number(3.0), px(10.0)]
+        );
+        assert_eq!(
+            flex(vec![px(10.0)]),
+            vec![Value::Number(1.0), Value::Number(1.0), px(10.0)]
+        );
+    }
+
+    #[test]
+    fn grid_area() {
+        assert_eq!(
+            expanded("grid-area", vec![keyword("header")]),
+            pairs(&[
+                ("grid-row-start", keyword("header")),
+                ("grid-column-start", keyword("header")),
+                ("grid-row-end", keyword("header")),
+                ("grid-column-end", keyword("header")),
+            ])
+        );
+        assert_eq!(
+            expanded(
+                "grid-area",
+                vec![
+                    Value::Number(1.0),
+                    Value::Delim('/'),
+                    keyword("span"),
+                    Value::Number(2.0),
+                ]
+            ),
+            pairs(&[
+                ("grid-row-start", Value::Number(1.0)),
+                (
+                    "grid-column-start",
+                    Value::List(vec![keyword("span"), Value::Number(2.0)])
+                ),
+                ("grid-row-end", keyword("auto")),
+                ("grid-column-end", keyword("auto")),
+            ])
+        );
+    }
+
+    #[test]
+    fn css_wide_keywords() {
+        assert_eq!(
+            expanded("padding", vec![keyword("inherit")]),
+            pairs(&[
+                ("padding-top", keyword("inherit")),
+                ("padding-right", keyword("inherit")),
+                ("padding-bottom", keyword("inherit")),
+                ("padding-left", keyword("inherit")),
+            ])
+        );
+    }
+}
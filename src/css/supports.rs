@@ -0,0 +1,172 @@
+//! `@supports` conditions: parsing them, and evaluating them.
+//! spec: https://www.w3.org/TR/css-conditional-3/#at-supports
+
+use super::{Parser, SupportsCondition};
+
+impl Parser {
+    /// Parse the condition of an `@supports` rule, up to its block. An
+    /// invalid condition is `Unknown`.
+    pub(super) fn parse_supports_prelude(&mut self) -> SupportsCondition {
+        let prelude = self.consume_prelude();
+        let mut parser = Parser::new(prelude.trim().to_string());
+        parser
+            .parse_supports_condition()
+            .filter(|_| parser.eof())
+            .unwrap_or(SupportsCondition::Unknown)
+    }
+
+    /// <supports-condition> := "not" <supports-in-parens>
+    ///                       | <supports-in-parens> ("and" <supports-in-parens>)*
+    ///                       | <supports-in-parens> ("or" <supports-in-parens>)*
+    fn parse_supports_condition(&mut self) -> Option<SupportsCondition> {
+        if self.consume_keyword("not") {
+            let condition = self.parse_supports_in_parens()?;
+            return Some(SupportsCondition::Not(Box::new(condition)));
+        }
+        let first = self.parse_supports_in_parens()?;
+        let combinator = match () {
+            _ if self.consume_keyword("and") => "and",
+            _ if self.consume_keyword("or") => "or",
+            _ => return Some(first),
+        };
+        let mut conditions = vec![first];
+        loop {
+            conditions.push(self.parse_supports_in_parens()?);
+            if !self.consume_keyword(combinator) {
+                break;
+            }
+        }
+        Some(match combinator {
+            "and" => SupportsCondition::And(conditions),
+            _ => SupportsCondition::Or(conditions),
+        })
+    }
+
+    /// <supports-in-parens> := "(" (<supports-condition> | <declaration>) ")"
+    ///                       | <general-enclosed>
+    fn parse_supports_in_parens(&mut self) -> Option<SupportsCondition> {
+        self.consume_whitespace();
+        // Functions such as `selector()` aren't supported.
+        let start = self.pos;
+        let function = self.parse_identifier();
+        if !function.is_empty() && self.peek() != Some('(') {
+            self.pos = start;
+            return None;
+        }
+        let contents = self.parse_parenthesized()?;
+        self.consume_whitespace();
+        if !function.is_empty() {
+            return Some(SupportsCondition::Unknown);
+        }
+
+        let mut parser = Parser::new(contents.clone());
+        if parser.peek() == Some('(') || parser.consume_keyword("not") {
+            parser.pos = 0;
+            let condition = parser.parse_supports_condition();
+            return Some(
+                condition
+                    .filter(|_| parser.eof())
+                    .unwrap_or(SupportsCondition::Unknown),
+            );
+        }
+        let condition = match contents.split_once(':') {
+            Some((name, value))
+                if !name.trim().is_empty()
+                    && name
+                        .trim()
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') =>
+            {
+                SupportsCondition::Declaration(
+                    name.trim().to_ascii_lowercase(),
+                    value.trim().to_string(),
+                )
+            }
+            _ => SupportsCondition::Unknown,
+        };
+        Some(condition)
+    }
+}
+
+impl SupportsCondition {
+    /// Evaluate the condition, given whether each declaration is supported,
+    /// i.e. whether the engine knows its property and the value is valid for
+    /// it. A declaration with an empty value isn't.
+    /// spec: https://www.w3.org/TR/css-conditional-3/#support-definition
+    pub fn evaluate(&self, supports_declaration: &dyn Fn(&str, &str) -> bool) -> bool {
+        match self {
+            SupportsCondition::Declaration(name, value) => {
+                !value.is_empty() && supports_declaration(name, value)
+            }
+            SupportsCondition::Not(condition) => !condition.evaluate(supports_declaration),
+            SupportsCondition::And(conditions) => conditions
+                .iter()
+                .all(|condition| condition.evaluate(supports_declaration)),
+            SupportsCondition::Or(conditions) => conditions
+                .iter()
+                .any(|condition| condition.evaluate(supports_declaration)),
+            SupportsCondition::Unknown => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(condition: &str) -> SupportsCondition {
+        let mut parser = Parser::new(format!("{} {{", condition));
+        parser.parse_supports_prelude()
+    }
+
+    fn declaration(name: &str, value: &str) -> SupportsCondition {
+        SupportsCondition::Declaration(name.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn parse_supports_prelude() {
+        assert_eq!(
+            parse("(display: flow-root)"),
+            declaration("display", "flow-root")
+        );
+        assert_eq!(
+            parse("not (Color: red) or (x: y)"),
+            SupportsCondition::Unknown,
+        );
+        assert_eq!(
+            parse("(a: 1) AND ((b: 2) or (not (c: 3)))"),
+            SupportsCondition::And(vec![
+                declaration("a", "1"),
+                SupportsCondition::Or(vec![
+                    declaration("b", "2"),
+                    SupportsCondition::Not(Box::new(declaration("c", "3"))),
+                ]),
+            ]),
+        );
+        assert_eq!(
+            parse("selector(a > b) or (a b)"),
+            SupportsCondition::Or(vec![SupportsCondition::Unknown, SupportsCondition::Unknown]),
+        );
+        assert_eq!(
+            parse("(a: 1) and (b: 2) or (c: 3)"),
+            SupportsCondition::Unknown
+        );
+        assert_eq!(parse("a: 1"), SupportsCondition::Unknown);
+    }
+
+    #[test]
+    fn evaluate() {
+        let evaluate = |condition| {
+            parse(condition).evaluate(&|name, value| name == "color" && value != "notacolor")
+        };
+        assert!(evaluate("(color: red)"));
+        assert!(!evaluate("(grid-template-areas: none)"));
+        assert!(!evaluate("(color: )"));
+        assert!(!evaluate("(color: notacolor)"));
+        assert!(evaluate("not (color: notacolor)"));
+        assert!(evaluate("not (float: left)"));
+        assert!(evaluate("(float: left) or (color: red)"));
+        assert!(!evaluate("(float: left) and (color: red)"));
+        assert!(!evaluate("selector(a > b)"));
+    }
+}
@@ -0,0 +1,487 @@
+//! Tokenization of CSS: comments, strings, `url()`, numbers and identifiers
+//! with escapes. The parser consumes tokens where a character isn't enough.
+//! spec: https://www.w3.org/TR/css-syntax-3/#tokenization
+
+use super::{Parser, Token};
+
+/// Split `source` into tokens. Comments are dropped.
+pub fn tokenize(source: &str) -> Vec<Token> {
+    let mut parser = Parser::new(preprocess(source));
+    std::iter::from_fn(|| parser.next_token()).collect()
+}
+
+/// Normalize newlines to `\n`, and replace NUL characters.
+/// spec: https://www.w3.org/TR/css-syntax-3/#input-preprocessing
+pub(super) fn preprocess(source: &str) -> String {
+    source
+        .replace("\r\n", "\n")
+        .replace(['\r', '\x0c'], "\n")
+        .replace('\0', "\u{fffd}")
+}
+
+/// Return true if `c` may start an identifier. Non-ASCII characters may.
+/// spec: https://www.w3.org/TR/css-syntax-3/#ident-start-code-point
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || !c.is_ascii()
+}
+
+/// spec: https://www.w3.org/TR/css-syntax-3/#ident-code-point
+fn is_name(c: char) -> bool {
+    is_name_start(c) || c.is_ascii_digit() || c == '-'
+}
+
+/// Return true if the two characters are a backslash and a character it
+/// escapes.
+/// spec: https://www.w3.org/TR/css-syntax-3/#starts-with-a-valid-escape
+fn is_valid_escape(first: Option<char>, second: Option<char>) -> bool {
+    first == Some('\\') && second != Some('\n')
+}
+
+/// Return true if the three characters start an identifier.
+/// spec: https://www.w3.org/TR/css-syntax-3/#would-start-an-identifier
+fn starts_identifier(first: Option<char>, second: Option<char>, third: Option<char>) -> bool {
+    match first {
+        Some('-') => {
+            second.is_some_and(|c| is_name_start(c) || c == '-') || is_valid_escape(second, third)
+        }
+        Some('\\') => is_valid_escape(first, second),
+        Some(c) => is_name_start(c),
+        None => false,
+    }
+}
+
+impl Parser {
+    /// Consume the next token, skipping the comments before it. Return
+    /// `None` at the end of the input.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-token
+    pub(super) fn next_token(&mut self) -> Option<Token> {
+        while self.consume_comment() {}
+        let [first, second, third] = self.lookahead();
+        let token = match first? {
+            c if c.is_whitespace() => {
+                self.consume_while(char::is_whitespace);
+                Token::Whitespace
+            }
+            quote @ ('"' | '\'') => {
+                self.consume_char();
+                self.consume_string(quote)
+            }
+            '#' if second.is_some_and(is_name) || is_valid_escape(second, third) => {
+                self.consume_char();
+                let is_id = self.starts_identifier();
+                Token::Hash(self.consume_name(), is_id)
+            }
+            '+' | '-' | '.' | '0'..='9' if self.starts_number() => self.consume_numeric(),
+            '-' if second == Some('-') && third == Some('>') => {
+                self.pos += 3;
+                Token::Cdc
+            }
+            '<' if self.input[self.pos..].starts_with("<!--") => {
+                self.pos += 4;
+                Token::Cdo
+            }
+            '@' if starts_identifier(second, third, self.input[self.pos..].chars().nth(3)) => {
+                self.consume_char();
+                Token::AtKeyword(self.consume_name())
+            }
+            _ if self.starts_identifier() => self.consume_ident_like(),
+            c => {
+                self.consume_char();
+                match c {
+                    '(' => Token::OpenParen,
+                    ')' => Token::CloseParen,
+                    '[' => Token::OpenBracket,
+                    ']' => Token::CloseBracket,
+                    '{' => Token::OpenBrace,
+                    '}' => Token::CloseBrace,
+                    ',' => Token::Comma,
+                    ':' => Token::Colon,
+                    ';' => Token::Semicolon,
+                    c => Token::Delim(c),
+                }
+            }
+        };
+        Some(token)
+    }
+
+    /// Consume a comment, e.g. `/* a */`, returning false if there's none.
+    /// An unterminated comment runs to the end of the input.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-comment
+    pub(super) fn consume_comment(&mut self) -> bool {
+        if !self.input[self.pos..].starts_with("/*") {
+            return false;
+        }
+        self.pos = match self.input[self.pos + 2..].find("*/") {
+            Some(i) => self.pos + 2 + i + 2,
+            None => self.input.len(),
+        };
+        true
+    }
+
+    /// Consume tokens up to one at the top level for which `stop` returns
+    /// true, or the end of the input, skipping over blocks and functions.
+    /// Return their source text without comments.
+    pub(super) fn consume_component_values(&mut self, stop: impl Fn(&Token) -> bool) -> String {
+        let mut text = String::new();
+        let mut depth = 0_usize;
+        loop {
+            while self.consume_comment() {}
+            let start = self.pos;
+            let Some(token) = self.next_token() else {
+                break;
+            };
+            match token {
+                _ if depth == 0 && stop(&token) => {
+                    self.pos = start;
+                    break;
+                }
+                Token::Function(_) | Token::OpenParen | Token::OpenBracket | Token::OpenBrace => {
+                    depth += 1
+                }
+                Token::CloseParen | Token::CloseBracket | Token::CloseBrace => {
+                    depth = depth.saturating_sub(1)
+                }
+                _ => {}
+            }
+            text += &self.input[start..self.pos];
+        }
+        text
+    }
+
+    /// Return true if an identifier starts at the next character.
+    pub(super) fn starts_identifier(&self) -> bool {
+        let [first, second, third] = self.lookahead();
+        starts_identifier(first, second, third)
+    }
+
+    /// Consume a name, i.e. the characters of an identifier, with its escapes
+    /// resolved.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-name
+    pub(super) fn consume_name(&mut self) -> String {
+        let mut name = String::new();
+        loop {
+            let [first, second, _] = self.lookahead();
+            match first {
+                Some(c) if is_name(c) => name.push(self.consume_char()),
+                _ if is_valid_escape(first, second) => {
+                    self.consume_char();
+                    name.push(self.consume_escape());
+                }
+                _ => return name,
+            }
+        }
+    }
+
+    /// Consume an escape after its backslash: up to 6 hex digits and a
+    /// whitespace character, or any other character.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-escaped-code-point
+    fn consume_escape(&mut self) -> char {
+        let Some(c) = self.peek() else {
+            return '\u{fffd}';
+        };
+        if !c.is_ascii_hexdigit() {
+            return self.consume_char();
+        }
+        let mut digits = String::new();
+        while digits.len() < 6 && self.peek().is_some_and(|c| c.is_ascii_hexdigit()) {
+            digits.push(self.consume_char());
+        }
+        if self.peek().is_some_and(char::is_whitespace) {
+            self.consume_char();
+        }
+        match u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+        {
+            Some('\0') | None => '\u{fffd}',
+            Some(c) => c,
+        }
+    }
+
+    /// Consume a string after its opening quote, up to the closing one.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-string-token
+    fn consume_string(&mut self, quote: char) -> Token {
+        let mut s = String::new();
+        loop {
+            match self.peek() {
+                // An unterminated string ends at the end of the input.
+                None => return Token::String(s),
+                Some(c) if c == quote => {
+                    self.consume_char();
+                    return Token::String(s);
+                }
+                // The newline is left for the next token.
+                Some('\n') => return Token::BadString,
+                Some('\\') => {
+                    self.consume_char();
+                    match self.peek() {
+                        None => {}
+                        // An escaped newline continues the string.
+                        Some('\n') => {
+                            self.consume_char();
+                        }
+                        Some(_) => s.push(self.consume_escape()),
+                    }
+                }
+                Some(_) => s.push(self.consume_char()),
+            }
+        }
+    }
+
+    /// Consume a number, a percentage or a dimension, e.g. `1.5em`.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-numeric-token
+    fn consume_numeric(&mut self) -> Token {
+        let number = self.parse_number();
+        if self.starts_identifier() {
+            Token::Dimension(number, self.consume_name())
+        } else if self.peek() == Some('%') {
+            self.consume_char();
+            Token::Percentage(number)
+        } else {
+            Token::Number(number)
+        }
+    }
+
+    /// Consume an identifier, a function or a `url()`.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-ident-like-token
+    fn consume_ident_like(&mut self) -> Token {
+        let name = self.consume_name();
+        if self.peek() != Some('(') {
+            return Token::Ident(name);
+        }
+        self.consume_char();
+        if !name.eq_ignore_ascii_case("url") {
+            return Token::Function(name);
+        }
+        // A quoted URL is an argument of the function `url(`.
+        let rest = self.input[self.pos..].trim_start();
+        if rest.starts_with(['"', '\'']) {
+            return Token::Function(name);
+        }
+        self.consume_url()
+    }
+
+    /// Consume an unquoted URL after `url(`, up to the `)`.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-url-token
+    fn consume_url(&mut self) -> Token {
+        let mut url = String::new();
+        self.consume_while(char::is_whitespace);
+        loop {
+            let [first, second, _] = self.lookahead();
+            match first {
+                None => return Token::Url(url),
+                Some(')') => {
+                    self.consume_char();
+                    return Token::Url(url);
+                }
+                Some(c) if c.is_whitespace() => {
+                    self.consume_while(char::is_whitespace);
+                    if matches!(self.peek(), None | Some(')')) {
+                        continue;
+                    }
+                    self.consume_bad_url();
+                    return Token::BadUrl;
+                }
+                Some('"' | '\'' | '(') => {
+                    self.consume_bad_url();
+                    return Token::BadUrl;
+                }
+                Some(c) if c.is_control() && c != '\t' => {
+                    self.consume_bad_url();
+                    return Token::BadUrl;
+                }
+                Some('\\') if is_valid_escape(first, second) => {
+                    self.consume_char();
+                    url.push(self.consume_escape());
+                }
+                Some('\\') => {
+                    self.consume_bad_url();
+                    return Token::BadUrl;
+                }
+                Some(_) => url.push(self.consume_char()),
+            }
+        }
+    }
+
+    /// Consume the rest of an invalid `url()`, up to its `)`.
+    /// spec: https://www.w3.org/TR/css-syntax-3/#consume-remnants-of-bad-url
+    fn consume_bad_url(&mut self) {
+        loop {
+            let [first, second, _] = self.lookahead();
+            match first {
+                None => return,
+                Some(')') => {
+                    self.consume_char();
+                    return;
+                }
+                _ if is_valid_escape(first, second) => {
+                    self.consume_char();
+                    self.consume_escape();
+                }
+                Some(_) => {
+                    self.consume_char();
+                }
+            }
+        }
+    }
+
+    /// Return the next three characters, `None` past the end of the input.
+    fn lookahead(&self) -> [Option<char>; 3] {
+        let mut chars = self.input[self.pos..].chars();
+        [chars.next(), chars.next(), chars.next()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ident(name: &str) -> Token {
+        Token::Ident(name.to_string())
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            tokenize("@media screen{a#b.c:hover{width:calc(50% - 1.5em)!important}}"),
+            [
+                Token::AtKeyword("media".to_string()),
+                Token::Whitespace,
+                ident("screen"),
+                Token::OpenBrace,
+                ident("a"),
+                Token::Hash("b".to_string(), true),
+                Token::Delim('.'),
+                ident("c"),
+                Token::Colon,
+                ident("hover"),
+                Token::OpenBrace,
+                ident("width"),
+                Token::Colon,
+                Token::Function("calc".to_string()),
+                Token::Percentage(50.0),
+                Token::Whitespace,
+                Token::Delim('-'),
+                Token::Whitespace,
+                Token::Dimension(1.5, "em".to_string()),
+                Token::CloseParen,
+                Token::Delim('!'),
+                ident("important"),
+                Token::CloseBrace,
+                Token::CloseBrace,
+            ]
+        );
+        assert_eq!(
+            tokenize("#123 -1 +.5e1 --x -->, <!--;"),
+            [
+                Token::Hash("123".to_string(), false),
+                Token::Whitespace,
+                Token::Number(-1.0),
+                Token::Whitespace,
+                Token::Number(5.0),
+                Token::Whitespace,
+                ident("--x"),
+                Token::Whitespace,
+                Token::Cdc,
+                Token::Comma,
+                Token::Whitespace,
+                Token::Cdo,
+                Token::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            tokenize("a/* b { c: d } */e /**/ /* unterminated"),
+            [ident("a"), ident("e"), Token::Whitespace, Token::Whitespace]
+        );
+        assert_eq!(tokenize("/* a */ /* b */"), [Token::Whitespace]);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            tokenize(
+                r#""a\"b" 'c"\27' "\
+d" "e"#
+            ),
+            [
+                Token::String("a\"b".to_string()),
+                Token::Whitespace,
+                Token::String("c\"'".to_string()),
+                Token::Whitespace,
+                Token::String("d".to_string()),
+                Token::Whitespace,
+                Token::String("e".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokenize("'a\nb'"),
+            [
+                Token::BadString,
+                Token::Whitespace,
+                ident("b"),
+                Token::String("".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            tokenize(r"url( a/b\).png ) URL('c') url(d e) url(f"),
+            [
+                Token::Url("a/b).png".to_string()),
+                Token::Whitespace,
+                Token::Function("URL".to_string()),
+                Token::String("c".to_string()),
+                Token::CloseParen,
+                Token::Whitespace,
+                Token::BadUrl,
+                Token::Whitespace,
+                Token::Url("f".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(
+            // The whitespace after a hex escape is part of it.
+            tokenize(r"\31 0 a\:b \E9t\E9  -\-x \0"),
+            [
+                ident("10"),
+                Token::Whitespace,
+                ident("a:b"),
+                Token::Whitespace,
+                ident("été"),
+                Token::Whitespace,
+                ident("--x"),
+                Token::Whitespace,
+                ident("\u{fffd}"),
+            ]
+        );
+        assert_eq!(
+            tokenize("été .日本 #ü 2π"),
+            [
+                ident("été"),
+                Token::Whitespace,
+                Token::Delim('.'),
+                ident("日本"),
+                Token::Whitespace,
+                Token::Hash("ü".to_string(), true),
+                Token::Whitespace,
+                Token::Dimension(2.0, "π".to_string()),
+            ]
+        );
+        // A backslash before a newline isn't an escape.
+        assert_eq!(tokenize("\\\n"), [Token::Delim('\\'), Token::Whitespace]);
+    }
+
+    #[test]
+    fn preprocess() {
+        assert_eq!(super::preprocess("a\r\nb\rc\x0cd\0"), "a\nb\nc\nd\u{fffd}");
+    }
+}
@@ -2,10 +2,39 @@
 
 use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub struct Node {
+    /// Changes made here directly aren't tracked, unlike those made with
+    /// `append_child`, `insert_child` and `remove_child`.
     pub children: Vec<Node>,
     pub node_type: NodeType,
+
+    /// The changes to the node since it was last styled.
+    pub(crate) changes: Changes,
+    /// Set when the node has to be restyled, see `style::Restyler`.
+    pub(crate) dirty: bool,
+}
+
+/// Nodes with the same type and children are equal, whatever changes are
+/// pending for styling.
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        (&self.node_type, &self.children) == (&other.node_type, &other.children)
+    }
+}
+
+/// The changes to a node which may change the style of it or of the nodes
+/// around it.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct Changes {
+    /// Classes added or removed.
+    pub(crate) classes: HashSet<String>,
+    /// The old and the new id, if the id changed.
+    pub(crate) ids: HashSet<String>,
+    /// Set if the `style` attribute changed.
+    pub(crate) style: bool,
+    /// Set if children were added or removed.
+    pub(crate) children: bool,
 }
 
 #[derive(Debug, PartialEq)]
@@ -26,6 +55,8 @@ pub fn text(data: String) -> Node {
     Node {
         children: Vec::new(),
         node_type: NodeType::Text(data),
+        changes: Changes::default(),
+        dirty: false,
     }
 }
 
@@ -36,10 +67,70 @@ pub fn elem(name: String, attrs: AttrMap, children: Vec<Node>) -> Node {
             tag_name: name,
             attributes: attrs,
         }),
+        changes: Changes::default(),
+        dirty: false,
+    }
+}
+
+impl Node {
+    /// Set the attribute `name` of an element to `value`, or remove it if
+    /// `value` is `None`.
+    pub fn set_attribute(&mut self, name: &str, value: Option<String>) {
+        let NodeType::Element(ref mut elem) = self.node_type else {
+            panic!("Expected an element, but got a text node.");
+        };
+        let old = match value {
+            Some(value) => elem.attributes.insert(name.to_string(), value),
+            None => elem.attributes.remove(name),
+        };
+        let new = elem.attributes.get(name);
+        if old.as_ref() == new {
+            return;
+        }
+        let changes = &mut self.changes;
+        match name {
+            "class" => {
+                let words = |value: Option<&String>| -> HashSet<String> {
+                    value
+                        .into_iter()
+                        .flat_map(|classes| classes.split(' '))
+                        .map(str::to_string)
+                        .collect()
+                };
+                let (old, new) = (words(old.as_ref()), words(new));
+                changes
+                    .classes
+                    .extend(old.symmetric_difference(&new).cloned());
+            }
+            "id" => changes.ids.extend(old.into_iter().chain(new.cloned())),
+            "style" => changes.style = true,
+            _ => {}
+        }
+    }
+
+    /// Add `child` after the children of this node.
+    pub fn append_child(&mut self, child: Node) {
+        self.insert_child(self.children.len(), child);
+    }
+
+    /// Insert `child` among the children of this node, at `index`.
+    pub fn insert_child(&mut self, index: usize, child: Node) {
+        self.children.insert(index, child);
+        self.changes.children = true;
+    }
+
+    /// Remove the child at `index`, and return it.
+    pub fn remove_child(&mut self, index: usize) -> Node {
+        self.changes.children = true;
+        self.children.remove(index)
     }
 }
 
 impl ElementData {
+    pub fn attr(&self, name: &str) -> Option<&String> {
+        self.attributes.get(name)
+    }
+
     pub fn id(&self) -> Option<&String> {
         self.attributes.get("id")
     }
@@ -92,3 +183,42 @@ mod element_data_tests {
         assert_eq!(elem.classes(), HashSet::from([]));
     }
 }
+
+#[cfg(test)]
+mod node_tests {
+    use super::*;
+
+    #[test]
+    fn changes() {
+        let attrs = HashMap::from([("class".to_string(), "a b".to_string())]);
+        let mut node = elem("div".to_string(), attrs, vec![]);
+        node.set_attribute("class", Some("b c".to_string()));
+        node.set_attribute("id", Some("x".to_string()));
+        node.set_attribute("id", Some("y".to_string()));
+        node.set_attribute("title", Some("t".to_string()));
+        assert_eq!(
+            node.changes,
+            Changes {
+                classes: HashSet::from(["a".to_string(), "c".to_string()]),
+                ids: HashSet::from(["x".to_string(), "y".to_string()]),
+                style: false,
+                children: false,
+            }
+        );
+
+        let mut node = elem("div".to_string(), AttrMap::new(), vec![]);
+        node.set_attribute("class", None);
+        assert_eq!(node.changes, Changes::default());
+        node.append_child(text("x".to_string()));
+        node.set_attribute("style", Some("color: red".to_string()));
+        assert!(node.changes.children && node.changes.style);
+        assert_eq!(node.remove_child(0), text("x".to_string()));
+
+        // Pending changes don't make nodes different.
+        node.dirty = true;
+        let mut other = elem("div".to_string(), AttrMap::new(), vec![]);
+        other.set_attribute("style", Some("color: red".to_string()));
+        assert_eq!(node, other);
+        assert_ne!(node.changes, other.changes);
+    }
+}
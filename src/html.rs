@@ -71,10 +71,7 @@ impl Parser {
 
     /// Parse a tag or attribute name.
     fn parse_tag_name(&mut self) -> String {
-        self.consume_while(|c| match c {
-            'a'..='z' | 'A'..='Z' | '0'..='9' => true,
-            _ => false,
-        })
+        self.consume_while(|c| c.is_ascii_alphanumeric())
     }
 
     /// Parse a list of name="value" pairs, separated by whitespace.
@@ -1,26 +1,55 @@
 //! This module controls the layout step, building a layout tree from a style
 //! tree.
 
-use crate::css::{Unit, Value};
-use crate::style::{Display, StyledNode};
+use crate::css::{Device, LengthContext, Unit, Value};
+use crate::dom::Node;
+use crate::style::{ComputedStyle, Display, DisplayInside, StyledNode};
+
+mod inline;
 
 #[derive(Debug)]
 pub struct LayoutBox<'a> {
     pub dimensions: Dimensions,
     pub box_type: BoxType<'a>,
     pub children: Vec<LayoutBox<'a>>,
+    /// The fragments of an inline box, one for each line it is on. For a
+    /// text box, they hold the text on each line.
+    pub fragments: Vec<Fragment>,
 }
 
 #[derive(Debug)]
 pub enum BoxType<'a> {
+    /// A block-level box, e.g. of `display: block`, `flow-root` or
+    /// `list-item`. Flex, grid and table containers and the boxes inside
+    /// tables are laid out like blocks for now.
     BlockNode(&'a StyledNode<'a>),
+    /// An inline box, of `display: inline`, whose content is laid out in the
+    /// inline formatting context it is in.
     InlineNode(&'a StyledNode<'a>),
+    /// An inline-level box laid out as a whole, e.g. of `display:
+    /// inline-block` or `inline-flex`.
+    /// spec: https://www.w3.org/TR/css-display-3/#atomic-inline
+    InlineBlockNode(&'a StyledNode<'a>),
     /// Anonymous block box, that is automatically inserted by browser.
     /// spec: https://www.w3.org/TR/CSS2/visuren.html#anonymous-block-level
     AnonymousBlock,
 }
 
-#[derive(Debug, Default)]
+/// What a box is laid out against, besides its containing block.
+#[derive(Clone, Copy, Debug)]
+struct LayoutContext<'a> {
+    /// Lengths of the parent box. The font size of a box is resolved against
+    /// these.
+    lengths: LengthContext,
+    /// The height of the containing block, if it doesn't depend on its
+    /// content. Percentage heights are only resolved against such a height.
+    containing_height: Option<f32>,
+    /// The style of the nearest block container, which anonymous blocks
+    /// inherit from.
+    container: Option<&'a StyledNode<'a>>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
 pub struct Dimensions {
     /// Position of the content area relative to the document origin.
     pub content: Rect,
@@ -42,7 +71,7 @@ pub struct Rect {
     pub height: f32,
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Copy, Debug, Default)]
 pub struct EdgeSizes {
     pub left: f32,
     pub right: f32,
@@ -50,21 +79,85 @@ pub struct EdgeSizes {
     pub bottom: f32,
 }
 
+/// The part of an inline box, or of its text, on one line box.
+/// spec: https://www.w3.org/TR/css-break-3/#box-fragment
+#[derive(Debug)]
+pub struct Fragment {
+    pub dimensions: Dimensions,
+    /// The text on the line, for a text box.
+    pub text: Option<String>,
+}
+
 impl<'a> LayoutBox<'a> {
     fn new(box_type: BoxType) -> LayoutBox {
         LayoutBox {
             box_type,
             dimensions: Default::default(),
             children: Vec::new(),
+            fragments: Vec::new(),
         }
     }
 
     fn get_style_node(&self) -> &'a StyledNode<'a> {
         match self.box_type {
-            BoxType::BlockNode(node) | BoxType::InlineNode(node) => node,
+            BoxType::BlockNode(node)
+            | BoxType::InlineNode(node)
+            | BoxType::InlineBlockNode(node) => node,
             BoxType::AnonymousBlock => panic!("Anonymous block box has no style node."),
         }
     }
+
+    /// Find the box of the DOM node `node` in this tree. Boxes generated by
+    /// pseudo-elements are skipped.
+    pub fn find(&self, node: &Node) -> Option<&LayoutBox<'a>> {
+        match self.box_type {
+            BoxType::BlockNode(style)
+            | BoxType::InlineNode(style)
+            | BoxType::InlineBlockNode(style) => {
+                if style.pseudo_element.is_some() {
+                    return None;
+                }
+                if std::ptr::eq(style.node(), node) {
+                    return Some(self);
+                }
+            }
+            BoxType::AnonymousBlock => {}
+        }
+        self.children.iter().find_map(|child| child.find(node))
+    }
+
+    /// The resolved style of this box, like `getComputedStyle`: the computed
+    /// style of its node, with the used values of the sizes of a laid out
+    /// block box. Anonymous boxes have no node, so they have none.
+    /// spec: https://www.w3.org/TR/cssom-1/#resolved-values
+    pub fn computed_style(&self, device: &Device) -> Option<ComputedStyle> {
+        let mut style = match self.box_type {
+            BoxType::BlockNode(node)
+            | BoxType::InlineNode(node)
+            | BoxType::InlineBlockNode(node) => node.computed_style(device),
+            BoxType::AnonymousBlock => return None,
+        };
+        if let BoxType::BlockNode(_) = self.box_type {
+            let d = &self.dimensions;
+            style.set_used_value("width", d.content.width);
+            style.set_used_value("height", d.content.height);
+            for (name, edges) in [
+                ("margin-{}", &d.margin),
+                ("padding-{}", &d.padding),
+                ("border-{}-width", &d.border),
+            ] {
+                for (side, px) in [
+                    ("top", edges.top),
+                    ("right", edges.right),
+                    ("bottom", edges.bottom),
+                    ("left", edges.left),
+                ] {
+                    style.set_used_value(&name.replace("{}", side), px);
+                }
+            }
+        }
+        Some(style)
+    }
 }
 
 /// Transform a style tree int a layout tree.
@@ -72,11 +165,25 @@ pub fn layout_tree<'a>(
     node: &'a StyledNode<'a>,
     containing_block: &mut Dimensions,
 ) -> LayoutBox<'a> {
+    // Viewport-percentage lengths refer to the initial containing block.
+    let viewport = containing_block.content;
+
     // The layout algorithm expects the container height to start at 0.
     containing_block.content.height = 0.0;
 
+    let mut lengths = LengthContext::new(viewport.width, viewport.height);
+    // `rem` refers to the font size of the root element.
+    lengths.root_font_size = node.font_size(&lengths);
+
     let mut root = build_layout_tree(node);
-    root.layout(containing_block);
+    root.layout(
+        containing_block,
+        LayoutContext {
+            lengths,
+            containing_height: Some(viewport.height),
+            container: None,
+        },
+    );
     root
 }
 
@@ -84,59 +191,90 @@ pub fn layout_tree<'a>(
 pub fn build_layout_tree<'a>(style_node: &'a StyledNode<'a>) -> LayoutBox<'a> {
     // Create the root box.
     let mut root = LayoutBox::new(match style_node.display() {
-        Display::Block => BoxType::BlockNode(style_node),
-        Display::Inline => BoxType::InlineNode(style_node),
         Display::None => panic!("Root node has display: none."),
+        Display::Contents => panic!("Root node has display: contents."),
+        display if display.is_block_level() => BoxType::BlockNode(style_node),
+        Display::Box {
+            inside: DisplayInside::Flow,
+            ..
+        } => BoxType::InlineNode(style_node),
+        _ => BoxType::InlineBlockNode(style_node),
     });
+    build_children(&mut root, style_node);
+    root
+}
 
-    // Create the descendant boxes.
-    for child in &style_node.children {
+/// Create the boxes of the children of `style_node` in `parent`, including the
+/// boxes generated by pseudo-elements.
+fn build_children<'a>(parent: &mut LayoutBox<'a>, style_node: &'a StyledNode<'a>) {
+    for child in style_node.box_children() {
         match child.display() {
-            Display::Block => root.children.push(build_layout_tree(child)),
-            Display::Inline => {
+            Display::None => {
+                // Skip nodes with `display: none`.
+            }
+            Display::Contents => {
+                // The element has no box, and its children take its place.
+                // spec: https://www.w3.org/TR/css-display-3/#box-generation
+                build_children(parent, child);
+            }
+            display if display.is_block_level() => parent.children.push(build_layout_tree(child)),
+            _ => {
                 // Put inline boxes into an anonymous box.
                 //
                 // NOTE: This is intentionally simplified. E.g., it generates an unnecessary
                 // box if a block-level node has only inline children.
-                root.get_inline_container()
+                parent
+                    .get_inline_container()
                     .children
                     .push(build_layout_tree(child))
             }
-            Display::None => {
-                // Skip nodes with `display: none`.
-            }
         }
     }
-    root
 }
 
 impl<'a> LayoutBox<'a> {
     /// Lay out a box and its descendants.
-    fn layout(&mut self, containing_block: &Dimensions) {
+    fn layout(&mut self, containing_block: &Dimensions, context: LayoutContext<'a>) {
         match self.box_type {
-            BoxType::BlockNode(_) => self.layout_block(containing_block),
-            BoxType::InlineNode(_) | BoxType::AnonymousBlock => {} // TODO
+            BoxType::BlockNode(_) | BoxType::InlineBlockNode(_) => {
+                self.layout_block(containing_block, context)
+            }
+            BoxType::AnonymousBlock => self.layout_inline(containing_block, context),
+            BoxType::InlineNode(_) => {
+                panic!("Inline boxes are laid out by the anonymous block containing them.")
+            }
         }
     }
 
     /// Lay out a block-level element and its descendants.
-    fn layout_block(&mut self, containing_block: &Dimensions) {
+    fn layout_block(&mut self, containing_block: &Dimensions, context: LayoutContext<'a>) {
+        // Relative lengths of this box are resolved against its own font size.
+        let lengths = LengthContext {
+            font_size: self.get_style_node().font_size(&context.lengths),
+            ..context.lengths
+        };
+
         // Child width can depend on parent width, so we need to calculate
         // this box's width before laying out its children.
-        self.calculate_block_width(containing_block);
+        self.calculate_block_width(containing_block, &lengths);
 
         // Determine where the box is located within its container.
-        self.calculate_block_position(containing_block);
+        self.calculate_block_position(containing_block, &lengths);
 
         // Recursively lay out the children of this box.
-        self.layout_block_children();
+        let height = self.specified_height(&lengths, context.containing_height);
+        self.layout_block_children(LayoutContext {
+            lengths,
+            containing_height: height,
+            container: Some(self.get_style_node()),
+        });
 
         // Parent height can depend on child height, so `calculate_height`
         // must be called *after* the children are laid out.
-        self.calculate_block_height();
+        self.calculate_block_height(height);
     }
 
-    fn calculate_block_width(&mut self, containing_block: &Dimensions) {
+    fn calculate_block_width(&mut self, containing_block: &Dimensions, lengths: &LengthContext) {
         let style = self.get_style_node();
 
         // Check CSS `width` property.
@@ -144,15 +282,30 @@ impl<'a> LayoutBox<'a> {
         let auto = Value::Keyword("auto".to_string());
         let mut width = style.value("width").unwrap_or(auto.clone());
 
+        // Math functions can only be resolved once the containing block is
+        // known, as they may mix percentages with other units.
+        let resolve = |value: Value| match value {
+            Value::Calc(calc) => Value::Length(
+                calc.to_px(lengths, containing_block.content.width),
+                Unit::Px,
+            ),
+            value => value,
+        };
+        // The result of a math function is clamped to the allowed range.
+        // spec: https://www.w3.org/TR/css-values-4/#calc-range
+        if matches!(width, Value::Calc(_)) {
+            width = non_negative(resolve(width));
+        }
+
         // Check all the left and right edge sizes.
         // margin, border, and padding have initial value 0.
         let zero = Value::Length(0.0, Unit::Px);
-        let mut margin_left = style.lookup("margin-left", "margin", &zero);
-        let mut margin_right = style.lookup("margin-right", "margin", &zero);
-        let border_left = style.lookup("border-left", "border", &zero);
-        let border_right = style.lookup("border-right", "border", &zero);
-        let padding_left = style.lookup("padding-left", "padding", &zero);
-        let padding_right = style.lookup("padding-right", "padding", &zero);
+        let mut margin_left = resolve(style.value_or("margin-left", &zero));
+        let mut margin_right = resolve(style.value_or("margin-right", &zero));
+        let border_left = border_width(style, "left");
+        let border_right = border_width(style, "right");
+        let padding_left = non_negative(resolve(style.value_or("padding-left", &zero)));
+        let padding_right = non_negative(resolve(style.value_or("padding-right", &zero)));
 
         let total: f32 = [
             &width,
@@ -164,7 +317,7 @@ impl<'a> LayoutBox<'a> {
             &padding_right,
         ]
         .iter()
-        .map(|v| v.to_px())
+        .map(|v| v.to_px(lengths, containing_block.content.width))
         .sum();
 
         // If width is not auto and the total is wider than the container,
@@ -187,7 +340,10 @@ impl<'a> LayoutBox<'a> {
             // If the values are overconstrained, calculate margin-right to fit
             // container's width.
             (false, false, false) => {
-                margin_right = Value::Length(margin_right.to_px() + underflow, Unit::Px);
+                margin_right = Value::Length(
+                    margin_right.to_px(lengths, containing_block.content.width) + underflow,
+                    Unit::Px,
+                );
             }
 
             // If exactly one size is auto, its used value follows from the equality.
@@ -219,40 +375,50 @@ impl<'a> LayoutBox<'a> {
                 } else {
                     // Width can't be negative. Adjust the right margin instead.
                     width = Value::Length(0.0, Unit::Px);
-                    margin_right = Value::Length(margin_right.to_px() + underflow, Unit::Px);
+                    margin_right = Value::Length(
+                        margin_right.to_px(lengths, containing_block.content.width) + underflow,
+                        Unit::Px,
+                    );
                 }
             }
         }
 
         let d = &mut self.dimensions;
 
-        d.content.width = width.to_px();
+        d.content.width = width.to_px(lengths, containing_block.content.width);
 
-        d.padding.left = padding_left.to_px();
-        d.padding.right = padding_right.to_px();
+        d.padding.left = padding_left.to_px(lengths, containing_block.content.width);
+        d.padding.right = padding_right.to_px(lengths, containing_block.content.width);
 
-        d.border.left = border_left.to_px();
-        d.border.right = border_right.to_px();
+        d.border.left = border_left.to_px(lengths, containing_block.content.width);
+        d.border.right = border_right.to_px(lengths, containing_block.content.width);
 
-        d.margin.left = margin_left.to_px();
-        d.margin.right = margin_right.to_px();
+        d.margin.left = margin_left.to_px(lengths, containing_block.content.width);
+        d.margin.right = margin_right.to_px(lengths, containing_block.content.width);
     }
 
-    fn calculate_block_position(&mut self, containing_block: &Dimensions) {
+    fn calculate_block_position(&mut self, containing_block: &Dimensions, lengths: &LengthContext) {
         let style = self.get_style_node();
         let d = &mut self.dimensions;
 
         // margin, border, and padding have initial value 0.
         let zero = Value::Length(0.0, Unit::Px);
 
-        d.padding.top = style.lookup("padding-top", "padding", &zero).to_px();
-        d.padding.bottom = style.lookup("padding-bottom", "padding", &zero).to_px();
+        d.padding.top = non_negative(style.value_or("padding-top", &zero))
+            .to_px(lengths, containing_block.content.width);
+        d.padding.bottom = non_negative(style.value_or("padding-bottom", &zero))
+            .to_px(lengths, containing_block.content.width);
 
-        d.border.top = style.lookup("border-top", "border", &zero).to_px();
-        d.border.bottom = style.lookup("border-bottom", "border", &zero).to_px();
+        d.border.top = border_width(style, "top").to_px(lengths, containing_block.content.width);
+        d.border.bottom =
+            border_width(style, "bottom").to_px(lengths, containing_block.content.width);
 
-        d.margin.top = style.lookup("margin-top", "margin", &zero).to_px();
-        d.margin.bottom = style.lookup("margin-bottom", "margin", &zero).to_px();
+        d.margin.top = style
+            .value_or("margin-top", &zero)
+            .to_px(lengths, containing_block.content.width);
+        d.margin.bottom = style
+            .value_or("margin-bottom", &zero)
+            .to_px(lengths, containing_block.content.width);
 
         d.content.x = containing_block.content.x + d.margin.left + d.border.left + d.padding.left;
         // Position the box below all the previous boxes in the container.
@@ -263,19 +429,41 @@ impl<'a> LayoutBox<'a> {
             + d.padding.top;
     }
 
-    fn layout_block_children(&mut self) {
+    fn layout_block_children(&mut self, context: LayoutContext<'a>) {
         let d = &mut self.dimensions;
+        // Start from no content, as an inline-block may be laid out twice.
+        d.content.height = 0.0;
         for child in &mut self.children {
-            child.layout(d);
+            child.layout(d, context);
             // Track the height so each child is laid out below the previous content.
-            d.content.height = d.content.height + child.dimensions.margin_box().height;
+            d.content.height += child.dimensions.margin_box().height;
+        }
+    }
+
+    /// The content height set by the `height` property, if it doesn't depend on
+    /// the content. Percentages are relative to the containing block's height,
+    /// and compute to `auto` if that depends on its content.
+    fn specified_height(
+        &self,
+        lengths: &LengthContext,
+        containing_height: Option<f32>,
+    ) -> Option<f32> {
+        match (self.get_style_node().value("height"), containing_height) {
+            (Some(Value::Length(_, Unit::Percent)), None) => None,
+            (Some(Value::Calc(calc)), None) if calc.has_unit(&|unit| unit == Unit::Percent) => None,
+            (Some(height @ (Value::Length(..) | Value::Calc(_))), _) => Some(
+                height
+                    .to_px(lengths, containing_height.unwrap_or(0.0))
+                    .max(0.0),
+            ),
+            _ => None,
         }
     }
 
-    fn calculate_block_height(&mut self) {
+    fn calculate_block_height(&mut self, specified_height: Option<f32>) {
         // If the height is set to an explicit length, use the exact length.
         // Otherwise, just keep the value set by `layout_block_children`.
-        if let Some(Value::Length(h, Unit::Px)) = self.get_style_node().value("height") {
+        if let Some(h) = specified_height {
             self.dimensions.content.height = h;
         }
     }
@@ -284,7 +472,7 @@ impl<'a> LayoutBox<'a> {
     fn get_inline_container(&mut self) -> &mut LayoutBox<'a> {
         match self.box_type {
             BoxType::InlineNode(_) | BoxType::AnonymousBlock => self,
-            BoxType::BlockNode(_) => {
+            BoxType::BlockNode(_) | BoxType::InlineBlockNode(_) => {
                 // If we've just generated an anonymous block box, keep using it.
                 // Otherwise, create a new one.
                 match self.children.last() {
@@ -300,6 +488,34 @@ impl<'a> LayoutBox<'a> {
     }
 }
 
+/// The used width of the border on `side`, which is 0 unless a border style is
+/// set.
+/// spec: https://www.w3.org/TR/css-backgrounds-3/#border-width
+fn border_width(style: &StyledNode, side: &str) -> Value {
+    match style.value(&format!("border-{}-style", side)) {
+        Some(Value::Keyword(s)) if s != "none" && s != "hidden" => {}
+        _ => return Value::Length(0.0, Unit::Px),
+    }
+    let width = match style.value(&format!("border-{}-width", side)) {
+        Some(Value::Keyword(k)) if k == "thin" => 1.0,
+        Some(Value::Keyword(k)) if k == "thick" => 5.0,
+        Some(width @ Value::Length(..)) => return non_negative(width),
+        // The initial value is `medium`.
+        _ => 3.0,
+    };
+    Value::Length(width, Unit::Px)
+}
+
+/// Negative paddings and border widths are invalid, and treated as 0. Unlike
+/// them, margins can be negative.
+/// spec: https://www.w3.org/TR/CSS2/box.html#padding-properties
+fn non_negative(value: Value) -> Value {
+    match value {
+        Value::Length(f, _) if f < 0.0 => Value::Length(0.0, Unit::Px),
+        value => value,
+    }
+}
+
 impl Dimensions {
     fn padding_box(&self) -> Rect {
         Rect {
@@ -340,3 +556,372 @@ impl Dimensions {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{css, html, style};
+
+    /// Lay out `html` styled with `css` in an 800x600 viewport, and pass the
+    /// root box to `check`.
+    fn with_layout(html: &str, css: &str, check: impl FnOnce(&LayoutBox)) {
+        let dom = html::parse(html.to_string());
+        let stylesheet = css::parse(css.to_string());
+        let styled = style::style_tree(&dom, &[stylesheet], &css::Device::new(800.0, 600.0));
+        let mut viewport: Dimensions = Default::default();
+        viewport.content.width = 800.0;
+        viewport.content.height = 600.0;
+        check(&layout_tree(&styled, &mut viewport));
+    }
+
+    #[test]
+    fn shorthands() {
+        with_layout(
+            r#"<html><div class="a"></div><div class="b"></div></html>"#,
+            r#"
+            * { display: block; }
+            .a { margin: 1px 2px 3px; padding: 4px 5px; border: 6px solid red; }
+            .b { border-width: 7px; border-left: thick dotted; }
+            "#,
+            |root| {
+                let a = &root.children[0].dimensions;
+                assert_eq!((a.margin.top, a.margin.right), (1.0, 2.0));
+                assert_eq!((a.margin.bottom, a.margin.left), (3.0, 2.0));
+                assert_eq!((a.padding.top, a.padding.left), (4.0, 5.0));
+                assert_eq!((a.border.top, a.border.left), (6.0, 6.0));
+
+                // Sides without a border style have no border.
+                let b = &root.children[1].dimensions;
+                assert_eq!((b.border.top, b.border.left), (0.0, 5.0));
+            },
+        );
+    }
+
+    #[test]
+    fn math_functions() {
+        with_layout(
+            r#"<html><div class="a"><div class="b"></div><div class="c"></div></div></html>"#,
+            r#"
+            * { display: block; }
+            html { font-size: 10px; }
+            .a { width: calc(100% - 2 * 1em); padding-left: max(5px, 1rem); }
+            .b { width: clamp(100px, 50%, 300px); height: calc(1em + 3px); }
+            .c { width: calc(10px - 50%); margin-left: min(10%, 5vw); }
+            "#,
+            |root| {
+                let a = &root.children[0].dimensions;
+                assert_eq!(a.content.width, 780.0);
+                assert_eq!(a.padding.left, 10.0);
+
+                let b = &root.children[0].children[0].dimensions;
+                assert_eq!(b.content.width, 300.0);
+                assert_eq!(b.content.height, 13.0);
+
+                // A negative width is clamped to zero.
+                let c = &root.children[0].children[1].dimensions;
+                assert_eq!(c.content.width, 0.0);
+                assert_eq!(c.margin.left, 40.0);
+            },
+        );
+    }
+
+    #[test]
+    fn relative_lengths() {
+        with_layout(
+            r#"<html><div class="a"><div class="b"></div></div></html>"#,
+            r#"
+            * { display: block; }
+            html { font-size: 10px; height: 100%; }
+            .a { width: 50%; height: 50%; padding-left: 2em; font-size: 2em; }
+            .b { width: 10vw; height: 3rem; margin-left: 25%; }
+            "#,
+            |root| {
+                let a = &root.children[0].dimensions;
+                assert_eq!(a.content.width, 400.0);
+                assert_eq!(a.content.height, 300.0);
+                assert_eq!(a.padding.left, 40.0);
+
+                let b = &root.children[0].children[0].dimensions;
+                assert_eq!(b.content.width, 80.0);
+                assert_eq!(b.content.height, 30.0);
+                assert_eq!(b.margin.left, 100.0);
+            },
+        );
+    }
+
+    #[test]
+    fn negative_margins() {
+        with_layout(
+            r#"<html><div class="a"></div><div class="b"></div><div class="c"></div></html>"#,
+            r#"
+            * { display: block; }
+            html { padding: 20px; }
+            .a { height: 50px; margin-left: -10px; margin-right: -.5em; }
+            .b { height: 30px; margin-top: -20px; padding-left: -5px; }
+            .c { width: 100px; height: 10px; margin-left: auto; margin-right: -20px; }
+            "#,
+            |root| {
+                // The box is pulled outward, and gets wider.
+                let a = &root.children[0].dimensions;
+                assert_eq!(a.content.x, 10.0);
+                assert_eq!(a.content.width, 760.0 + 10.0 + 8.0);
+
+                // The box overlaps the previous one, and the negative padding
+                // is ignored.
+                let b = &root.children[1].dimensions;
+                assert_eq!(b.content.y, 20.0 + 50.0 - 20.0);
+                assert_eq!(b.padding.left, 0.0);
+
+                let c = &root.children[2].dimensions;
+                assert_eq!(c.content.y, 20.0 + 50.0 - 20.0 + 30.0);
+                assert_eq!(c.content.x, 20.0 + 760.0 + 20.0 - 100.0);
+
+                assert_eq!(root.dimensions.content.height, 70.0);
+            },
+        );
+    }
+
+    #[test]
+    fn percentage_height_of_auto_height_container() {
+        with_layout(
+            r#"<html><div class="a"><div class="b"></div></div></html>"#,
+            r#"
+            * { display: block; }
+            .a { height: auto; }
+            .b { height: 50%; padding-top: 10px; }
+            "#,
+            |root| {
+                // The percentage computes to `auto`, so the height is that of
+                // the (empty) content.
+                let b = &root.children[0].children[0].dimensions;
+                assert_eq!(b.content.height, 0.0);
+                assert_eq!(root.children[0].dimensions.content.height, 10.0);
+            },
+        );
+    }
+
+    #[test]
+    fn used_values() {
+        let html = r#"<html><div class="a"><p id="p"></p><span>x</span></div></html>"#;
+        let dom = html::parse(html.to_string());
+        let stylesheet = css::parse(
+            r#"
+            html, div, p { display: block; }
+            .a { width: 50%; margin: 0 auto; padding: 1em; }
+            p { height: 2em; margin-left: 10%; }
+            p::before { content: "x"; display: block; }
+            "#
+            .to_string(),
+        );
+        let device = css::Device::new(800.0, 600.0);
+        let styled = style::style_tree(&dom, &[stylesheet], &device);
+        let mut viewport: Dimensions = Default::default();
+        viewport.content.width = 800.0;
+        viewport.content.height = 600.0;
+        let root = layout_tree(&styled, &mut viewport);
+
+        let px = |px| Some(Value::Length(px, Unit::Px));
+        let div = &dom.children[0];
+        let a = root.find(div).unwrap().computed_style(&device).unwrap();
+        assert_eq!(a.get("width").cloned(), px(400.0));
+        assert_eq!(a.get("margin-left").cloned(), px(184.0));
+        assert_eq!(a.get("padding-top").cloned(), px(16.0));
+        // The paragraph, with its default margins of 1em, and the line of the
+        // span.
+        assert_eq!(a.get("height").cloned(), px(16.0 + 32.0 + 16.0 + 19.2));
+
+        // The box of the paragraph, not that of its `::before`.
+        let p = root.find(&div.children[0]).unwrap();
+        assert!(matches!(p.box_type, BoxType::BlockNode(s) if s.pseudo_element.is_none()));
+        let p = p.computed_style(&device).unwrap();
+        assert_eq!(p.get("margin-left").cloned(), px(40.0));
+        assert_eq!(p.get("width").cloned(), px(360.0));
+
+        // Sizes don't apply to inline boxes, so they keep their computed
+        // values.
+        let span = root.find(&div.children[1]).unwrap();
+        assert_eq!(
+            span.computed_style(&device).unwrap().get("width"),
+            Some(&Value::Keyword("auto".to_string()))
+        );
+        // The anonymous block around the span has no style.
+        let anonymous = root.find(div).unwrap().children.last().unwrap();
+        assert!(matches!(anonymous.box_type, BoxType::AnonymousBlock));
+        assert!(anonymous.computed_style(&device).is_none());
+        assert!(root.find(&html::parse("<p></p>".to_string())).is_none());
+    }
+
+    #[test]
+    fn box_types() {
+        with_layout(
+            r#"<html><div class="c"><p></p>x</div><i></i><p class="f"><b></b></p></html>"#,
+            r#"
+            html, p { display: block; }
+            .c { display: contents; }
+            .c::before { content: "y"; display: block; }
+            i { display: inline-block; }
+            .f { display: flex; }
+            "#,
+            |root| {
+                let types: Vec<&str> = root
+                    .children
+                    .iter()
+                    .map(|child| match child.box_type {
+                        BoxType::BlockNode(_) => "block",
+                        BoxType::AnonymousBlock => "anonymous",
+                        _ => "inline",
+                    })
+                    .collect();
+                // The `::before` and the children of the element with
+                // `display: contents` take its place.
+                assert_eq!(types, ["block", "block", "anonymous", "block"]);
+                let inline = &root.children[2].children;
+                assert!(matches!(inline[0].box_type, BoxType::InlineNode(_)));
+                assert!(matches!(inline[1].box_type, BoxType::InlineBlockNode(_)));
+                // The inline child of a flex container is block-level.
+                let flex = &root.children[3];
+                assert!(matches!(flex.children[0].box_type, BoxType::BlockNode(_)));
+            },
+        );
+    }
+
+    /// The texts of the fragments of the text boxes in the tree at
+    /// `layout_box`, with their content rectangles, in tree order.
+    fn text_fragments<'b>(layout_box: &'b LayoutBox, fragments: &mut Vec<&'b Fragment>) {
+        fragments.extend(layout_box.fragments.iter().filter(|f| f.text.is_some()));
+        for child in &layout_box.children {
+            text_fragments(child, fragments);
+        }
+    }
+
+    #[test]
+    fn line_breaking() {
+        with_layout(
+            r#"<html><p>  aaaa   bb cc
+            dddddddd </p><p>e</p></html>"#,
+            r#"
+            html { font-size: 10px; }
+            p { display: block; width: 30px; margin: 0; line-height: 20px; }
+            "#,
+            |root| {
+                let p = &root.children[0];
+                let mut fragments = vec![];
+                text_fragments(p, &mut fragments);
+                let lines: Vec<(&str, f32, f32, f32)> = fragments
+                    .iter()
+                    .map(|f| {
+                        let r = f.dimensions.content;
+                        (f.text.as_deref().unwrap(), r.x, r.y, r.width)
+                    })
+                    .collect();
+                // White space collapses, and a word wider than the line
+                // overflows it. The glyphs are centered in the line height.
+                assert_eq!(
+                    lines,
+                    [
+                        ("aaaa", 0.0, 5.0, 20.0),
+                        ("bb cc", 0.0, 25.0, 25.0),
+                        ("dddddddd", 0.0, 45.0, 40.0)
+                    ]
+                );
+                let anonymous = &p.children[0];
+                assert!(matches!(anonymous.box_type, BoxType::AnonymousBlock));
+                assert_eq!(anonymous.dimensions.content.height, 60.0);
+                assert_eq!(p.dimensions.content.height, 60.0);
+                assert_eq!(root.children[1].dimensions.content.y, 60.0);
+            },
+        );
+    }
+
+    #[test]
+    fn inline_boxes() {
+        with_layout(
+            r#"<html><p>a <span>bb cc</span>d</p></html>"#,
+            r#"
+            html { font-size: 10px; }
+            p { display: block; width: 50px; margin: 0; line-height: 20px; }
+            span { padding: 2px 5px; border: 1px solid; margin-left: 3px; }
+            "#,
+            |root| {
+                let span = &root.children[0].children[0].children[1];
+                assert_eq!(span.fragments.len(), 2);
+                // Only the first fragment has the left edges, and only the
+                // last one the right edges. The vertical edges don't affect
+                // the line height.
+                let first = &span.fragments[0].dimensions;
+                assert_eq!(first.content.x, 10.0 + 3.0 + 1.0 + 5.0);
+                assert_eq!((first.padding.left, first.padding.right), (5.0, 0.0));
+                assert_eq!((first.padding.top, first.border.top), (2.0, 1.0));
+                // The space at the end of the line hangs, and doesn't count.
+                assert_eq!(first.content.width, 10.0);
+                assert_eq!((first.content.y, first.content.height), (5.0, 10.0));
+                let last = &span.fragments[1].dimensions;
+                assert_eq!((last.content.x, last.content.y), (0.0, 25.0));
+                assert_eq!((last.padding.left, last.padding.right), (0.0, 5.0));
+                assert_eq!(span.dimensions.content.x, first.content.x);
+
+                let mut fragments = vec![];
+                text_fragments(root, &mut fragments);
+                let d = fragments.last().unwrap();
+                assert_eq!(d.text.as_deref(), Some("d"));
+                assert_eq!(d.dimensions.content.x, 10.0 + 6.0);
+            },
+        );
+    }
+
+    #[test]
+    fn inline_blocks() {
+        with_layout(
+            r#"<html><p>a<i></i><b>x y</b></p><div> </div></html>"#,
+            r#"
+            html { font-size: 10px; }
+            p, div { display: block; width: 100px; margin: 0; text-align: center; }
+            i { display: inline-block; width: 20px; height: 30px; margin-right: 1px; }
+            b { display: inline-block; padding: 0 2px; }
+            "#,
+            |root| {
+                let line = &root.children[0].children[0];
+                // The inline-block sits on the baseline, and makes the line
+                // taller. `b` shrinks to fit its text.
+                let b = &line.children[2];
+                assert_eq!(b.dimensions.content.width, 15.0);
+                assert_eq!(b.dimensions.content.height, 12.0);
+                let width = 5.0 + 21.0 + 19.0;
+                let i = &line.children[1].dimensions;
+                assert_eq!(i.content.x, (100.0 - width) / 2.0 + 5.0);
+                assert_eq!(i.content.y, 0.0);
+                assert_eq!(b.dimensions.content.y, 30.0 - 12.0);
+                let a = &line.children[0].fragments[0].dimensions;
+                assert_eq!(a.content.y, 30.0 - 7.5);
+                assert_eq!(line.dimensions.content.height, 30.0 + 2.5 + 1.0);
+
+                // A line of collapsed white space has no height.
+                assert_eq!(root.children[1].dimensions.content.height, 0.0);
+            },
+        );
+    }
+
+    #[test]
+    fn preserved_white_space() {
+        with_layout(
+            "<html><pre>a  b\n\nc</pre><p>d  \n e</p></html>",
+            r#"
+            html { font-size: 10px; line-height: 10px; }
+            pre { margin: 0; width: 10px; }
+            p { white-space: pre-line; margin: 0; }
+            "#,
+            |root| {
+                let mut fragments = vec![];
+                text_fragments(root, &mut fragments);
+                let texts: Vec<&str> = fragments
+                    .iter()
+                    .map(|f| f.text.as_deref().unwrap())
+                    .collect();
+                // `pre` doesn't wrap, and `pre-line` only keeps newlines.
+                assert_eq!(texts, ["a  b", "c", "d", "e"]);
+                assert_eq!(root.children[0].dimensions.content.height, 30.0);
+                assert_eq!(root.children[1].dimensions.content.height, 20.0);
+            },
+        );
+    }
+}
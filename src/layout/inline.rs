@@ -0,0 +1,720 @@
+//! Inline formatting context: laying out the inline-level content of an
+//! anonymous block in line boxes.
+//! spec: https://www.w3.org/TR/CSS2/visuren.html#inline-formatting
+
+use super::{border_width, non_negative, BoxType, Dimensions, Fragment, LayoutBox, LayoutContext};
+use crate::css::{LengthContext, Unit, Value};
+use crate::style::StyledNode;
+use std::ops::Range;
+
+/// Text is set in a monospace font, where each character advances by this
+/// many em.
+const ADVANCE: f32 = 0.5;
+/// How far the font extends above and below the baseline, in em.
+const ASCENT: f32 = 0.75;
+const DESCENT: f32 = 0.25;
+/// The used value of `line-height: normal`, in em.
+const NORMAL_LINE_HEIGHT: f32 = 1.2;
+
+/// A piece of inline content, in the order it is laid out.
+#[derive(Debug)]
+enum Item {
+    /// The start of the inline box `id`, with the width of its left margin,
+    /// border and padding.
+    Start { id: usize, width: f32 },
+    /// The end of the innermost inline box, with the width of its right
+    /// edges.
+    End { width: f32 },
+    /// A word of the text box `id`.
+    Text { id: usize, text: String, width: f32 },
+    /// White space of the text box `id`, which lines may wrap after if
+    /// `wrap`.
+    Space {
+        id: usize,
+        text: String,
+        width: f32,
+        wrap: bool,
+    },
+    /// A preserved newline.
+    ForcedBreak,
+    /// The atomic inline `id`, with the size of its margin box.
+    Atomic {
+        id: usize,
+        width: f32,
+        height: f32,
+        wrap: bool,
+    },
+}
+
+impl Item {
+    fn width(&self) -> f32 {
+        match *self {
+            Item::Start { width, .. }
+            | Item::End { width, .. }
+            | Item::Text { width, .. }
+            | Item::Space { width, .. }
+            | Item::Atomic { width, .. } => width,
+            Item::ForcedBreak => 0.0,
+        }
+    }
+}
+
+/// An inline box or text box, with what its fragments are made of.
+struct InlineBox {
+    font_size: f32,
+    line_height: f32,
+    /// The margins, borders and paddings of an inline box. Only the
+    /// horizontal margins apply.
+    edges: Dimensions,
+}
+
+/// The inline content of an anonymous block, flattened into items. Boxes are
+/// numbered in tree order, and atomic inlines are numbered but not entered.
+struct InlineItems {
+    items: Vec<Item>,
+    /// The inline and text boxes by number, or `None` for atomic inlines.
+    boxes: Vec<Option<InlineBox>>,
+    /// Whether white space here collapses away: after other collapsible white
+    /// space, or at the start of a line.
+    collapse_space: bool,
+    /// What percentages in the edges of inline boxes refer to.
+    percent_base: f32,
+}
+
+/// Measures or lays out an atomic inline, given the lengths of its parent,
+/// and returns the size of its margin box.
+type AtomicLayout<'a, 'f> = dyn FnMut(&mut LayoutBox<'a>, LengthContext) -> (f32, f32) + 'f;
+
+impl InlineItems {
+    fn new(percent_base: f32) -> InlineItems {
+        InlineItems {
+            items: vec![],
+            boxes: vec![],
+            collapse_space: true,
+            percent_base,
+        }
+    }
+
+    /// Add the items of `layout_box`, given the lengths of its parent.
+    fn collect<'a>(
+        &mut self,
+        layout_box: &mut LayoutBox<'a>,
+        lengths: &LengthContext,
+        atomic: &mut AtomicLayout<'a, '_>,
+    ) {
+        let id = self.boxes.len();
+        let style = match layout_box.box_type {
+            BoxType::InlineNode(style) => style,
+            BoxType::BlockNode(style) | BoxType::InlineBlockNode(style) => {
+                self.boxes.push(None);
+                let (width, height) = atomic(layout_box, *lengths);
+                let (_, wrap, _) = white_space(style);
+                self.items.push(Item::Atomic {
+                    id,
+                    width,
+                    height,
+                    wrap,
+                });
+                self.collapse_space = false;
+                return;
+            }
+            BoxType::AnonymousBlock => {
+                unreachable!("Anonymous blocks only contain inline-level boxes.")
+            }
+        };
+
+        let lengths = LengthContext {
+            font_size: style.font_size(lengths),
+            ..*lengths
+        };
+        let mut inline_box = InlineBox {
+            font_size: lengths.font_size,
+            line_height: line_height(style, &lengths),
+            edges: Dimensions::default(),
+        };
+        if let Some(text) = style.text() {
+            self.boxes.push(Some(inline_box));
+            self.push_text(id, text, style);
+            return;
+        }
+
+        inline_box.edges = inline_edges(style, &lengths, self.percent_base);
+        let e = &inline_box.edges;
+        let start = e.margin.left + e.border.left + e.padding.left;
+        let end = e.margin.right + e.border.right + e.padding.right;
+        self.boxes.push(Some(inline_box));
+        self.items.push(Item::Start { id, width: start });
+        for child in &mut layout_box.children {
+            self.collect(child, &lengths, atomic);
+        }
+        self.items.push(Item::End { width: end });
+    }
+
+    /// Add the words and white space of the text box `id`, processing white
+    /// space according to the `white-space` property.
+    /// spec: https://www.w3.org/TR/css-text-3/#white-space-processing
+    fn push_text(&mut self, id: usize, text: &str, style: &StyledNode) {
+        let (collapse, wrap, preserve_newlines) = white_space(style);
+        let advance = ADVANCE * self.boxes[id].as_ref().unwrap().font_size;
+        let mut word = String::new();
+        for c in text.chars() {
+            match c {
+                '\n' if preserve_newlines => {
+                    self.push_word(id, &mut word, advance);
+                    // Collapsible spaces before a newline are removed.
+                    if collapse && matches!(self.items.last(), Some(Item::Space { .. })) {
+                        self.items.pop();
+                    }
+                    self.items.push(Item::ForcedBreak);
+                    self.collapse_space = true;
+                }
+                ' ' | '\t' | '\n' | '\r' => {
+                    self.push_word(id, &mut word, advance);
+                    if collapse && self.collapse_space {
+                        continue;
+                    }
+                    self.collapse_space = collapse;
+                    self.items.push(Item::Space {
+                        id,
+                        text: " ".to_string(),
+                        width: advance,
+                        wrap,
+                    });
+                }
+                c => word.push(c),
+            }
+        }
+        self.push_word(id, &mut word, advance);
+    }
+
+    fn push_word(&mut self, id: usize, word: &mut String, advance: f32) {
+        if !word.is_empty() {
+            self.items.push(Item::Text {
+                id,
+                width: word.chars().count() as f32 * advance,
+                text: std::mem::take(word),
+            });
+            self.collapse_space = false;
+        }
+    }
+}
+
+/// Whether white space collapses, whether lines wrap, and whether newlines
+/// are preserved, for the `white-space` of `style`.
+/// spec: https://www.w3.org/TR/css-text-3/#white-space-property
+fn white_space(style: &StyledNode) -> (bool, bool, bool) {
+    match style.value("white-space") {
+        Some(Value::Keyword(k)) if k == "pre" => (false, false, true),
+        Some(Value::Keyword(k)) if k == "pre-wrap" || k == "break-spaces" => (false, true, true),
+        Some(Value::Keyword(k)) if k == "pre-line" => (true, true, true),
+        Some(Value::Keyword(k)) if k == "nowrap" => (true, false, false),
+        // The initial value is `normal`.
+        _ => (true, true, false),
+    }
+}
+
+/// The used `line-height` of a box, given its lengths.
+/// spec: https://www.w3.org/TR/CSS2/visudet.html#propdef-line-height
+fn line_height(style: &StyledNode, lengths: &LengthContext) -> f32 {
+    match style.value("line-height") {
+        Some(Value::Number(n)) => n * lengths.font_size,
+        Some(length @ (Value::Length(..) | Value::Calc(_))) => {
+            length.to_px(lengths, lengths.font_size)
+        }
+        // The initial value is `normal`.
+        _ => NORMAL_LINE_HEIGHT * lengths.font_size,
+    }
+}
+
+/// The margins, borders and paddings of an inline box. `auto` margins are 0.
+fn inline_edges(style: &StyledNode, lengths: &LengthContext, percent_base: f32) -> Dimensions {
+    let zero = Value::Length(0.0, Unit::Px);
+    let margin = |side: &str| {
+        style
+            .value_or(&format!("margin-{}", side), &zero)
+            .to_px(lengths, percent_base)
+    };
+    let padding = |side: &str| {
+        non_negative(style.value_or(&format!("padding-{}", side), &zero))
+            .to_px(lengths, percent_base)
+    };
+    let border = |side: &str| border_width(style, side).to_px(lengths, percent_base);
+
+    let mut d = Dimensions::default();
+    (d.margin.left, d.margin.right) = (margin("left"), margin("right"));
+    (d.padding.left, d.padding.right) = (padding("left"), padding("right"));
+    (d.padding.top, d.padding.bottom) = (padding("top"), padding("bottom"));
+    (d.border.left, d.border.right) = (border("left"), border("right"));
+    (d.border.top, d.border.bottom) = (border("top"), border("bottom"));
+    d
+}
+
+/// The items of a line, without the white space that hangs at its end.
+fn trim_end(line: &[Item]) -> &[Item] {
+    let end = line
+        .iter()
+        .rposition(|item| !matches!(item, Item::Space { wrap: true, .. }))
+        .map_or(0, |i| i + 1);
+    &line[..end]
+}
+
+/// Break the items into lines at forced breaks, and at the last wrap
+/// opportunity before a line would get wider than `available`. A line only
+/// overflows if it has a single unbreakable run of items.
+/// spec: https://www.w3.org/TR/css-text-3/#line-breaking
+fn break_lines(items: &[Item], available: f32) -> Vec<Range<usize>> {
+    let mut breaker = LineBreaker {
+        lines: vec![],
+        available,
+        line_start: 0,
+        line_width: 0.0,
+        run_start: 0,
+        run_width: 0.0,
+        trailing_space: 0.0,
+    };
+    for (i, item) in items.iter().enumerate() {
+        match *item {
+            Item::ForcedBreak => breaker.forced_break(i + 1),
+            Item::Space { width, wrap, .. } => {
+                breaker.run_width += width;
+                breaker.trailing_space += width;
+                if wrap {
+                    breaker.wrap_opportunity(i + 1);
+                }
+            }
+            Item::Atomic { width, wrap, .. } => {
+                if wrap {
+                    breaker.wrap_opportunity(i);
+                }
+                breaker.add(width);
+                if wrap {
+                    breaker.wrap_opportunity(i + 1);
+                }
+            }
+            Item::Start { width, .. } | Item::End { width, .. } | Item::Text { width, .. } => {
+                breaker.add(width)
+            }
+        }
+    }
+    breaker.wrap_opportunity(items.len());
+    if breaker.line_start < items.len() {
+        breaker.lines.push(breaker.line_start..items.len());
+    }
+    breaker.lines
+}
+
+struct LineBreaker {
+    lines: Vec<Range<usize>>,
+    available: f32,
+    line_start: usize,
+    /// Width of the current line, up to the run of items since the last wrap
+    /// opportunity.
+    line_width: f32,
+    run_start: usize,
+    run_width: f32,
+    /// Width of the white space at the end of the run, which doesn't count
+    /// if the run ends the line.
+    trailing_space: f32,
+}
+
+impl LineBreaker {
+    fn add(&mut self, width: f32) {
+        self.run_width += width;
+        self.trailing_space = 0.0;
+    }
+
+    /// Put the run of items before `end` on the current line, or start a new
+    /// line with it if it doesn't fit.
+    fn wrap_opportunity(&mut self, end: usize) {
+        let visible_width = self.run_width - self.trailing_space;
+        if self.line_start < self.run_start
+            && visible_width > 0.0
+            && self.line_width + visible_width > self.available
+        {
+            self.lines.push(self.line_start..self.run_start);
+            self.line_start = self.run_start;
+            self.line_width = 0.0;
+        }
+        self.line_width += self.run_width;
+        self.run_start = end;
+        self.run_width = 0.0;
+        self.trailing_space = 0.0;
+    }
+
+    fn forced_break(&mut self, end: usize) {
+        self.wrap_opportunity(end);
+        self.lines.push(self.line_start..end);
+        self.line_start = end;
+        self.line_width = 0.0;
+    }
+}
+
+/// Part of a box on a line, at a horizontal offset from the start of the line.
+struct Piece {
+    id: usize,
+    x: f32,
+    width: f32,
+    kind: PieceKind,
+}
+
+enum PieceKind {
+    /// A fragment of an inline box, which has its left edges if it is the
+    /// `first` one, and its right edges if it is the `last` one.
+    Inline {
+        first: bool,
+        last: bool,
+    },
+    Text(String),
+    Atomic {
+        height: f32,
+    },
+}
+
+/// Place the items of a line horizontally. `open` holds the inline boxes that
+/// continue from the previous line, with whether they have been started, and
+/// is left with those that continue on the next one. Returns the pieces and
+/// the width of the line.
+fn place_line(line: &[Item], open: &mut Vec<(usize, f32, bool)>) -> (Vec<Piece>, f32) {
+    let mut pieces = vec![];
+    let mut x = 0.0;
+    for (_, start, _) in open.iter_mut() {
+        *start = 0.0;
+    }
+    // The text of the current text box, which runs until a different item.
+    let mut text: Option<(usize, f32, String)> = None;
+    let flush = |text: &mut Option<(usize, f32, String)>, pieces: &mut Vec<Piece>, x: f32| {
+        if let Some((id, start, s)) = text.take() {
+            pieces.push(Piece {
+                id,
+                x: start,
+                width: x - start,
+                kind: PieceKind::Text(s),
+            });
+        }
+    };
+    for item in trim_end(line) {
+        match item {
+            Item::Text { id, text: s, .. } | Item::Space { id, text: s, .. } => {
+                match &mut text {
+                    Some((text_id, _, text)) if text_id == id => text.push_str(s),
+                    _ => {
+                        flush(&mut text, &mut pieces, x);
+                        text = Some((*id, x, s.clone()));
+                    }
+                }
+                x += item.width();
+                continue;
+            }
+            _ => flush(&mut text, &mut pieces, x),
+        }
+        match *item {
+            Item::Start { id, width } => {
+                open.push((id, x, true));
+                x += width;
+            }
+            Item::End { width, .. } => {
+                x += width;
+                let (id, start, first) = open.pop().unwrap();
+                pieces.push(Piece {
+                    id,
+                    x: start,
+                    width: x - start,
+                    kind: PieceKind::Inline { first, last: true },
+                });
+            }
+            Item::Atomic {
+                id, width, height, ..
+            } => {
+                pieces.push(Piece {
+                    id,
+                    x,
+                    width,
+                    kind: PieceKind::Atomic { height },
+                });
+                x += width;
+            }
+            Item::ForcedBreak | Item::Text { .. } | Item::Space { .. } => {}
+        }
+    }
+    flush(&mut text, &mut pieces, x);
+    // Boxes which continue on the next line end here without their right
+    // edges.
+    for (id, start, first) in open.iter_mut() {
+        pieces.push(Piece {
+            id: *id,
+            x: *start,
+            width: x - *start,
+            kind: PieceKind::Inline {
+                first: *first,
+                last: false,
+            },
+        });
+        *first = false;
+    }
+    (pieces, x)
+}
+
+impl<'a> LayoutBox<'a> {
+    /// Lay out the inline-level children of an anonymous block in line boxes,
+    /// below the content of its containing block.
+    pub(super) fn layout_inline(
+        &mut self,
+        containing_block: &Dimensions,
+        context: LayoutContext<'a>,
+    ) {
+        let container = context
+            .container
+            .expect("Expected an anonymous block in a block container, but got none.");
+
+        let d = &mut self.dimensions;
+        d.content.x = containing_block.content.x;
+        d.content.y = containing_block.content.y + containing_block.content.height;
+        d.content.width = containing_block.content.width;
+        let available = d.content.width;
+
+        let mut items = InlineItems::new(available);
+        for child in &mut self.children {
+            items.collect(child, &context.lengths, &mut |child, lengths| {
+                child.layout_atomic(
+                    available,
+                    LayoutContext {
+                        lengths,
+                        containing_height: None,
+                        container: context.container,
+                    },
+                )
+            });
+        }
+
+        // Every line box starts with a strut of the font and line height of
+        // the block container.
+        // spec: https://www.w3.org/TR/CSS2/visudet.html#strut
+        let strut = InlineBox {
+            font_size: context.lengths.font_size,
+            line_height: line_height(container, &context.lengths),
+            edges: Dimensions::default(),
+        };
+        let text_align = match container.value("text-align") {
+            Some(Value::Keyword(k)) => k,
+            _ => "start".to_string(),
+        };
+
+        let mut fragments: Vec<Vec<Fragment>> = items.boxes.iter().map(|_| vec![]).collect();
+        let mut positions = vec![None; items.boxes.len()];
+        let mut open = vec![];
+        let mut height = 0.0;
+        for line in break_lines(&items.items, available) {
+            let line = &items.items[line];
+            let (pieces, width) = place_line(line, &mut open);
+            // Lines without any content take no space.
+            // spec: https://www.w3.org/TR/CSS2/visuren.html#phantom-line-box
+            let empty = !matches!(line.last(), Some(Item::ForcedBreak))
+                && pieces.iter().all(|piece| match piece.kind {
+                    PieceKind::Inline { .. } => piece.width == 0.0,
+                    PieceKind::Text(_) | PieceKind::Atomic { .. } => false,
+                });
+            if empty {
+                continue;
+            }
+
+            let offset = match &*text_align {
+                "center" => (available - width) / 2.0,
+                "right" | "end" => available - width,
+                _ => 0.0,
+            }
+            .max(0.0);
+
+            // Align the baselines of the pieces: the line box is as tall as
+            // needed to fit the half-leading of every box above and below the
+            // baseline, and the margin box of every atomic inline above it.
+            // spec: https://www.w3.org/TR/CSS2/visudet.html#line-height
+            let half_leading = |b: &InlineBox| {
+                let leading = (b.line_height - b.font_size) / 2.0;
+                (
+                    ASCENT * b.font_size + leading,
+                    DESCENT * b.font_size + leading,
+                )
+            };
+            let (mut ascent, mut descent) = half_leading(&strut);
+            for piece in &pieces {
+                let (a, d) = match (&piece.kind, &items.boxes[piece.id]) {
+                    (PieceKind::Atomic { height }, _) => (*height, 0.0),
+                    (_, Some(b)) => half_leading(b),
+                    (_, None) => (0.0, 0.0),
+                };
+                ascent = f32::max(ascent, a);
+                descent = f32::max(descent, d);
+            }
+
+            let x = self.dimensions.content.x + offset;
+            let baseline = self.dimensions.content.y + height + ascent;
+            for piece in pieces {
+                let Some(b) = &items.boxes[piece.id] else {
+                    if let PieceKind::Atomic { height } = piece.kind {
+                        positions[piece.id] = Some((x + piece.x, baseline - height));
+                    }
+                    continue;
+                };
+                let mut d = Dimensions::default();
+                let mut text = None;
+                match piece.kind {
+                    PieceKind::Inline { first, last } => {
+                        let e = &b.edges;
+                        (d.padding.top, d.padding.bottom) = (e.padding.top, e.padding.bottom);
+                        (d.border.top, d.border.bottom) = (e.border.top, e.border.bottom);
+                        if first {
+                            d.margin.left = e.margin.left;
+                            d.border.left = e.border.left;
+                            d.padding.left = e.padding.left;
+                        }
+                        if last {
+                            d.margin.right = e.margin.right;
+                            d.border.right = e.border.right;
+                            d.padding.right = e.padding.right;
+                        }
+                    }
+                    PieceKind::Text(s) => text = Some(s),
+                    PieceKind::Atomic { .. } => {}
+                }
+                let left = d.margin.left + d.border.left + d.padding.left;
+                let right = d.margin.right + d.border.right + d.padding.right;
+                d.content.x = x + piece.x + left;
+                d.content.width = piece.width - left - right;
+                // The content area is as tall as the font.
+                d.content.y = baseline - ASCENT * b.font_size;
+                d.content.height = b.font_size;
+                fragments[piece.id].push(Fragment {
+                    dimensions: d,
+                    text,
+                });
+            }
+            height += ascent + descent;
+        }
+        self.dimensions.content.height = height;
+
+        let mut id = 0;
+        for child in &mut self.children {
+            child.place_fragments(&mut id, &mut fragments, &positions);
+        }
+    }
+
+    /// Lay out an atomic inline at the document origin, in a containing block
+    /// of width `available`, and return the size of its margin box. An
+    /// inline-block of `width: auto` shrinks to fit its content.
+    /// spec: https://www.w3.org/TR/CSS2/visudet.html#inlineblock-width
+    fn layout_atomic(&mut self, available: f32, context: LayoutContext<'a>) -> (f32, f32) {
+        let mut containing_block = Dimensions::default();
+        containing_block.content.width = available;
+        self.layout_block(&containing_block, context);
+
+        if let BoxType::InlineBlockNode(style) = self.box_type {
+            // Unlike those of a block, the horizontal margins of an
+            // inline-block don't stretch to fill the line, and `auto` ones
+            // are 0.
+            let lengths = LengthContext {
+                font_size: style.font_size(&context.lengths),
+                ..context.lengths
+            };
+            let margin = inline_edges(style, &lengths, available).margin;
+            let set_margins = |layout_box: &mut LayoutBox| {
+                let d = &mut layout_box.dimensions;
+                (d.margin.left, d.margin.right) = (margin.left, margin.right);
+            };
+            set_margins(self);
+
+            if matches!(style.value("width"), None | Some(Value::Keyword(_))) {
+                let d = self.dimensions;
+                let edges = d.margin_box().width - d.content.width;
+                let content = self.max_content_width(&context.lengths) - edges;
+                if content < d.content.width {
+                    containing_block.content.width = content + edges;
+                    self.layout_block(&containing_block, context);
+                    set_margins(self);
+                }
+            }
+        }
+        let margin_box = self.dimensions.margin_box();
+        (margin_box.width, margin_box.height)
+    }
+
+    /// The width of the margin box of this block-level or atomic box if none
+    /// of its lines wrap, given the lengths of its parent.
+    /// spec: https://www.w3.org/TR/css-sizing-3/#max-content
+    fn max_content_width(&mut self, lengths: &LengthContext) -> f32 {
+        match self.box_type {
+            BoxType::BlockNode(style) | BoxType::InlineBlockNode(style) => {
+                let lengths = LengthContext {
+                    font_size: style.font_size(lengths),
+                    ..*lengths
+                };
+                // Percentages depend on the containing block, so they act as 0.
+                let edges = inline_edges(style, &lengths, 0.0).margin_box().width;
+                let content = match style.value("width") {
+                    Some(Value::Length(_, Unit::Percent)) | None | Some(Value::Keyword(_)) => self
+                        .children
+                        .iter_mut()
+                        .map(|child| child.max_content_width(&lengths))
+                        .fold(0.0, f32::max),
+                    Some(width) => width.to_px(&lengths, 0.0),
+                };
+                edges + content
+            }
+            BoxType::AnonymousBlock => {
+                let mut items = InlineItems::new(0.0);
+                for child in &mut self.children {
+                    items.collect(child, lengths, &mut |child, lengths| {
+                        (child.max_content_width(&lengths), 0.0)
+                    });
+                }
+                items
+                    .items
+                    .split(|item| matches!(item, Item::ForcedBreak))
+                    .map(|line| trim_end(line).iter().map(Item::width).sum())
+                    .fold(0.0, f32::max)
+            }
+            BoxType::InlineNode(_) => {
+                unreachable!("Inline boxes are measured by their anonymous block.")
+            }
+        }
+    }
+
+    /// Give the boxes of this subtree the fragments and positions found by
+    /// `layout_inline`, numbering them in the same order as `collect`.
+    fn place_fragments(
+        &mut self,
+        id: &mut usize,
+        fragments: &mut [Vec<Fragment>],
+        positions: &[Option<(f32, f32)>],
+    ) {
+        let this = *id;
+        *id += 1;
+        if let BoxType::InlineNode(_) = self.box_type {
+            self.fragments = std::mem::take(&mut fragments[this]);
+            // The box itself is described by its first fragment.
+            self.dimensions = self
+                .fragments
+                .first()
+                .map(|fragment| fragment.dimensions)
+                .unwrap_or_default();
+            for child in &mut self.children {
+                child.place_fragments(id, fragments, positions);
+            }
+        } else if let Some((x, y)) = positions[this] {
+            let margin_box = self.dimensions.margin_box();
+            self.translate(x - margin_box.x, y - margin_box.y);
+        }
+    }
+
+    /// Move this box and all its descendants.
+    fn translate(&mut self, dx: f32, dy: f32) {
+        let moves = std::iter::once(&mut self.dimensions)
+            .chain(self.fragments.iter_mut().map(|f| &mut f.dimensions));
+        for d in moves {
+            d.content.x += dx;
+            d.content.y += dy;
+        }
+        for child in &mut self.children {
+            child.translate(dx, dy);
+        }
+    }
+}
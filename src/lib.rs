@@ -0,0 +1,8 @@
+//! A toy browser engine, from HTML/CSS sources to a painted canvas.
+
+pub mod css;
+pub mod dom;
+pub mod html;
+pub mod layout;
+pub mod painting;
+pub mod style;
@@ -1,9 +1,4 @@
-mod css;
-mod dom;
-mod html;
-mod layout;
-mod painting;
-mod style;
+use obe::{css, dom, html, layout, painting, style};
 
 fn main() {
     let html = r#"
@@ -14,6 +9,7 @@ fn main() {
                 <div class="e">
                   <div class="f">
                     <div class="g">
+                      Hello, world! Héllo, wörld!
                     </div>
                   </div>
                 </div>
@@ -23,22 +19,40 @@ fn main() {
         </div>
         "#;
     let css = r#"
-        * { display: block; padding: 12px; }
+        * { padding: 12px; }
         .a { background: #ff0000; }
         .b { background: #ffa500; }
         .c { background: #ffff00; }
         .d { background: #008000; }
         .e { background: #0000ff; }
         .f { background: #4b0082; }
-        .g { background: #800080; }
+        .g { background: #800080; color: #ffffff; font-size: 24px; }
+        @media (width < 600px) {
+          * { padding: 6px; }
+        }
         "#;
 
+    // Since we don't have an actual window, the "viewport" size is given as
+    // `--viewport WIDTHxHEIGHT`, e.g. 375x667 for a phone.
+    let (width, height) = viewport_size().unwrap_or((800.0, 600.0));
+    let device = css::Device::new(width, height);
+
     let dom = html::parse(html.to_string());
-    let cssom = css::parse(css.to_string());
-    let style = style::style_tree(&dom, &cssom);
+    let mut cssom = css::parse(css.to_string());
+    // Imports are relative to the working directory.
+    for error in cssom.load_imports("", &css::FileLoader) {
+        eprintln!("{}", error);
+    }
+    let stylesheets = [cssom];
+
+    // `--explain SELECTOR` prints the rules that match the first element the
+    // selector matches, and which of their declarations win.
+    if let Some(selector) = arg_value("--explain") {
+        explain(&dom, &selector, &stylesheets, &device);
+    }
+
+    let style = style::style_tree(&dom, &stylesheets, &device);
 
-    // Since we don't have an actual window, hard-code the "viewport" size.
-    let (width, height) = (800.0, 600.0);
     let mut viewport: layout::Dimensions = Default::default();
     viewport.content.width = width;
     viewport.content.height = height;
@@ -46,11 +60,48 @@ fn main() {
     let layout = layout::layout_tree(&style, &mut viewport);
     let canvas = painting::paint(&layout, width as usize, height as usize);
 
-    // Save output as an image file.
+    // Save output as an image file. The canvas is opaque, so alpha is dropped.
     let (w, h) = (canvas.width as u32, canvas.height as u32);
-    let buffer: Vec<image::Rgb<u8>> = unsafe { std::mem::transmute(canvas.pixels) };
-    let img = image::ImageBuffer::from_fn(w, h, |x, y| buffer[(y * w + x) as usize]);
+    let img = image::ImageBuffer::from_fn(w, h, |x, y| {
+        let pixel = canvas.pixels[(y * w + x) as usize];
+        image::Rgb([pixel.r, pixel.g, pixel.b])
+    });
     image::DynamicImage::ImageRgb8(img)
         .save("output.png")
         .unwrap();
 }
+
+/// Return the value after the command-line argument `name`, if any.
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != name).nth(1)
+}
+
+/// Parse the `--viewport WIDTHxHEIGHT` command-line argument, if any.
+fn viewport_size() -> Option<(f32, f32)> {
+    let size = arg_value("--viewport")?;
+    let (width, height) = size
+        .split_once('x')
+        .unwrap_or_else(|| panic!("Expected --viewport WIDTHxHEIGHT, but got {}.", size));
+    let parse = |n: &str| {
+        n.parse::<u32>()
+            .unwrap_or_else(|_| panic!("Invalid viewport size {}.", size)) as f32
+    };
+    Some((parse(width), parse(height)))
+}
+
+/// Print the cascade of the first element that `selector` matches, or of its
+/// pseudo-element if the selector has one, e.g. `.c::before`.
+fn explain(dom: &dom::Node, selector: &str, stylesheets: &[css::Stylesheet], device: &css::Device) {
+    let selectors = css::parse_selectors(selector);
+    let [single] = &selectors[..] else {
+        panic!("Expected a single selector, but got {}.", selector);
+    };
+    let Some(element) = style::query_selector(dom, &selectors) else {
+        eprintln!("No element matches {}.", selector);
+        return;
+    };
+    let rules = style::explain_cascade(dom, element, stylesheets, device, single.pseudo_element());
+    for rule in rules.unwrap() {
+        println!("{}\n", rule);
+    }
+}
@@ -1,8 +1,17 @@
 use crate::css::{Color, Value};
-use crate::layout::{BoxType, LayoutBox, Rect};
+use crate::layout::{BoxType, Dimensions, LayoutBox, Rect};
 
 use std::fmt;
 
+mod font;
+
+const BLACK: Color = Color {
+    r: 0,
+    g: 0,
+    b: 0,
+    a: 255,
+};
+
 pub struct Canvas {
     pub pixels: Vec<Color>,
     pub width: usize,
@@ -11,9 +20,9 @@ pub struct Canvas {
 
 impl fmt::Debug for Canvas {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
+        writeln!(
             f,
-            "Canvas width={} height={} pixels:\n",
+            "Canvas width={} height={} pixels:",
             self.width, self.height
         )?;
         for y in 0..self.height {
@@ -21,7 +30,7 @@ impl fmt::Debug for Canvas {
                 let pixel = self.pixels[x + y * self.width];
                 write!(f, "#{:02x}{:02x}{:02x} ", pixel.r, pixel.g, pixel.b)?;
             }
-            write!(f, "\n")?;
+            writeln!(f)?;
         }
         fmt::Result::Ok(())
     }
@@ -43,6 +52,9 @@ type DisplayList = Vec<DisplayCommand>;
 enum DisplayCommand {
     /// Paint a solid-color rectangle.
     SolidColor(Color, Rect),
+    /// Paint a line of text in the built-in font, with glyphs scaled to the
+    /// height of the rectangle.
+    Text(String, Color, Rect),
 }
 
 fn build_display_list(layout_root: &LayoutBox) -> DisplayList {
@@ -52,35 +64,36 @@ fn build_display_list(layout_root: &LayoutBox) -> DisplayList {
 }
 
 fn render_layout_box(list: &mut DisplayList, layout_box: &LayoutBox) {
-    render_background(list, layout_box);
-    render_borders(list, layout_box);
+    // An inline box is painted one fragment at a time, as its edges are split
+    // across lines.
+    let boxes: Vec<&Dimensions> = match layout_box.box_type {
+        BoxType::InlineNode(_) => layout_box.fragments.iter().map(|f| &f.dimensions).collect(),
+        _ => vec![&layout_box.dimensions],
+    };
+    for d in boxes {
+        render_background(list, layout_box, d);
+        render_borders(list, layout_box, d);
+    }
+    render_text(list, layout_box);
 
     for child in &layout_box.children {
         render_layout_box(list, child);
     }
 }
 
-fn render_background(list: &mut DisplayList, layout_box: &LayoutBox) {
-    get_color(layout_box, "background").map(|color| {
-        list.push(DisplayCommand::SolidColor(
-            color,
-            layout_box.dimensions.border_box(),
-        ))
-    });
+fn render_background(list: &mut DisplayList, layout_box: &LayoutBox, d: &Dimensions) {
+    // The initial background color is transparent.
+    if let Some(color) = get_color(layout_box, "background-color").filter(|c| c.a > 0) {
+        list.push(DisplayCommand::SolidColor(color, d.border_box()));
+    }
 }
 
-fn render_borders(list: &mut DisplayList, layout_box: &LayoutBox) {
-    let Some(color) = get_color(layout_box, "border-color") else {
-        // Bail out if no border-color is specified.
-        return;
-    };
-
-    let d = &layout_box.dimensions;
+fn render_borders(list: &mut DisplayList, layout_box: &LayoutBox, d: &Dimensions) {
     let border_box = d.border_box();
 
     // Left border
     list.push(DisplayCommand::SolidColor(
-        color,
+        border_color(layout_box, "left"),
         Rect {
             x: border_box.x,
             y: border_box.y,
@@ -91,7 +104,7 @@ fn render_borders(list: &mut DisplayList, layout_box: &LayoutBox) {
 
     // Right border
     list.push(DisplayCommand::SolidColor(
-        color,
+        border_color(layout_box, "right"),
         Rect {
             x: border_box.x + border_box.width - d.border.right,
             y: border_box.y,
@@ -102,7 +115,7 @@ fn render_borders(list: &mut DisplayList, layout_box: &LayoutBox) {
 
     // Top border
     list.push(DisplayCommand::SolidColor(
-        color,
+        border_color(layout_box, "top"),
         Rect {
             x: border_box.x,
             y: border_box.y,
@@ -113,7 +126,7 @@ fn render_borders(list: &mut DisplayList, layout_box: &LayoutBox) {
 
     // Bottom border
     list.push(DisplayCommand::SolidColor(
-        color,
+        border_color(layout_box, "bottom"),
         Rect {
             x: border_box.x,
             y: border_box.y + border_box.height - d.border.bottom,
@@ -123,17 +136,41 @@ fn render_borders(list: &mut DisplayList, layout_box: &LayoutBox) {
     ));
 }
 
+fn render_text(list: &mut DisplayList, layout_box: &LayoutBox) {
+    let color = get_color(layout_box, "color").unwrap_or(BLACK);
+    for fragment in &layout_box.fragments {
+        if let Some(text) = &fragment.text {
+            list.push(DisplayCommand::Text(
+                text.clone(),
+                color,
+                fragment.dimensions.content,
+            ));
+        }
+    }
+}
+
+/// The color of the border on `side`. It defaults to `currentcolor`, and the
+/// initial `color` is black.
+/// spec: https://www.w3.org/TR/css-backgrounds-3/#border-color
+fn border_color(layout_box: &LayoutBox, side: &str) -> Color {
+    get_color(layout_box, &format!("border-{}-color", side))
+        .or_else(|| get_color(layout_box, "color"))
+        .unwrap_or(BLACK)
+}
+
 /// Return the specified color for CSS property `name`, or None if no color was
-/// specified.
+/// specified. `currentcolor` refers to the value of the `color` property.
 fn get_color(layout_box: &LayoutBox, name: &str) -> Option<Color> {
     match layout_box.box_type {
-        BoxType::BlockNode(style) | BoxType::InlineNode(style) => {
-            if let Some(Value::Colorvalue(color)) = style.value(name) {
-                Some(color)
-            } else {
-                None
+        BoxType::BlockNode(style)
+        | BoxType::InlineNode(style)
+        | BoxType::InlineBlockNode(style) => match style.value(name) {
+            Some(Value::Colorvalue(color)) => Some(color),
+            Some(Value::Keyword(keyword)) if keyword == "currentcolor" && name != "color" => {
+                get_color(layout_box, "color")
             }
-        }
+            _ => None,
+        },
         BoxType::AnonymousBlock => None,
     }
 }
@@ -145,6 +182,7 @@ impl Canvas {
             r: 255,
             g: 255,
             b: 255,
+            a: 255,
         };
         Canvas {
             pixels: vec![white; width * height],
@@ -157,14 +195,44 @@ impl Canvas {
     fn paint_item(&mut self, item: &DisplayCommand) {
         match item {
             DisplayCommand::SolidColor(color, rect) => {
-                let x0 = rect.x as usize;
-                let x1 = x0 + rect.width as usize;
-                let y0 = rect.y as usize;
-                let y1 = y0 + rect.height as usize;
+                // Clip the rectangle to the canvas boundaries, as boxes can be
+                // placed outside of it, e.g. by negative margins.
+                let x0 = rect.x.clamp(0.0, self.width as f32) as usize;
+                let y0 = rect.y.clamp(0.0, self.height as f32) as usize;
+                let x1 = (rect.x + rect.width).clamp(0.0, self.width as f32) as usize;
+                let y1 = (rect.y + rect.height).clamp(0.0, self.height as f32) as usize;
 
                 for y in y0..y1 {
                     for x in x0..x1 {
-                        self.pixels[x + y * self.width] = *color;
+                        let pixel = &mut self.pixels[x + y * self.width];
+                        *pixel = blend(*color, *pixel);
+                    }
+                }
+            }
+            DisplayCommand::Text(text, color, rect) => {
+                // Glyphs are scaled by nearest neighbor sampling: a pixel is
+                // painted if the glyph pixel under its center is set.
+                let scale = rect.height / font::HEIGHT as f32;
+                let glyph_width = font::WIDTH as f32 * scale;
+                let y0 = rect.y.clamp(0.0, self.height as f32) as usize;
+                let y1 = (rect.y + rect.height).clamp(0.0, self.height as f32) as usize;
+                for (i, c) in text.chars().enumerate() {
+                    let glyph = font::glyph(c);
+                    let left = rect.x + i as f32 * glyph_width;
+                    let x0 = left.clamp(0.0, self.width as f32) as usize;
+                    let x1 = (left + glyph_width).clamp(0.0, self.width as f32) as usize;
+                    for y in y0..y1 {
+                        let row = ((y as f32 + 0.5 - rect.y) / scale) as usize;
+                        for x in x0..x1 {
+                            let column = ((x as f32 + 0.5 - left) / scale) as usize;
+                            if row < font::HEIGHT
+                                && column < font::WIDTH
+                                && font::is_set(glyph, column, row)
+                            {
+                                let pixel = &mut self.pixels[x + y * self.width];
+                                *pixel = blend(*color, *pixel);
+                            }
+                        }
                     }
                 }
             }
@@ -172,6 +240,19 @@ impl Canvas {
     }
 }
 
+/// Composite `source` over the opaque color `backdrop`.
+/// spec: https://www.w3.org/TR/compositing-1/#simplealphacompositing
+fn blend(source: Color, backdrop: Color) -> Color {
+    let alpha = source.a as f32 / 255.0;
+    let mix = |s: u8, b: u8| (s as f32 * alpha + b as f32 * (1.0 - alpha)).round() as u8;
+    Color {
+        r: mix(source.r, backdrop.r),
+        g: mix(source.g, backdrop.g),
+        b: mix(source.b, backdrop.b),
+        a: 255,
+    }
+}
+
 #[cfg(test)]
 mod canvas_tests {
     use super::*;
@@ -188,6 +269,7 @@ mod canvas_tests {
             r: 255,
             g: 255,
             b: 255,
+            a: 255,
         };
         assert_eq!(pixels.len(), 64);
         assert!(pixels.iter().all(|p| *p == white));
@@ -197,7 +279,12 @@ mod canvas_tests {
     fn paint_item() {
         let mut canvas = Canvas::new(4, 4);
 
-        let black = Color { r: 0, g: 0, b: 0 };
+        let black = Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
         let rect = Rect {
             x: 2.0,
             y: 1.0,
@@ -216,6 +303,7 @@ mod canvas_tests {
             r: 255,
             g: 255,
             b: 255,
+            a: 255,
         };
         assert_eq!(pixels.len(), 16);
         assert_eq!(pixels[0..4], vec![white; 4]);
@@ -223,4 +311,126 @@ mod canvas_tests {
         assert_eq!(pixels[8..12], vec![white, white, black, black]);
         assert_eq!(pixels[12..16], vec![white, white, black, black]);
     }
+
+    #[test]
+    fn paint_item_translucent() {
+        let mut canvas = Canvas::new(1, 1);
+
+        let translucent_black = Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 64,
+        };
+        let rect = Rect {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        };
+        canvas.paint_item(&DisplayCommand::SolidColor(translucent_black, rect));
+        assert_eq!(
+            canvas.pixels[0],
+            Color {
+                r: 191,
+                g: 191,
+                b: 191,
+                a: 255,
+            }
+        );
+
+        let transparent = Color {
+            a: 0,
+            ..translucent_black
+        };
+        canvas.paint_item(&DisplayCommand::SolidColor(transparent, rect));
+        assert_eq!(canvas.pixels[0].r, 191);
+    }
+
+    #[test]
+    fn paint_item_outside_canvas() {
+        let mut canvas = Canvas::new(4, 4);
+
+        let black = Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+        let rect = Rect {
+            x: -2.0,
+            y: 3.0,
+            width: 3.0,
+            height: 5.0,
+        };
+        canvas.paint_item(&DisplayCommand::SolidColor(black, rect));
+
+        let white = Color {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 255,
+        };
+        let pixels = canvas.pixels;
+        assert_eq!(pixels[0..12], vec![white; 12]);
+        assert_eq!(pixels[12..16], vec![black, white, white, white]);
+    }
+
+    #[test]
+    fn paint_text() {
+        let mut canvas = Canvas::new(16, 32);
+        let red = Color {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+        let white = Color {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 255,
+        };
+        // At twice the size of the font, a pixel of a glyph covers 2x2
+        // pixels.
+        let rect = Rect {
+            x: 0.0,
+            y: 0.0,
+            width: 16.0,
+            height: 32.0,
+        };
+        canvas.paint_item(&DisplayCommand::Text("A".to_string(), red, rect));
+        let pixel = |x: usize, y: usize| canvas.pixels[x + y * canvas.width];
+        // The apex and the bar of `A`.
+        assert_eq!((pixel(6, 6), pixel(7, 7)), (red, red));
+        assert_eq!((pixel(5, 6), pixel(6, 8)), (white, white));
+        assert!((0..14).all(|x| pixel(x, 16) == red));
+        assert_eq!(pixel(14, 16), white);
+    }
+
+    #[test]
+    fn text_display_list() {
+        use crate::{css, html, layout, style};
+
+        let dom = html::parse("<p>ab <b>c</b></p>".to_string());
+        let stylesheet = css::parse("p { color: #ff0000; } b { color: #0000ff; }".to_string());
+        let styled = style::style_tree(&dom, &[stylesheet], &css::Device::new(800.0, 600.0));
+        let mut viewport: layout::Dimensions = Default::default();
+        viewport.content.width = 800.0;
+        let root = layout::layout_tree(&styled, &mut viewport);
+
+        let texts: Vec<(String, u8, f32)> = build_display_list(&root)
+            .into_iter()
+            .filter_map(|item| match item {
+                DisplayCommand::Text(text, color, rect) => Some((text, color.b, rect.x)),
+                DisplayCommand::SolidColor(..) => None,
+            })
+            .collect();
+        // Text is painted in the color of its element, at 8px per
+        // character at the default font size.
+        assert_eq!(
+            texts,
+            [("ab ".to_string(), 0, 0.0), ("c".to_string(), 255, 24.0)]
+        );
+    }
 }
@@ -0,0 +1,249 @@
+//! The built-in font: an 8x16 bitmap font covering printable ASCII and
+//! Latin-1, drawn for this crate. Every glyph is half as wide as it is tall,
+//! with the baseline below row 12, which is what layout assumes of text.
+
+/// Size of a glyph in pixels, at a font size of 16px.
+pub(super) const WIDTH: usize = 8;
+pub(super) const HEIGHT: usize = 16;
+
+/// Drawn for characters the font doesn't cover: an empty box.
+const MISSING: [u8; HEIGHT] = [
+    0x00, 0x00, 0x00, 0xfe, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0xfe, 0x00, 0x00, 0x00,
+];
+
+/// The glyphs of U+0020 to U+007E, then of U+00A0 to U+00FF. Each glyph is a
+/// row of pixels per byte, from the top, with the leftmost pixel in the most
+/// significant bit.
+#[rustfmt::skip]
+static GLYPHS: [[u8; HEIGHT]; 191] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+0020 space
+    [0x00, 0x00, 0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00], // U+0021 !
+    [0x00, 0x00, 0x00, 0x28, 0x28, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+0022 "
+    [0x00, 0x00, 0x00, 0x00, 0x28, 0x28, 0xfe, 0x28, 0x28, 0xfe, 0x28, 0x28, 0x00, 0x00, 0x00, 0x00], // U+0023 #
+    [0x00, 0x00, 0x10, 0x7c, 0x90, 0x90, 0x78, 0x14, 0x14, 0x14, 0xf8, 0x10, 0x00, 0x00, 0x00, 0x00], // U+0024 $
+    [0x00, 0x00, 0x00, 0x00, 0xc2, 0xc4, 0x08, 0x10, 0x20, 0x40, 0x8c, 0x0c, 0x00, 0x00, 0x00, 0x00], // U+0025 %
+    [0x00, 0x00, 0x00, 0x60, 0x90, 0x90, 0x60, 0x64, 0x94, 0x88, 0x94, 0x64, 0x00, 0x00, 0x00, 0x00], // U+0026 &
+    [0x00, 0x00, 0x00, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+0027 '
+    [0x00, 0x00, 0x00, 0x08, 0x10, 0x20, 0x20, 0x20, 0x20, 0x20, 0x10, 0x08, 0x00, 0x00, 0x00, 0x00], // U+0028 (
+    [0x00, 0x00, 0x00, 0x20, 0x10, 0x08, 0x08, 0x08, 0x08, 0x08, 0x10, 0x20, 0x00, 0x00, 0x00, 0x00], // U+0029 )
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x92, 0x54, 0x38, 0x54, 0x92, 0x10, 0x00, 0x00, 0x00, 0x00], // U+002A *
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x10, 0x10, 0xfe, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00], // U+002B +
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x08, 0x10, 0x00, 0x00], // U+002C ,
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+002D -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // U+002E .
+    [0x00, 0x00, 0x00, 0x02, 0x04, 0x04, 0x08, 0x10, 0x20, 0x40, 0x40, 0x80, 0x00, 0x00, 0x00, 0x00], // U+002F /
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x86, 0x8a, 0x92, 0xa2, 0xc2, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0030 0
+    [0x00, 0x00, 0x00, 0x10, 0x30, 0x50, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0031 1
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x02, 0x04, 0x18, 0x20, 0x40, 0x80, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+0032 2
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x02, 0x02, 0x3c, 0x02, 0x02, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0033 3
+    [0x00, 0x00, 0x00, 0x0c, 0x14, 0x24, 0x44, 0x84, 0xfe, 0x04, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00], // U+0034 4
+    [0x00, 0x00, 0x00, 0xfe, 0x80, 0x80, 0xfc, 0x02, 0x02, 0x02, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0035 5
+    [0x00, 0x00, 0x00, 0x3c, 0x40, 0x80, 0x80, 0xfc, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0036 6
+    [0x00, 0x00, 0x00, 0xfe, 0x02, 0x04, 0x08, 0x10, 0x10, 0x20, 0x20, 0x20, 0x00, 0x00, 0x00, 0x00], // U+0037 7
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x7c, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0038 8
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x7e, 0x02, 0x02, 0x04, 0x78, 0x00, 0x00, 0x00, 0x00], // U+0039 9
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // U+003A :
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x18, 0x18, 0x08, 0x10, 0x00, 0x00], // U+003B ;
+    [0x00, 0x00, 0x00, 0x00, 0x04, 0x08, 0x10, 0x20, 0x10, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00], // U+003C <
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfe, 0x00, 0x00, 0xfe, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+003D =
+    [0x00, 0x00, 0x00, 0x00, 0x40, 0x20, 0x10, 0x08, 0x10, 0x20, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00], // U+003E >
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x02, 0x04, 0x18, 0x10, 0x10, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00], // U+003F ?
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x9e, 0xa6, 0xa6, 0x9a, 0x80, 0x80, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0040 @
+    [0x00, 0x00, 0x00, 0x10, 0x28, 0x44, 0x82, 0x82, 0xfe, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+0041 A
+    [0x00, 0x00, 0x00, 0xfc, 0x82, 0x82, 0x82, 0xfc, 0x82, 0x82, 0x82, 0xfc, 0x00, 0x00, 0x00, 0x00], // U+0042 B
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x80, 0x80, 0x80, 0x80, 0x80, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0043 C
+    [0x00, 0x00, 0x00, 0xf8, 0x84, 0x82, 0x82, 0x82, 0x82, 0x82, 0x84, 0xf8, 0x00, 0x00, 0x00, 0x00], // U+0044 D
+    [0x00, 0x00, 0x00, 0xfe, 0x80, 0x80, 0x80, 0xfc, 0x80, 0x80, 0x80, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+0045 E
+    [0x00, 0x00, 0x00, 0xfe, 0x80, 0x80, 0x80, 0xfc, 0x80, 0x80, 0x80, 0x80, 0x00, 0x00, 0x00, 0x00], // U+0046 F
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x80, 0x80, 0x8e, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0047 G
+    [0x00, 0x00, 0x00, 0x82, 0x82, 0x82, 0x82, 0xfe, 0x82, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+0048 H
+    [0x00, 0x00, 0x00, 0x7c, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0049 I
+    [0x00, 0x00, 0x00, 0x3e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x88, 0x88, 0x70, 0x00, 0x00, 0x00, 0x00], // U+004A J
+    [0x00, 0x00, 0x00, 0x82, 0x84, 0x88, 0x90, 0xe0, 0x90, 0x88, 0x84, 0x82, 0x00, 0x00, 0x00, 0x00], // U+004B K
+    [0x00, 0x00, 0x00, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+004C L
+    [0x00, 0x00, 0x00, 0x82, 0xc6, 0xaa, 0x92, 0x82, 0x82, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+004D M
+    [0x00, 0x00, 0x00, 0x82, 0xc2, 0xa2, 0x92, 0x8a, 0x86, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+004E N
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+004F O
+    [0x00, 0x00, 0x00, 0xfc, 0x82, 0x82, 0x82, 0xfc, 0x80, 0x80, 0x80, 0x80, 0x00, 0x00, 0x00, 0x00], // U+0050 P
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x82, 0x8a, 0x84, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+0051 Q
+    [0x00, 0x00, 0x00, 0xfc, 0x82, 0x82, 0x82, 0xfc, 0x88, 0x84, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+0052 R
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x80, 0x80, 0x7c, 0x02, 0x02, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0053 S
+    [0x00, 0x00, 0x00, 0xfe, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00], // U+0054 T
+    [0x00, 0x00, 0x00, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0055 U
+    [0x00, 0x00, 0x00, 0x82, 0x82, 0x82, 0x82, 0x44, 0x44, 0x28, 0x28, 0x10, 0x00, 0x00, 0x00, 0x00], // U+0056 V
+    [0x00, 0x00, 0x00, 0x82, 0x82, 0x82, 0x82, 0x92, 0x92, 0xaa, 0xc6, 0x82, 0x00, 0x00, 0x00, 0x00], // U+0057 W
+    [0x00, 0x00, 0x00, 0x82, 0x82, 0x44, 0x28, 0x10, 0x28, 0x44, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+0058 X
+    [0x00, 0x00, 0x00, 0x82, 0x82, 0x44, 0x28, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00], // U+0059 Y
+    [0x00, 0x00, 0x00, 0xfe, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+005A Z
+    [0x00, 0x00, 0x00, 0x78, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x78, 0x00, 0x00, 0x00, 0x00], // U+005B [
+    [0x00, 0x00, 0x00, 0x80, 0x40, 0x40, 0x20, 0x10, 0x08, 0x04, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00], // U+005C \
+    [0x00, 0x00, 0x00, 0x3c, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x3c, 0x00, 0x00, 0x00, 0x00], // U+005D ]
+    [0x00, 0x00, 0x00, 0x10, 0x28, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+005E ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfe, 0x00, 0x00], // U+005F _
+    [0x00, 0x00, 0x20, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+0060 `
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0x02, 0x7e, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+0061 a
+    [0x00, 0x00, 0x00, 0x80, 0x80, 0x80, 0xfc, 0x82, 0x82, 0x82, 0x82, 0xfc, 0x00, 0x00, 0x00, 0x00], // U+0062 b
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0x82, 0x80, 0x80, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0063 c
+    [0x00, 0x00, 0x00, 0x02, 0x02, 0x02, 0x7e, 0x82, 0x82, 0x82, 0x82, 0x7e, 0x00, 0x00, 0x00, 0x00], // U+0064 d
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0x82, 0xfe, 0x80, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0065 e
+    [0x00, 0x00, 0x00, 0x1c, 0x20, 0x20, 0xf8, 0x20, 0x20, 0x20, 0x20, 0x20, 0x00, 0x00, 0x00, 0x00], // U+0066 f
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7e, 0x82, 0x82, 0x82, 0x7e, 0x02, 0x82, 0x7c, 0x00, 0x00], // U+0067 g
+    [0x00, 0x00, 0x00, 0x80, 0x80, 0x80, 0xfc, 0x82, 0x82, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+0068 h
+    [0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x70, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+0069 i
+    [0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x1c, 0x04, 0x04, 0x04, 0x04, 0x04, 0x84, 0x78, 0x00, 0x00], // U+006A j
+    [0x00, 0x00, 0x00, 0x80, 0x80, 0x80, 0x84, 0x88, 0xf0, 0x88, 0x84, 0x82, 0x00, 0x00, 0x00, 0x00], // U+006B k
+    [0x00, 0x00, 0x00, 0x70, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+006C l
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xec, 0x92, 0x92, 0x92, 0x92, 0x92, 0x00, 0x00, 0x00, 0x00], // U+006D m
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xbc, 0xc2, 0x82, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+006E n
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+006F o
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfc, 0x82, 0x82, 0x82, 0xfc, 0x80, 0x80, 0x80, 0x00, 0x00], // U+0070 p
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7e, 0x82, 0x82, 0x82, 0x7e, 0x02, 0x02, 0x02, 0x00, 0x00], // U+0071 q
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xbc, 0xc2, 0x80, 0x80, 0x80, 0x80, 0x00, 0x00, 0x00, 0x00], // U+0072 r
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7e, 0x80, 0x7c, 0x02, 0x02, 0xfc, 0x00, 0x00, 0x00, 0x00], // U+0073 s
+    [0x00, 0x00, 0x00, 0x00, 0x20, 0x20, 0xfc, 0x20, 0x20, 0x20, 0x22, 0x1c, 0x00, 0x00, 0x00, 0x00], // U+0074 t
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x82, 0x82, 0x82, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+0075 u
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x82, 0x82, 0x44, 0x44, 0x28, 0x10, 0x00, 0x00, 0x00, 0x00], // U+0076 v
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x82, 0x82, 0x92, 0x92, 0xaa, 0x44, 0x00, 0x00, 0x00, 0x00], // U+0077 w
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x82, 0x44, 0x38, 0x38, 0x44, 0x82, 0x00, 0x00, 0x00, 0x00], // U+0078 x
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x82, 0x82, 0x82, 0x82, 0x7e, 0x02, 0x82, 0x7c, 0x00, 0x00], // U+0079 y
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfe, 0x04, 0x08, 0x10, 0x20, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+007A z
+    [0x00, 0x00, 0x00, 0x0c, 0x10, 0x10, 0x10, 0x60, 0x10, 0x10, 0x10, 0x0c, 0x00, 0x00, 0x00, 0x00], // U+007B {
+    [0x00, 0x00, 0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00], // U+007C |
+    [0x00, 0x00, 0x00, 0x60, 0x10, 0x10, 0x10, 0x0c, 0x10, 0x10, 0x10, 0x60, 0x00, 0x00, 0x00, 0x00], // U+007D }
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x62, 0x92, 0x8c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+007E ~
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00A0 no-break space
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00], // U+00A1 ¡
+    [0x00, 0x00, 0x00, 0x00, 0x10, 0x7c, 0x92, 0x90, 0x90, 0x92, 0x7c, 0x10, 0x00, 0x00, 0x00, 0x00], // U+00A2 ¢
+    [0x00, 0x00, 0x00, 0x38, 0x44, 0x40, 0x40, 0xf0, 0x40, 0x40, 0x42, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+00A3 £
+    [0x00, 0x00, 0x00, 0x00, 0x82, 0x7c, 0x44, 0x44, 0x44, 0x7c, 0x82, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00A4 ¤
+    [0x00, 0x00, 0x00, 0x82, 0x44, 0x28, 0x10, 0xfe, 0x10, 0xfe, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00], // U+00A5 ¥
+    [0x00, 0x00, 0x00, 0x10, 0x10, 0x10, 0x10, 0x00, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00], // U+00A6 ¦
+    [0x00, 0x00, 0x00, 0x78, 0x84, 0x60, 0x90, 0x88, 0x48, 0x18, 0x84, 0x78, 0x00, 0x00, 0x00, 0x00], // U+00A7 §
+    [0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00A8 ¨
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0xba, 0xa2, 0xa2, 0xba, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00A9 ©
+    [0x00, 0x00, 0x00, 0x70, 0x08, 0x78, 0x88, 0x78, 0x00, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00AA ª
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x12, 0x24, 0x48, 0x24, 0x12, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00AB «
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfe, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00AC ¬
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00AD soft hyphen
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0xb2, 0xaa, 0xb2, 0xaa, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00AE ®
+    [0x00, 0xfe, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00AF ¯
+    [0x00, 0x00, 0x00, 0x70, 0x88, 0x88, 0x70, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00B0 °
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x10, 0xfe, 0x10, 0x10, 0x00, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+00B1 ±
+    [0x00, 0x00, 0x00, 0x70, 0x88, 0x10, 0x20, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00B2 ²
+    [0x00, 0x00, 0x00, 0xf0, 0x08, 0x30, 0x08, 0xf0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00B3 ³
+    [0x00, 0x00, 0x00, 0x08, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00B4 ´
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x82, 0x82, 0x82, 0x82, 0xc6, 0xba, 0x80, 0x80, 0x00, 0x00], // U+00B5 µ
+    [0x00, 0x00, 0x00, 0x7e, 0xf2, 0xf2, 0x72, 0x12, 0x12, 0x12, 0x12, 0x12, 0x00, 0x00, 0x00, 0x00], // U+00B6 ¶
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00B7 ·
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x08, 0x30, 0x00], // U+00B8 ¸
+    [0x00, 0x00, 0x00, 0x20, 0x60, 0x20, 0x20, 0x70, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00B9 ¹
+    [0x00, 0x00, 0x00, 0x70, 0x88, 0x88, 0x70, 0x00, 0xf8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00BA º
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x90, 0x48, 0x24, 0x48, 0x90, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00BB »
+    [0x00, 0x00, 0x00, 0x40, 0xc0, 0x44, 0x48, 0x10, 0x28, 0x58, 0xbc, 0x08, 0x00, 0x00, 0x00, 0x00], // U+00BC ¼
+    [0x00, 0x00, 0x00, 0x40, 0xc0, 0x44, 0x48, 0x10, 0x2c, 0x52, 0x84, 0x0e, 0x00, 0x00, 0x00, 0x00], // U+00BD ½
+    [0x00, 0x00, 0x00, 0xc0, 0x20, 0xc4, 0x28, 0xd0, 0x28, 0x58, 0xbc, 0x08, 0x00, 0x00, 0x00, 0x00], // U+00BE ¾
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x10, 0x10, 0x20, 0x40, 0x82, 0x82, 0x7c, 0x00], // U+00BF ¿
+    [0x20, 0x10, 0x00, 0x10, 0x28, 0x44, 0x82, 0x82, 0xfe, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+00C0 À
+    [0x08, 0x10, 0x00, 0x10, 0x28, 0x44, 0x82, 0x82, 0xfe, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+00C1 Á
+    [0x10, 0x28, 0x00, 0x10, 0x28, 0x44, 0x82, 0x82, 0xfe, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+00C2 Â
+    [0x64, 0x98, 0x00, 0x10, 0x28, 0x44, 0x82, 0x82, 0xfe, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+00C3 Ã
+    [0x00, 0x28, 0x00, 0x10, 0x28, 0x44, 0x82, 0x82, 0xfe, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+00C4 Ä
+    [0x10, 0x28, 0x10, 0x10, 0x28, 0x44, 0x82, 0x82, 0xfe, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+00C5 Å
+    [0x00, 0x00, 0x00, 0x3e, 0x50, 0x90, 0x90, 0xfc, 0x90, 0x90, 0x90, 0x9e, 0x00, 0x00, 0x00, 0x00], // U+00C6 Æ
+    [0x00, 0x00, 0x00, 0x7c, 0x82, 0x80, 0x80, 0x80, 0x80, 0x80, 0x82, 0x7c, 0x10, 0x08, 0x30, 0x00], // U+00C7 Ç
+    [0x20, 0x10, 0x00, 0xfe, 0x80, 0x80, 0x80, 0xfc, 0x80, 0x80, 0x80, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+00C8 È
+    [0x08, 0x10, 0x00, 0xfe, 0x80, 0x80, 0x80, 0xfc, 0x80, 0x80, 0x80, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+00C9 É
+    [0x10, 0x28, 0x00, 0xfe, 0x80, 0x80, 0x80, 0xfc, 0x80, 0x80, 0x80, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+00CA Ê
+    [0x00, 0x28, 0x00, 0xfe, 0x80, 0x80, 0x80, 0xfc, 0x80, 0x80, 0x80, 0xfe, 0x00, 0x00, 0x00, 0x00], // U+00CB Ë
+    [0x20, 0x10, 0x00, 0x7c, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00CC Ì
+    [0x08, 0x10, 0x00, 0x7c, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00CD Í
+    [0x10, 0x28, 0x00, 0x7c, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00CE Î
+    [0x00, 0x28, 0x00, 0x7c, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00CF Ï
+    [0x00, 0x00, 0x00, 0xf8, 0x44, 0x42, 0x42, 0xf2, 0x42, 0x42, 0x44, 0xf8, 0x00, 0x00, 0x00, 0x00], // U+00D0 Ð
+    [0x64, 0x98, 0x00, 0x82, 0xc2, 0xa2, 0x92, 0x8a, 0x86, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+00D1 Ñ
+    [0x20, 0x10, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00D2 Ò
+    [0x08, 0x10, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00D3 Ó
+    [0x10, 0x28, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00D4 Ô
+    [0x64, 0x98, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00D5 Õ
+    [0x00, 0x28, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00D6 Ö
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x82, 0x44, 0x28, 0x10, 0x28, 0x44, 0x82, 0x00, 0x00, 0x00, 0x00], // U+00D7 ×
+    [0x00, 0x00, 0x00, 0x7c, 0x86, 0x8a, 0x8a, 0x92, 0xa2, 0xa2, 0xc2, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00D8 Ø
+    [0x20, 0x10, 0x00, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00D9 Ù
+    [0x08, 0x10, 0x00, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00DA Ú
+    [0x10, 0x28, 0x00, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00DB Û
+    [0x00, 0x28, 0x00, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00DC Ü
+    [0x08, 0x10, 0x00, 0x82, 0x82, 0x44, 0x28, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00], // U+00DD Ý
+    [0x00, 0x00, 0x00, 0x80, 0x80, 0xfc, 0x82, 0x82, 0x82, 0xfc, 0x80, 0x80, 0x00, 0x00, 0x00, 0x00], // U+00DE Þ
+    [0x00, 0x00, 0x00, 0x78, 0x84, 0x84, 0x88, 0xb0, 0x88, 0x84, 0x84, 0xb8, 0x00, 0x00, 0x00, 0x00], // U+00DF ß
+    [0x00, 0x00, 0x00, 0x20, 0x10, 0x00, 0x7c, 0x02, 0x7e, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+00E0 à
+    [0x00, 0x00, 0x00, 0x08, 0x10, 0x00, 0x7c, 0x02, 0x7e, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+00E1 á
+    [0x00, 0x00, 0x00, 0x10, 0x28, 0x00, 0x7c, 0x02, 0x7e, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+00E2 â
+    [0x00, 0x00, 0x00, 0x64, 0x98, 0x00, 0x7c, 0x02, 0x7e, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+00E3 ã
+    [0x00, 0x00, 0x00, 0x00, 0x28, 0x00, 0x7c, 0x02, 0x7e, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+00E4 ä
+    [0x00, 0x00, 0x10, 0x28, 0x10, 0x00, 0x7c, 0x02, 0x7e, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+00E5 å
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x6c, 0x12, 0x7e, 0x90, 0x92, 0x6c, 0x00, 0x00, 0x00, 0x00], // U+00E6 æ
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7c, 0x82, 0x80, 0x80, 0x82, 0x7c, 0x10, 0x08, 0x30, 0x00], // U+00E7 ç
+    [0x00, 0x00, 0x00, 0x20, 0x10, 0x00, 0x7c, 0x82, 0xfe, 0x80, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00E8 è
+    [0x00, 0x00, 0x00, 0x08, 0x10, 0x00, 0x7c, 0x82, 0xfe, 0x80, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00E9 é
+    [0x00, 0x00, 0x00, 0x10, 0x28, 0x00, 0x7c, 0x82, 0xfe, 0x80, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00EA ê
+    [0x00, 0x00, 0x00, 0x00, 0x28, 0x00, 0x7c, 0x82, 0xfe, 0x80, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00EB ë
+    [0x00, 0x00, 0x00, 0x20, 0x10, 0x00, 0x70, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00EC ì
+    [0x00, 0x00, 0x00, 0x08, 0x10, 0x00, 0x70, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00ED í
+    [0x00, 0x00, 0x00, 0x10, 0x28, 0x00, 0x70, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00EE î
+    [0x00, 0x00, 0x00, 0x00, 0x28, 0x00, 0x70, 0x10, 0x10, 0x10, 0x10, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00EF ï
+    [0x00, 0x00, 0x00, 0x68, 0x10, 0x28, 0x04, 0x7e, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00F0 ð
+    [0x00, 0x00, 0x00, 0x64, 0x98, 0x00, 0xbc, 0xc2, 0x82, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00], // U+00F1 ñ
+    [0x00, 0x00, 0x00, 0x20, 0x10, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00F2 ò
+    [0x00, 0x00, 0x00, 0x08, 0x10, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00F3 ó
+    [0x00, 0x00, 0x00, 0x10, 0x28, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00F4 ô
+    [0x00, 0x00, 0x00, 0x64, 0x98, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00F5 õ
+    [0x00, 0x00, 0x00, 0x00, 0x28, 0x00, 0x7c, 0x82, 0x82, 0x82, 0x82, 0x7c, 0x00, 0x00, 0x00, 0x00], // U+00F6 ö
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0xfe, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // U+00F7 ÷
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x7c, 0x8a, 0x92, 0xa2, 0xc2, 0x7c, 0x80, 0x00, 0x00, 0x00], // U+00F8 ø
+    [0x00, 0x00, 0x00, 0x20, 0x10, 0x00, 0x82, 0x82, 0x82, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+00F9 ù
+    [0x00, 0x00, 0x00, 0x08, 0x10, 0x00, 0x82, 0x82, 0x82, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+00FA ú
+    [0x00, 0x00, 0x00, 0x10, 0x28, 0x00, 0x82, 0x82, 0x82, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+00FB û
+    [0x00, 0x00, 0x00, 0x00, 0x28, 0x00, 0x82, 0x82, 0x82, 0x82, 0x86, 0x7a, 0x00, 0x00, 0x00, 0x00], // U+00FC ü
+    [0x00, 0x00, 0x00, 0x08, 0x10, 0x00, 0x82, 0x82, 0x82, 0x82, 0x7e, 0x02, 0x82, 0x7c, 0x00, 0x00], // U+00FD ý
+    [0x00, 0x00, 0x00, 0x80, 0x80, 0x80, 0xfc, 0x82, 0x82, 0x82, 0x82, 0xfc, 0x80, 0x80, 0x80, 0x00], // U+00FE þ
+    [0x00, 0x00, 0x00, 0x00, 0x28, 0x00, 0x82, 0x82, 0x82, 0x82, 0x7e, 0x02, 0x82, 0x7c, 0x00, 0x00], // U+00FF ÿ
+];
+
+/// Return the glyph of `c`. White space and control characters are blank.
+pub(super) fn glyph(c: char) -> &'static [u8; HEIGHT] {
+    match c as u32 {
+        0x20..=0x7e => &GLYPHS[c as usize - 0x20],
+        0xa0..=0xff => &GLYPHS[c as usize - 0xa0 + 0x5f],
+        _ if c.is_whitespace() || c.is_control() => &GLYPHS[0],
+        _ => &MISSING,
+    }
+}
+
+/// Return true if the pixel at column `x` and row `y` of `glyph` is set.
+pub(super) fn is_set(glyph: &[u8; HEIGHT], x: usize, y: usize) -> bool {
+    glyph[y] & (0x80 >> x) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coverage() {
+        let blank = [0; HEIGHT];
+        assert_eq!(glyph(' '), &blank);
+        assert_eq!(glyph('\u{a0}'), &blank);
+        assert_eq!(glyph('\t'), &blank);
+        for c in ('!'..='~').chain('¡'..='ÿ').filter(|&c| c != '\u{ad}') {
+            assert_ne!(glyph(c), &blank, "{:?} is blank", c);
+            assert_ne!(glyph(c), &MISSING);
+        }
+        assert_eq!(glyph('€'), &MISSING);
+
+        // The apex of `A`, and the bottom of the descender of `g`.
+        assert!(is_set(glyph('A'), 3, 3));
+        assert!(!is_set(glyph('A'), 3, 4));
+        assert!(is_set(glyph('g'), 1, 13));
+        assert_eq!(glyph('ä')[6..], glyph('a')[6..]);
+    }
+}
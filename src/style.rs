@@ -1,9 +1,35 @@
 //! This module controls the style step, combining the DOM and the CSSOM into
 //! a style tree (a render tree).
 
-use crate::css::{Rule, Selector, SimpleSelector, Specificity, Stylesheet, Value};
+use crate::css::{
+    self, Combinator, ContentItem, Declaration, Device, Layers, LengthContext, Origin,
+    PseudoElement, Rule, Selector, SimpleSelector, Specificity, Stylesheet, Unit, Value,
+    DEFAULT_FONT_SIZE,
+};
 use crate::dom::{ElementData, Node, NodeType};
-use std::collections::HashMap;
+use restyle::Previous;
+use rule_hash::RuleHash;
+use sharing::{ElementStyle, StyleSharingCache};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+mod computed;
+mod counters;
+mod display;
+mod explain;
+mod invalidation;
+mod parallel;
+mod properties;
+mod restyle;
+mod rule_hash;
+mod sharing;
+mod user_agent;
+mod variables;
+
+pub use computed::ComputedStyle;
+pub use display::{Display, DisplayInside, DisplayInternal, DisplayOutside};
+pub use explain::{explain_cascade, query_selector, MatchingRule};
+pub use restyle::Restyler;
 
 /// Map from CSS property names to values.
 type PropertyMap = HashMap<String, Value>;
@@ -11,106 +37,712 @@ type PropertyMap = HashMap<String, Value>;
 /// A node with associated style data.
 #[derive(Debug)]
 pub struct StyledNode<'a> {
-    /// A pointer to a DOM node.
-    ///
-    /// NOTE: For now, This is only for debug.
-    #[allow(dead_code)]
+    /// The DOM node, or for a pseudo-element and its generated text, the
+    /// element it belongs to.
     node: &'a Node,
 
-    specified_values: PropertyMap,
+    /// Set if this is a pseudo-element box generated for `node`.
+    pub pseudo_element: Option<PseudoElement>,
+    /// Text produced by the `content` property of a pseudo-element.
+    generated_text: Option<String>,
+
+    /// Shared with the elements that have the same style, see `sharing`.
+    computed_values: Arc<PropertyMap>,
     pub children: Vec<StyledNode<'a>>,
-}
 
-pub enum Display {
-    Inline,
-    Block,
-    None,
+    /// The `::marker`, `::before` and `::after` pseudo-elements, if they
+    /// generate boxes.
+    pub marker: Option<Box<StyledNode<'a>>>,
+    pub before: Option<Box<StyledNode<'a>>>,
+    pub after: Option<Box<StyledNode<'a>>>,
 }
 
 impl<'a> StyledNode<'a> {
-    /// Return the specified value of a property if it exists, otherwise `None`.
+    /// Create a pseudo-element node for the element `node`. Its content is
+    /// generated later.
+    fn pseudo_element(
+        node: &'a Node,
+        pseudo_element: PseudoElement,
+        computed_values: Arc<PropertyMap>,
+    ) -> StyledNode<'a> {
+        StyledNode {
+            node,
+            pseudo_element: Some(pseudo_element),
+            generated_text: None,
+            computed_values,
+            children: vec![],
+            marker: None,
+            before: None,
+            after: None,
+        }
+    }
+
+    /// The DOM node this node was styled for. Pseudo-elements and their
+    /// generated text give the element they belong to.
+    pub fn node(&self) -> &'a Node {
+        self.node
+    }
+
+    /// Find the styled node of the DOM node `node` in this tree.
+    pub fn find(&self, node: &Node) -> Option<&StyledNode<'a>> {
+        if std::ptr::eq(self.node, node) {
+            return Some(self);
+        }
+        // Pseudo-elements are only in `marker`, `before` and `after`.
+        self.children.iter().find_map(|child| child.find(node))
+    }
+
+    /// Iterate over the children of this node in box tree order: the
+    /// `::marker`, `::before`, the DOM children, then `::after`.
+    pub fn box_children(&self) -> impl Iterator<Item = &StyledNode<'a>> {
+        self.marker
+            .as_deref()
+            .into_iter()
+            .chain(self.before.as_deref())
+            .chain(&self.children)
+            .chain(self.after.as_deref())
+    }
+
+    /// Return the text of a text node or of generated content, or `None` for
+    /// elements.
+    pub fn text(&self) -> Option<&str> {
+        match (&self.generated_text, &self.node.node_type) {
+            (Some(text), _) => Some(text),
+            (None, NodeType::Text(text)) => Some(text),
+            (None, NodeType::Element(_)) => None,
+        }
+    }
+
+    /// Return the computed value of a property if it exists, otherwise `None`.
     pub fn value(&self, name: &str) -> Option<Value> {
-        self.specified_values.get(name).map(|v| v.clone())
+        self.computed_values.get(name).cloned()
+    }
+
+    /// Return the computed value of property `name`, or value `default` if it
+    /// doesn't exist.
+    pub fn value_or(&self, name: &str, default: &Value) -> Value {
+        self.value(name).unwrap_or_else(|| default.clone())
     }
 
-    /// Return the specified value of property `name`, or property `fallback_name`
-    /// if that doesn't exist, or value `default` if neither does.
-    pub fn lookup(&self, name: &str, fallback_name: &str, default: &Value) -> Value {
-        self.value(name)
-            .unwrap_or_else(|| self.value(fallback_name).unwrap_or_else(|| default.clone()))
+    /// The `font-size` in px, given the lengths of the parent element. The
+    /// computed font size is in px unless it depends on the viewport.
+    pub fn font_size(&self, parent: &LengthContext) -> f32 {
+        match self.value("font-size") {
+            Some(length @ Value::Length(..)) => length.to_px(parent, parent.font_size),
+            _ => parent.font_size,
+        }
     }
 
     /// The value of the `display` property (defaults to inline).
     pub fn display(&self) -> Display {
-        match self.value("display") {
-            Some(Value::Keyword(s)) => match &*s {
-                "block" => Display::Block,
-                "none" => Display::None,
-                _ => Display::Inline,
-            },
-            _ => Display::Inline,
-        }
+        Display::of(&self.computed_values)
     }
 }
 
-/// Apply a stylesheet to an entire DOM tree, returning a StyledNode tree.
-pub fn style_tree<'a>(root: &'a Node, stylesheet: &'a Stylesheet) -> StyledNode<'a> {
-    StyledNode {
-        node: root,
-        specified_values: match root.node_type {
-            NodeType::Element(ref elem) => specified_values(elem, stylesheet),
-            NodeType::Text(_) => HashMap::new(),
+/// Apply stylesheets to an entire DOM tree, returning a StyledNode tree.
+/// Stylesheets are given in source order, and come after the built-in user
+/// agent stylesheet. Media queries are evaluated against `device`, and
+/// `@supports` conditions against the properties and values the engine
+/// implements.
+pub fn style_tree<'a>(
+    root: &'a Node,
+    stylesheets: &[Stylesheet],
+    device: &Device,
+) -> StyledNode<'a> {
+    style_tree_with(root, stylesheets, device, sharing::CACHE_SIZE, 1)
+}
+
+/// Like `style_tree`, styling independent subtrees on up to `threads`
+/// threads. The result is the same as with `style_tree`.
+pub fn style_tree_parallel<'a>(
+    root: &'a Node,
+    stylesheets: &[Stylesheet],
+    device: &Device,
+    threads: usize,
+) -> StyledNode<'a> {
+    let threads = threads.max(1);
+    style_tree_with(root, stylesheets, device, sharing::CACHE_SIZE, threads)
+}
+
+/// Like `style_tree`, sharing the styles of the last `cache_size` elements
+/// styled on each of up to `threads` threads.
+fn style_tree_with<'a>(
+    root: &'a Node,
+    stylesheets: &[Stylesheet],
+    device: &Device,
+    cache_size: usize,
+    threads: usize,
+) -> StyledNode<'a> {
+    let stylesheets = active_stylesheets(stylesheets, device);
+    let mut cache = StyleSharingCache::new(cache_size);
+    // Subtree sizes are only needed to split the work between threads.
+    let sizes = (threads > 1).then(|| parallel::Sizes::new(root));
+    let context = StyleContext {
+        stylesheets: &stylesheets,
+        cache: &mut cache,
+        threads,
+        sizes: sizes.as_ref(),
+        previous: None,
+    };
+    let mut styled = build_style_tree(root, None, DEFAULT_FONT_SIZE, None, &[], context);
+    // Generated content depends on counters, which are only known once the
+    // whole tree is styled.
+    counters::generate_content(&mut styled);
+    styled
+}
+
+/// The style rules of the user agent stylesheet and of `stylesheets` that
+/// apply to `device`, indexed for matching.
+fn active_stylesheets<'s>(
+    stylesheets: &'s [Stylesheet],
+    device: &Device,
+) -> Vec<ActiveStylesheet<'s>> {
+    let stylesheets: Vec<&Stylesheet> = std::iter::once(user_agent::stylesheet())
+        .chain(stylesheets)
+        .collect();
+    // The stylesheets of an origin share their cascade layers, which are
+    // only ordered once all of them are known.
+    let mut layers: BTreeMap<Origin, Layers> = BTreeMap::new();
+    let style_rules: Vec<_> = stylesheets
+        .iter()
+        .map(|stylesheet| {
+            let layers = layers.entry(stylesheet.origin).or_default();
+            stylesheet.style_rules(device, &properties::supports_declaration, layers)
+        })
+        .collect();
+    let layer_order: BTreeMap<Origin, Vec<usize>> = layers
+        .iter()
+        .map(|(&origin, layers)| (origin, layers.order()))
+        .collect();
+    stylesheets
+        .iter()
+        .zip(style_rules)
+        .map(|(stylesheet, rules)| {
+            let order = &layer_order[&stylesheet.origin];
+            let rules: Vec<_> = rules
+                .into_iter()
+                .map(|(layer, rule)| (order[layer], rule))
+                .collect();
+            ActiveStylesheet {
+                origin: stylesheet.origin,
+                hash: RuleHash::new(&rules),
+                rules,
+            }
+        })
+        .collect()
+}
+
+/// The style rules of a stylesheet that apply, each with the rank of its
+/// cascade layer.
+struct ActiveStylesheet<'a> {
+    origin: Origin,
+    rules: Vec<(usize, &'a Rule)>,
+    /// The selectors of the rules, indexed for matching.
+    hash: RuleHash,
+}
+
+/// An element, with the elements around it that selectors with combinators
+/// depend on.
+#[derive(Clone, Copy)]
+struct ElementContext<'a> {
+    elem: &'a ElementData,
+    /// The elements among its siblings that come before it, in order.
+    previous_siblings: &'a [&'a ElementData],
+    parent: Option<&'a ElementContext<'a>>,
+}
+
+/// The state of a traversal that `build_style_tree` passes down to each node.
+struct StyleContext<'a, 'c> {
+    stylesheets: &'c [ActiveStylesheet<'c>],
+    /// The style sharing cache of the current thread.
+    cache: &'c mut StyleSharingCache<'a>,
+    /// The number of threads to style the node's descendants on.
+    threads: usize,
+    /// The sizes of the node's subtrees, which are only given for several
+    /// threads.
+    sizes: Option<&'c parallel::Sizes>,
+    /// The styles the node had, when restyling.
+    previous: Option<Previous<'c>>,
+}
+
+/// Style `root` and its descendants, given the computed values of its parent
+/// (`None` for the root element) and the font size of the root element, as
+/// well as its parent element and the elements among its siblings before it.
+/// Elements reuse the styles in the cache of `context` when they can, and the
+/// descendants are styled on up to its number of threads, split by the sizes
+/// of their subtrees. Clean nodes keep their previous styles, if any, unless
+/// the style of their parent changed.
+fn build_style_tree<'a>(
+    root: &'a Node,
+    parent: Option<&Arc<PropertyMap>>,
+    root_font_size: f32,
+    parent_element: Option<&ElementContext>,
+    previous_siblings: &[&ElementData],
+    context: StyleContext<'a, '_>,
+) -> StyledNode<'a> {
+    let StyleContext {
+        stylesheets,
+        cache,
+        threads,
+        sizes,
+        previous,
+    } = context;
+    let elem_data = match root.node_type {
+        NodeType::Element(ref elem) => Some(elem),
+        NodeType::Text(_) => None,
+    };
+    let elem = elem_data.map(|elem| ElementContext {
+        elem,
+        previous_siblings,
+        parent: parent_element,
+    });
+    let reused = previous
+        .filter(|previous| !root.dirty && !previous.parent_changed)
+        .map(|previous| previous.styles.style.clone());
+    let mut style = match (reused, elem_data, &elem) {
+        (Some(style), ..) => style,
+        (None, Some(elem), Some(context)) => match parent {
+            Some(parent) => {
+                let revalidation = sharing::revalidation(context, stylesheets);
+                match cache.lookup(elem, parent, &revalidation) {
+                    Some(style) => style,
+                    None => {
+                        let style =
+                            element_style(context, stylesheets, Some(parent), root_font_size);
+                        cache.insert(elem, parent, revalidation, style.clone());
+                        style
+                    }
+                }
+            }
+            None => element_style(context, stylesheets, None, root_font_size),
         },
-        children: root
-            .children
+        (None, ..) => {
+            let parent = parent.expect("Text nodes have a parent.");
+            ElementStyle {
+                values: Arc::new(properties::inherit(parent, root_font_size)),
+                marker: None,
+                before: None,
+                after: None,
+            }
+        }
+    };
+    // Whether the children inherit other values than before.
+    let changed = match previous {
+        Some(previous) if previous.styles.same_values(&style) => {
+            style.values = previous.styles.style.values.clone();
+            false
+        }
+        _ => true,
+    };
+    let computed_values = style.values;
+
+    // `rem` lengths are relative to the font size of the root element.
+    let root_font_size = match (parent, computed_values.get("font-size")) {
+        (None, Some(&Value::Length(px, Unit::Px))) => px,
+        _ => root_font_size,
+    };
+
+    let pseudo_element = |pseudo_element, values: Option<Arc<PropertyMap>>| {
+        Some(Box::new(StyledNode::pseudo_element(
+            root,
+            pseudo_element,
+            values?,
+        )))
+    };
+    let marker = pseudo_element(PseudoElement::Marker, style.marker);
+    let before = pseudo_element(PseudoElement::Before, style.before);
+    let after = pseudo_element(PseudoElement::After, style.after);
+
+    let child_elements: Vec<&ElementData> = root
+        .children
+        .iter()
+        .filter_map(|child| match child.node_type {
+            NodeType::Element(ref elem) => Some(elem),
+            NodeType::Text(_) => None,
+        })
+        .collect();
+    let mut element_index = 0;
+    // `rem` lengths depend on the root element, so everything is restyled
+    // when its style changes.
+    let previous_children = match (parent, changed) {
+        (None, true) => &[][..],
+        _ => previous.map_or(&[][..], |previous| &previous.styles.children),
+    };
+    let children: Vec<(&Node, &[&ElementData], Option<Previous>, usize)> = root
+        .children
+        .iter()
+        .enumerate()
+        .map(|(i, child)| {
+            let previous_siblings = &child_elements[..element_index];
+            if let NodeType::Element(_) = child.node_type {
+                element_index += 1;
+            }
+            let previous = previous_children.get(i).map(|styles| Previous {
+                styles,
+                parent_changed: changed,
+            });
+            (child, previous_siblings, previous, i)
+        })
+        .collect();
+    let child_sizes = |i: usize| sizes.map(|sizes| &sizes.children[i]);
+    let child_nodes = |i: usize| child_sizes(i).map_or(0, |sizes| sizes.nodes);
+    let style_children = |children: &[(&'a Node, &[&ElementData], Option<Previous>, usize)],
+                          cache: &mut StyleSharingCache<'a>,
+                          threads: usize| {
+        children
             .iter()
-            .map(|child| style_tree(child, stylesheet))
-            .collect(),
+            .map(|&(child, previous_siblings, previous, i)| {
+                let context = StyleContext {
+                    stylesheets,
+                    cache,
+                    threads,
+                    sizes: child_sizes(i).filter(|_| threads > 1),
+                    previous,
+                };
+                build_style_tree(
+                    child,
+                    Some(&computed_values),
+                    root_font_size,
+                    elem.as_ref(),
+                    previous_siblings,
+                    context,
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let runs = match threads {
+        1 => vec![&children[..]],
+        _ => {
+            let sizes: Vec<usize> = children.iter().map(|&(.., i)| child_nodes(i)).collect();
+            parallel::partition(&children, &sizes, threads)
+        }
+    };
+    let children = match runs.len() {
+        // Siblings too small to split have descendants too small as well.
+        1 if children.len() > 1 => style_children(&children, cache, 1),
+        0 | 1 => style_children(&children, cache, threads),
+        _ => {
+            let run_sizes: Vec<usize> = runs
+                .iter()
+                .map(|run| run.iter().map(|&(.., i)| child_nodes(i)).sum())
+                .collect();
+            let runs: Vec<_> = runs
+                .into_iter()
+                .zip(parallel::share(threads, &run_sizes))
+                .collect();
+            parallel::map(&runs, |&(run, threads)| {
+                style_children(run, &mut cache.fork(), threads)
+            })
+            .into_iter()
+            .flatten()
+            .collect()
+        }
+    };
+    StyledNode {
+        node: root,
+        pseudo_element: None,
+        generated_text: None,
+        computed_values,
+        children,
+        marker,
+        before,
+        after,
+    }
+}
+
+/// Cascade and compute the values of an element and of its pseudo-elements,
+/// given the computed values of its parent and the font size of the root
+/// element. For the root element itself, its own font size is used for the
+/// pseudo-elements.
+fn element_style(
+    elem: &ElementContext,
+    stylesheets: &[ActiveStylesheet],
+    parent: Option<&PropertyMap>,
+    root_font_size: f32,
+) -> ElementStyle {
+    let cascaded = cascaded_values(elem, stylesheets, None);
+    let mut values = properties::compute_values(cascaded, parent, root_font_size);
+    display::blockify(&mut values, parent);
+    let root_font_size = match (parent, values.get("font-size")) {
+        (None, Some(&Value::Length(px, Unit::Px))) => px,
+        _ => root_font_size,
+    };
+    let pseudo_element = |pseudo_element| {
+        let cascaded = cascaded_values(elem, stylesheets, Some(pseudo_element));
+        let cascaded = match pseudo_element {
+            PseudoElement::Marker => marker_values(cascaded, &values)?,
+            // Only `::before` and `::after` with `content` generate boxes.
+            _ if !matches!(cascaded.get("content"), Some(Value::Content(_))) => return None,
+            _ => cascaded,
+        };
+        let mut computed = properties::compute_values(cascaded, Some(&values), root_font_size);
+        display::blockify(&mut computed, Some(&values));
+        Some(Arc::new(computed))
+    };
+    ElementStyle {
+        marker: pseudo_element(PseudoElement::Marker),
+        before: pseudo_element(PseudoElement::Before),
+        after: pseudo_element(PseudoElement::After),
+        values: Arc::new(values),
+    }
+}
+
+/// Return the cascaded values of the `::marker` of a list item, or `None` if
+/// it doesn't generate a box. Unless `content` is specified for it, the
+/// marker's content comes from the `list-style-type` of the list item, e.g.
+/// `counter(list-item, decimal) ". "`.
+///
+/// The text of the generated content is filled in later, by
+/// `counters::generate_content`.
+/// spec: https://www.w3.org/TR/css-lists-3/#content-property
+fn marker_values(mut values: PropertyMap, elem_values: &PropertyMap) -> Option<PropertyMap> {
+    if !Display::of(elem_values).is_list_item() {
+        return None;
+    }
+    if !matches!(values.get("content"), Some(Value::Content(_))) {
+        let style = match elem_values.get("list-style-type") {
+            Some(Value::Keyword(style)) => style.clone(),
+            _ => "disc".to_string(),
+        };
+        let suffix = match &*style {
+            "none" => return None,
+            "disc" | "circle" | "square" => " ",
+            _ => ". ",
+        };
+        let content = Value::Content(vec![
+            ContentItem::Counter("list-item".to_string(), style),
+            ContentItem::String(suffix.to_string()),
+        ]);
+        values.insert("content".to_string(), content);
+    }
+    Some(values)
+}
+
+/// Apply styles to a single element, or to one of its pseudo-elements,
+/// returning the cascaded values.
+/// spec: https://www.w3.org/TR/css-cascade-4/#cascade-sort
+fn cascaded_values(
+    elem: &ElementContext,
+    stylesheets: &[ActiveStylesheet],
+    pseudo_element: Option<PseudoElement>,
+) -> PropertyMap {
+    let inline = inline_declarations(elem, pseudo_element);
+    let declarations = applicable_declarations(elem, stylesheets, pseudo_element, &inline);
+    cascade(&declarations)
+        .into_iter()
+        .map(|(name, i)| (name.to_string(), declarations[i].2.value.clone()))
+        .collect()
+}
+
+/// Return the declarations of the `style` attribute of an element, which
+/// applies to the element itself only.
+fn inline_declarations(
+    elem: &ElementContext,
+    pseudo_element: Option<PseudoElement>,
+) -> Vec<Declaration> {
+    match (pseudo_element, elem.elem.attr("style")) {
+        (None, Some(style)) => css::parse_inline(style),
+        _ => vec![],
     }
 }
 
-/// Apply styles to a single element, returning the specified values.
-fn specified_values(elem: &ElementData, stylesheet: &Stylesheet) -> PropertyMap {
-    let mut values = HashMap::new();
-    let mut rules = matching_rules(elem, stylesheet);
+/// A declaration that applies to an element, with its precedence and origin.
+type ApplicableDeclaration<'d> = (Precedence, Origin, &'d Declaration);
 
-    // Go through the rules from lowest to highest specificity.
-    rules.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
-    for (_, rule) in rules {
-        for declaration in &rule.declarations {
-            // The more-specific rules can overwrite their values in the HashMap.
-            values.insert(declaration.name.clone(), declaration.value.clone());
+/// Return the declarations of the rules that match an element, or one of its
+/// pseudo-elements, and of its `inline` style, sorted from the lowest
+/// precedence to the highest.
+fn applicable_declarations<'d>(
+    elem: &ElementContext,
+    stylesheets: &[ActiveStylesheet<'d>],
+    pseudo_element: Option<PseudoElement>,
+    inline: &'d [Declaration],
+) -> Vec<ApplicableDeclaration<'d>> {
+    let mut declarations = vec![];
+    for stylesheet in stylesheets {
+        for (layer, (specificity, rule)) in matching_rules(elem, stylesheet, pseudo_element) {
+            for declaration in &rule.declarations {
+                let precedence = Precedence {
+                    level: cascade_level(stylesheet.origin, declaration.important),
+                    inline: false,
+                    // `!important` reverses the order of layers too.
+                    layer: match declaration.important {
+                        true => usize::MAX - layer,
+                        false => layer,
+                    },
+                    specificity,
+                };
+                declarations.push((precedence, stylesheet.origin, declaration));
+            }
         }
     }
-    values
+    for declaration in inline {
+        let precedence = Precedence {
+            level: cascade_level(Origin::Author, declaration.important),
+            inline: true,
+            layer: 0,
+            specificity: (0, 0, 0),
+        };
+        declarations.push((precedence, Origin::Author, declaration));
+    }
+
+    // The sort is stable, so declarations of equal precedence stay in source
+    // order.
+    declarations.sort_by_key(|&(precedence, ..)| precedence);
+    declarations
 }
 
-type MatchedRule<'a> = (Specificity, &'a Rule);
+/// Return the index of the declaration whose value wins the cascade for each
+/// property, among `declarations` sorted by precedence.
+fn cascade<'d>(declarations: &[ApplicableDeclaration<'d>]) -> HashMap<&'d str, usize> {
+    // Go through them from the highest precedence down, so the first value
+    // found for a property wins. `revert` discards the declarations of its
+    // origin and later origins, rolling back to earlier ones, and
+    // `revert-layer` those of its layer, rolling back to earlier layers.
+    // spec: https://www.w3.org/TR/css-cascade-5/#default
+    let mut winners = HashMap::new();
+    let mut reverted: HashMap<&str, Origin> = HashMap::new();
+    let mut reverted_layers: HashMap<&str, Precedence> = HashMap::new();
+    for (i, &(precedence, origin, declaration)) in declarations.iter().enumerate().rev() {
+        let name = &*declaration.name;
+        if winners.contains_key(name)
+            || reverted.get(name).is_some_and(|&o| origin >= o)
+            || reverted_layers
+                .get(name)
+                .is_some_and(|p| p.same_layer(&precedence))
+        {
+            continue;
+        }
+        match declaration.value {
+            Value::Keyword(ref k) if k == "revert" => {
+                reverted.insert(name, origin);
+            }
+            Value::Keyword(ref k) if k == "revert-layer" => {
+                reverted_layers.insert(name, precedence);
+            }
+            _ => {
+                winners.insert(name, i);
+            }
+        }
+    }
+    winners
+}
 
-/// Find all CSS rules that match the given element.
-fn matching_rules<'a>(elem: &ElementData, stylesheet: &'a Stylesheet) -> Vec<MatchedRule<'a>> {
-    stylesheet
-        .rules
-        .iter()
-        .filter_map(|rule| match_rule(elem, rule))
-        .collect()
+/// The precedence of a declaration in the cascade. Fields are compared in
+/// order, and declarations of equal precedence are ordered by their position
+/// in the source.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Precedence {
+    /// The origin and importance, see `cascade_level`.
+    level: u8,
+    /// Declarations of a `style` attribute win over those of any selector.
+    inline: bool,
+    /// The rank of the cascade layer, reversed for important declarations.
+    layer: usize,
+    specificity: Specificity,
 }
 
-/// If `rule` matches `elem`, return a `MatchedRule`. Otherwise return `None`.
-fn match_rule<'a>(elem: &ElementData, rule: &'a Rule) -> Option<MatchedRule<'a>> {
-    // Find the first (highest-specificity) matching selector.
-    rule.selectors
-        .iter()
-        .find(|selector| matches(elem, *selector))
-        .map(|selector| (selector.specificity(), rule))
+impl Precedence {
+    /// Return true if both declarations are in the same layer of the same
+    /// origin, with the same importance.
+    fn same_layer(&self, other: &Precedence) -> bool {
+        (self.level, self.inline, self.layer) == (other.level, other.inline, other.layer)
+    }
+}
+
+/// Rank the origin and importance of a declaration, from the lowest precedence
+/// to the highest. `!important` reverses the order of origins.
+/// spec: https://www.w3.org/TR/css-cascade-4/#cascade-origin
+fn cascade_level(origin: Origin, important: bool) -> u8 {
+    match (important, origin) {
+        (false, Origin::UserAgent) => 0,
+        (false, Origin::User) => 1,
+        (false, Origin::Author) => 2,
+        (true, Origin::Author) => 3,
+        (true, Origin::User) => 4,
+        (true, Origin::UserAgent) => 5,
+    }
 }
 
-fn matches(elem: &ElementData, selector: &Selector) -> bool {
+type MatchedRule<'a> = (Specificity, &'a Rule);
+
+/// Find all CSS rules that match the given element, or the given
+/// pseudo-element of it, each with the rank of its layer. Only the selectors
+/// which the rule hash finds for the element are tested.
+fn matching_rules<'a>(
+    elem: &ElementContext,
+    stylesheet: &ActiveStylesheet<'a>,
+    pseudo_element: Option<PseudoElement>,
+) -> Vec<(usize, MatchedRule<'a>)> {
+    let mut matched = vec![];
+    let mut matched_rule = None;
+    for (i, j) in stylesheet.hash.candidates(elem.elem) {
+        // The selectors of a rule are sorted by specificity, so the first
+        // one that matches counts.
+        if matched_rule == Some(i) {
+            continue;
+        }
+        let (layer, rule) = stylesheet.rules[i];
+        let selector = &rule.selectors[j];
+        if selector.pseudo_element() == pseudo_element && matches(elem, selector) {
+            matched.push((layer, (selector.specificity(), rule)));
+            matched_rule = Some(i);
+        }
+    }
+    matched
+}
+
+fn matches(elem: &ElementContext, selector: &Selector) -> bool {
     match selector {
-        Selector::Simple(ref simple_selector) => matches_simple_selector(elem, simple_selector),
+        Selector::Simple(ref simple_selector) => {
+            matches_simple_selector(elem.elem, simple_selector)
+        }
+        Selector::Complex(ref first, ref rest) => matches_complex_selector(elem, first, rest),
     }
 }
 
+/// Return true if `elem` matches the complex selector made of `first`
+/// followed by `rest`. It is matched from right to left: once the last simple
+/// selector matches, the rest must match an element related to `elem` by the
+/// last combinator.
+fn matches_complex_selector(
+    elem: &ElementContext,
+    first: &SimpleSelector,
+    rest: &[(Combinator, SimpleSelector)],
+) -> bool {
+    let Some(((combinator, subject), rest)) = rest.split_last() else {
+        return matches_simple_selector(elem.elem, first);
+    };
+    if !matches_simple_selector(elem.elem, subject) {
+        return false;
+    }
+    let sibling = |i: usize| ElementContext {
+        elem: elem.previous_siblings[i],
+        previous_siblings: &elem.previous_siblings[..i],
+        parent: elem.parent,
+    };
+    let candidates: Vec<ElementContext> = match combinator {
+        Combinator::Child => elem.parent.into_iter().copied().collect(),
+        Combinator::Descendant => std::iter::successors(elem.parent, |e| e.parent)
+            .copied()
+            .collect(),
+        Combinator::NextSibling => elem
+            .previous_siblings
+            .len()
+            .checked_sub(1)
+            .map(sibling)
+            .into_iter()
+            .collect(),
+        Combinator::SubsequentSibling => (0..elem.previous_siblings.len())
+            .rev()
+            .map(sibling)
+            .collect(),
+    };
+    candidates
+        .iter()
+        .any(|candidate| matches_complex_selector(candidate, first, rest))
+}
+
 fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> bool {
     // Check type selector.
     if selector
@@ -139,3 +771,317 @@ fn matches_simple_selector(elem: &ElementData, selector: &SimpleSelector) -> boo
     // We didn't find any non-matching selector components.
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{css, html};
+
+    #[test]
+    fn cascade() {
+        let dom = html::parse(
+            r#"<div id="x" class="a" style="width: 4px; height: 4px !important"></div>"#
+                .to_string(),
+        );
+        let mut user_agent = css::parse(
+            "div { display: block !important; color: #000001; width: 1px !important; }".to_string(),
+        );
+        user_agent.origin = Origin::UserAgent;
+        let mut user = css::parse("div { color: #000002; height: 2px !important; }".to_string());
+        user.origin = Origin::User;
+        let author = css::parse(
+            r#"
+            #x { display: inline; height: 3px !important; }
+            .a { margin-top: 1px !important; margin-left: 1px; }
+            div { margin-top: 2px; color: #000003; }
+            .a { margin-left: 2px; }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(
+            &dom,
+            &[user_agent, user, author],
+            &Device::new(800.0, 600.0),
+        );
+
+        let value = |name| match styled.value(name) {
+            Some(Value::Length(px, _)) => px,
+            Some(Value::Colorvalue(color)) => color.b as f32,
+            Some(Value::Keyword(s)) => (s == "block") as u8 as f32,
+            v => panic!("unexpected {:?}", v),
+        };
+        // Important user agent declarations win over everything.
+        assert_eq!(value("display"), 1.0);
+        assert_eq!(value("width"), 1.0);
+        // Important user declarations win over important author ones.
+        assert_eq!(value("height"), 2.0);
+        // Normal author declarations win over user ones.
+        assert_eq!(value("color"), 3.0);
+        // Importance wins over specificity.
+        assert_eq!(value("margin-top"), 1.0);
+        // Equal specificity falls back to source order.
+        assert_eq!(value("margin-left"), 2.0);
+    }
+
+    #[test]
+    fn revert() {
+        let dom = html::parse(r#"<p class="a"><b>x</b></p>"#.to_string());
+        let mut user = css::parse("p { margin-top: 2px; }".to_string());
+        user.origin = Origin::User;
+        let author = css::parse(
+            r#"
+            p { margin-top: 3px; display: revert; }
+            .a { margin-top: revert; }
+            b { display: revert; }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[user, author], &Device::new(800.0, 600.0));
+
+        // Author declarations roll back to the user's, then to the UA's.
+        assert_eq!(
+            styled.value("margin-top"),
+            Some(Value::Length(2.0, Unit::Px))
+        );
+        assert!(matches!(styled.display(), Display::BLOCK));
+        // With no UA declaration, `revert` acts like `unset`.
+        assert!(matches!(styled.children[0].display(), Display::INLINE));
+    }
+
+    #[test]
+    fn inheritance() {
+        let dom = html::parse(r#"<body><p><b>x</b></p></body>"#.to_string());
+        let stylesheet = css::parse(
+            r#"
+            body { color: #000001; font-size: 20px; border-left-width: 3px; }
+            p { font-size: 1.5em; margin-left: 1em; }
+            b { border-left-width: inherit; }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &Device::new(800.0, 600.0));
+
+        let p = &styled.children[0];
+        let b = &p.children[0];
+        assert!(matches!(b.value("color"), Some(Value::Colorvalue(c)) if c.b == 1));
+        assert_eq!(p.value("font-size"), Some(Value::Length(30.0, Unit::Px)));
+        assert_eq!(p.value("margin-left"), Some(Value::Length(30.0, Unit::Px)));
+        assert_eq!(b.value("font-size"), Some(Value::Length(30.0, Unit::Px)));
+        // Non-inherited properties take their initial value.
+        assert_eq!(b.value("margin-left"), Some(Value::Length(0.0, Unit::Px)));
+        assert_eq!(
+            p.value("border-left-width"),
+            Some(Value::Keyword("medium".into()))
+        );
+        assert_eq!(b.value("border-left-width"), p.value("border-left-width"));
+        // Text inherits from its element.
+        assert_eq!(b.children[0].value("font-size"), b.value("font-size"));
+    }
+
+    #[test]
+    fn custom_properties() {
+        let dom = html::parse(r#"<div class="a"><p class="b"></p></div>"#.to_string());
+        let stylesheet = css::parse(
+            r#"
+            div { --space: 4px; --color: #000001; --loop: var(--loop); }
+            .a { padding: var(--space) 2px; color: var(--color); }
+            .b { --space: 1em; margin: var(--space); width: var(--loop, 3px); }
+            .b { height: var(--none); font-size: 10px; }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &Device::new(800.0, 600.0));
+
+        let px = |node: &StyledNode, name| match node.value(name) {
+            Some(Value::Length(px, Unit::Px)) => px,
+            v => panic!("unexpected {:?}", v),
+        };
+        assert_eq!(px(&styled, "padding-top"), 4.0);
+        assert_eq!(px(&styled, "padding-left"), 2.0);
+        assert!(matches!(styled.value("color"), Some(Value::Colorvalue(c)) if c.b == 1));
+
+        // Custom properties are inherited, and can be overridden.
+        let b = &styled.children[0];
+        assert_eq!(b.value("--color"), Some(Value::Raw("#000001".to_string())));
+        assert_eq!(px(b, "margin-left"), 10.0);
+        // A cycle is invalid, so the fallback is used.
+        assert_eq!(b.value("--loop"), None);
+        assert_eq!(px(b, "width"), 3.0);
+        // An undefined variable without a fallback makes the property `unset`.
+        assert_eq!(b.value("height"), Some(Value::Keyword("auto".to_string())));
+    }
+
+    #[test]
+    fn inline_style() {
+        let dom = html::parse(r#"<p id="x" style="color: #000001"></p>"#.to_string());
+        let stylesheet = css::parse("#x { color: #000002; }".to_string());
+        let styled = style_tree(
+            &dom,
+            std::slice::from_ref(&stylesheet),
+            &Device::new(800.0, 600.0),
+        );
+        assert!(matches!(styled.value("color"), Some(Value::Colorvalue(c)) if c.b == 1));
+
+        // Invalid declarations in the attribute are skipped.
+        for style in [
+            ";",
+            "color",
+            "}{",
+            "color: #000001 !x; width: 2px",
+            "width: 2px; color: rgb(a b c)",
+        ] {
+            let dom = html::parse(format!(r#"<p id="x" style="{}"></p>"#, style));
+            let styled = style_tree(
+                &dom,
+                std::slice::from_ref(&stylesheet),
+                &Device::new(800.0, 600.0),
+            );
+            assert!(
+                matches!(styled.value("color"), Some(Value::Colorvalue(c)) if c.b == 2),
+                "{}",
+                style
+            );
+        }
+    }
+
+    #[test]
+    fn media_queries() {
+        let dom = html::parse("<p></p>".to_string());
+        let stylesheet = css::parse(
+            r#"
+            p { width: 1px; }
+            @media (width >= 600px) {
+                p { width: 2px; }
+                @media (min-width: 1024px) { p { width: 3px; } }
+            }
+            @media print { p { width: 4px; } }
+            "#
+            .to_string(),
+        );
+        let width = |viewport_width| {
+            let device = Device::new(viewport_width, 600.0);
+            match style_tree(&dom, std::slice::from_ref(&stylesheet), &device).value("width") {
+                Some(Value::Length(px, Unit::Px)) => px,
+                v => panic!("unexpected {:?}", v),
+            }
+        };
+        // Phone, tablet and desktop widths.
+        assert_eq!(width(375.0), 1.0);
+        assert_eq!(width(768.0), 2.0);
+        assert_eq!(width(1280.0), 3.0);
+    }
+
+    #[test]
+    fn combinators() {
+        let dom = html::parse(
+            r#"<div class="a"><p id="x"></p>text<b></b><i><b></b></i><p></p></div>"#.to_string(),
+        );
+        let stylesheet = css::parse(
+            r#"
+            .a b { margin-top: 1px; }
+            .a > b { margin-left: 1px; }
+            #x + b { margin-right: 1px; }
+            #x ~ p { margin-bottom: 1px; }
+            div > * > b { padding-top: 1px; }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &Device::new(800.0, 600.0));
+        let set = |node: &StyledNode, name| node.value(name) == Some(Value::Length(1.0, Unit::Px));
+
+        let [p, _, b, i, last_p] = &styled.children[..] else {
+            panic!("unexpected children");
+        };
+        let nested_b = &i.children[0];
+        assert!(set(b, "margin-top") && set(nested_b, "margin-top"));
+        assert!(set(b, "margin-left") && !set(nested_b, "margin-left"));
+        // Text between siblings doesn't count.
+        assert!(set(b, "margin-right") && !set(nested_b, "margin-right"));
+        assert!(set(last_p, "margin-bottom") && !set(p, "margin-bottom"));
+        assert!(set(nested_b, "padding-top") && !set(b, "padding-top"));
+    }
+
+    #[test]
+    fn layers() {
+        let dom = html::parse(r#"<p id="x"></p>"#.to_string());
+        let stylesheet = css::parse(
+            r#"
+            @layer base, theme;
+            p { margin-top: 1px; }
+            @layer theme {
+                p { margin-top: 2px; margin-left: 2px !important; }
+                p { margin-bottom: 2px; }
+                @layer dark { #x { margin-right: 3px; margin-bottom: 3px; } }
+            }
+            @layer base {
+                #x { margin-top: 3px; margin-left: 3px !important; margin-right: 1px; }
+                p { padding-top: 1px; }
+            }
+            @layer theme { p { padding-top: revert-layer; margin-bottom: revert-layer; } }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &Device::new(800.0, 600.0));
+        let px = |name| match styled.value(name) {
+            Some(Value::Length(px, Unit::Px)) => px,
+            v => panic!("unexpected {:?}", v),
+        };
+        // Unlayered rules win, whatever the specificity.
+        assert_eq!(px("margin-top"), 1.0);
+        // Important declarations of earlier layers win.
+        assert_eq!(px("margin-left"), 3.0);
+        // Rules directly in a layer win over those in its sublayers.
+        assert_eq!(px("margin-right"), 3.0);
+        // `revert-layer` rolls back to the layer below.
+        assert_eq!(px("margin-bottom"), 3.0);
+        assert_eq!(px("padding-top"), 1.0);
+    }
+
+    #[test]
+    fn supports() {
+        let dom = html::parse("<p></p>".to_string());
+        let stylesheet = css::parse(
+            r#"
+            @supports (margin: 0) and (not (grid-area: a)) { p { margin-top: 1px; } }
+            @supports (grid-area: a) { p { margin-top: 2px; } }
+            @supports (display: bogus) or (color: notacolor) { p { margin-top: 3px; } }
+            p { @supports (--x: y) { margin-left: 1px; } }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &Device::new(800.0, 600.0));
+        assert_eq!(
+            styled.value("margin-top"),
+            Some(Value::Length(1.0, Unit::Px))
+        );
+        assert_eq!(
+            styled.value("margin-left"),
+            Some(Value::Length(1.0, Unit::Px))
+        );
+    }
+
+    #[test]
+    fn pseudo_elements() {
+        let dom = html::parse(r#"<p title="hi"><b>x</b></p>"#.to_string());
+        let stylesheet = css::parse(
+            r#"
+            p::before { content: "[" attr(title) "]"; display: block; }
+            p:after { content: none; }
+            b::after { content: ""; }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &Device::new(800.0, 600.0));
+
+        let before = styled.before.as_ref().unwrap();
+        assert_eq!(before.pseudo_element, Some(PseudoElement::Before));
+        assert!(matches!(before.display(), Display::BLOCK));
+        assert_eq!(before.children[0].text(), Some("[hi]"));
+        assert!(styled.after.is_none());
+
+        let b = &styled.children[0];
+        assert!(b.before.is_none());
+        assert_eq!(b.after.as_ref().unwrap().children[0].text(), Some(""));
+        assert_eq!(b.children[0].text(), Some("x"));
+    }
+}
@@ -0,0 +1,187 @@
+//! The resolved style of an element, as `getComputedStyle` gives it: its
+//! computed values with every length that doesn't depend on layout in px,
+//! and, once laid out, the used values of its box.
+//! spec: https://www.w3.org/TR/cssom-1/#resolved-values
+
+use super::{PropertyMap, StyledNode};
+use crate::css::{Declaration, Device, Format, LengthContext, ToCss, Unit, Value};
+use std::collections::BTreeMap;
+
+/// The resolved values of an element, by property name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComputedStyle {
+    values: BTreeMap<String, Value>,
+}
+
+impl ComputedStyle {
+    /// Resolve the computed values of `node`, given the `device` whose
+    /// viewport units are resolved against.
+    pub(super) fn new(node: &StyledNode, device: &Device) -> ComputedStyle {
+        let values = &node.computed_values;
+        let mut lengths = LengthContext::new(device.width, device.height);
+        if let Some(&Value::Length(px, Unit::Px)) = values.get("font-size") {
+            lengths.font_size = px;
+        }
+        let values = values
+            .iter()
+            .map(|(name, value)| (name.clone(), resolve(name, value, values, &lengths)))
+            .collect();
+        ComputedStyle { values }
+    }
+
+    /// Return the resolved value of property `name`, if it exists.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    /// Iterate over the properties and their resolved values, by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values.iter().map(|(name, value)| (&**name, value))
+    }
+
+    /// Replace the value of property `name` with its used value in px.
+    pub fn set_used_value(&mut self, name: &str, px: f32) {
+        self.values
+            .insert(name.to_string(), Value::Length(px, Unit::Px));
+    }
+}
+
+impl ToCss for ComputedStyle {
+    /// Serialize the values as a declaration block, in the order of the
+    /// property names.
+    fn to_css(&self, format: Format) -> String {
+        let declarations = self.values.iter().map(|(name, value)| {
+            let declaration = Declaration {
+                name: name.clone(),
+                value: value.clone(),
+                important: false,
+            };
+            declaration.to_css(format) + ";"
+        });
+        let separator = match format {
+            Format::Minified => "",
+            Format::Pretty => "\n",
+        };
+        declarations.collect::<Vec<_>>().join(separator)
+    }
+}
+
+/// Return the resolved value of property `name` from its computed `value`,
+/// given the computed `values` of the element and its `lengths`.
+fn resolve(name: &str, value: &Value, values: &PropertyMap, lengths: &LengthContext) -> Value {
+    let resolve = |value| resolve(name, value, values, lengths);
+    match value {
+        // Borders without a style have no width.
+        // spec: https://www.w3.org/TR/css-backgrounds-3/#border-width
+        _ if name.starts_with("border-") && name.ends_with("-width") => {
+            let style = name.replace("-width", "-style");
+            let px = match (values.get(&style), value) {
+                (Some(Value::Keyword(s)), _) if s == "none" || s == "hidden" => 0.0,
+                (_, Value::Keyword(k)) if k == "thin" => 1.0,
+                (_, Value::Keyword(k)) if k == "thick" => 5.0,
+                (_, Value::Keyword(_)) => 3.0,
+                (_, value) => value.to_px(lengths, 0.0).max(0.0),
+            };
+            Value::Length(px, Unit::Px)
+        }
+        Value::Length(_, Unit::Percent | Unit::Px) => value.clone(),
+        Value::Length(..) => Value::Length(value.to_px(lengths, 0.0), Unit::Px),
+        Value::Calc(calc) if !calc.has_unit(&|unit| unit == Unit::Percent) => {
+            Value::Length(calc.to_px(lengths, 0.0), Unit::Px)
+        }
+        Value::Keyword(k) if k == "currentcolor" => values
+            .get("color")
+            .cloned()
+            .unwrap_or_else(|| value.clone()),
+        Value::List(list) => Value::List(list.iter().map(resolve).collect()),
+        value => value.clone(),
+    }
+}
+
+impl<'a> StyledNode<'a> {
+    /// The resolved style of this node, like `getComputedStyle`. Viewport
+    /// units are resolved against `device`.
+    pub fn computed_style(&self, device: &Device) -> ComputedStyle {
+        ComputedStyle::new(self, device)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::css::{self, Color, Device, Format, ToCss, Unit, Value};
+    use crate::html;
+    use crate::style::style_tree;
+
+    #[test]
+    fn resolved_values() {
+        let dom = html::parse(r#"<div><p class="a">x</p></div>"#.to_string());
+        let stylesheet = css::parse(
+            r#"
+            div { font-size: 20px; color: #ff0000; }
+            .a {
+                width: 10vw;
+                height: calc(1in + 4px);
+                margin: 1em 5%;
+                padding-left: calc(50% - 2vw);
+                border-top: thick solid;
+                border-left-width: 2px;
+                background-position: 1em 10%;
+                --gap: 3px;
+            }
+            "#
+            .to_string(),
+        );
+        let device = Device::new(800.0, 600.0);
+        let styled = style_tree(&dom, &[stylesheet], &device);
+        let p = styled.find(&dom.children[0]).unwrap();
+        let style = p.computed_style(&device);
+        let px = |px| Some(Value::Length(px, Unit::Px));
+        assert_eq!(style.get("width").cloned(), px(80.0));
+        assert_eq!(style.get("height").cloned(), px(100.0));
+        assert_eq!(style.get("margin-top").cloned(), px(20.0));
+        assert_eq!(
+            style.get("margin-left"),
+            Some(&Value::Length(5.0, Unit::Percent))
+        );
+        assert!(matches!(style.get("padding-left"), Some(Value::Calc(_))));
+        assert_eq!(style.get("border-top-width").cloned(), px(5.0));
+        // Without a style, a border has no width.
+        assert_eq!(style.get("border-left-width").cloned(), px(0.0));
+        let red = Value::Colorvalue(Color {
+            r: 255,
+            g: 0,
+            b: 0,
+            a: 255,
+        });
+        assert_eq!(style.get("border-top-color"), Some(&red));
+        assert_eq!(
+            style.get("background-position"),
+            Some(&Value::List(vec![
+                Value::Length(20.0, Unit::Px),
+                Value::Length(10.0, Unit::Percent)
+            ]))
+        );
+        assert!(style.get("--gap").is_some());
+        assert!(style.iter().map(|(name, _)| name).is_sorted());
+    }
+
+    #[test]
+    fn serialize() {
+        let dom = html::parse(r#"<p style="color: #ff0000; width: 1in"></p>"#.to_string());
+        let device = Device::new(800.0, 600.0);
+        let styled = style_tree(&dom, &[], &device);
+        let style = styled.computed_style(&device);
+        let pretty = style.to_css(Format::Pretty);
+        assert!(pretty.contains("\ncolor: rgb(255, 0, 0);\n"));
+        assert!(pretty.ends_with("\nwidth: 96px;"));
+        assert!(pretty.starts_with("background-attachment: scroll;\n"));
+        let minified = style.to_css(Format::Minified);
+        assert!(minified.contains(";color:#f00;") && minified.ends_with(";width:96px;"));
+        // The serialized values parse back to the same ones.
+        let declarations = css::parse_inline(&minified);
+        assert_eq!(declarations.len(), style.iter().count());
+        for declaration in declarations {
+            assert_eq!(style.get(&declaration.name), Some(&declaration.value));
+        }
+    }
+}
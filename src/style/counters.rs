@@ -0,0 +1,358 @@
+//! CSS counters and generated content.
+//!
+//! Counters are tracked by walking the style tree in document order, so this
+//! runs as a separate pass once every node has its specified values.
+//! spec: https://www.w3.org/TR/css-lists-3/#auto-numbering
+
+use super::{properties, StyledNode};
+use crate::css::{ContentItem, Unit, Value, DEFAULT_FONT_SIZE};
+use crate::dom::NodeType;
+use std::sync::Arc;
+
+/// Apply `counter-reset`, `counter-increment` and `counter-set` over the whole
+/// tree, and fill in the text of every pseudo-element's generated content.
+pub(super) fn generate_content(root: &mut StyledNode) {
+    // `rem` lengths are relative to the font size of the root element.
+    let root_font_size = match root.computed_values.get("font-size") {
+        Some(&Value::Length(px, Unit::Px)) => px,
+        _ => DEFAULT_FONT_SIZE,
+    };
+    let mut counters = Counters::default();
+    visit(root, &mut counters, 0, root_font_size);
+}
+
+fn visit(node: &mut StyledNode, counters: &mut Counters, depth: usize, root_font_size: f32) {
+    if node.text().is_some() {
+        return;
+    }
+    update_counters(node, counters, depth);
+    if node.pseudo_element.is_some() {
+        generate_text(node, counters, depth, root_font_size);
+    }
+
+    // Counters instantiated by the children are visible to their following
+    // siblings, but not outside of this node.
+    let scope_start = counters.stack.len();
+    let StyledNode {
+        marker,
+        before,
+        children,
+        after,
+        ..
+    } = node;
+    let children = marker
+        .as_deref_mut()
+        .into_iter()
+        .chain(before.as_deref_mut())
+        .chain(children.iter_mut())
+        .chain(after.as_deref_mut());
+    for child in children {
+        visit(child, counters, depth + 1, root_font_size);
+    }
+    counters.stack.truncate(scope_start);
+}
+
+/// Apply the counter properties of a node, in the order the spec requires:
+/// reset, increment, then set.
+fn update_counters(node: &StyledNode, counters: &mut Counters, depth: usize) {
+    if let Some(Value::CounterList(list)) = node.value("counter-reset") {
+        for (name, value) in list {
+            counters.reset(&name, value, depth);
+        }
+    }
+
+    let mut increments = match node.value("counter-increment") {
+        Some(Value::CounterList(list)) => list,
+        _ => vec![],
+    };
+    // List items implicitly increment the `list-item` counter.
+    if node.pseudo_element.is_none()
+        && node.display().is_list_item()
+        && !increments.iter().any(|(name, _)| name == "list-item")
+    {
+        increments.push(("list-item".to_string(), 1));
+    }
+    // Counters clamp at the limits of an integer rather than overflowing.
+    for (name, value) in increments {
+        let counter = counters.innermost(&name, depth);
+        *counter = counter.saturating_add(value);
+    }
+
+    if let Some(Value::CounterList(list)) = node.value("counter-set") {
+        for (name, value) in list {
+            *counters.innermost(&name, depth) = value;
+        }
+    }
+}
+
+/// Resolve the `content` of a pseudo-element into a text child.
+fn generate_text(
+    node: &mut StyledNode,
+    counters: &mut Counters,
+    depth: usize,
+    root_font_size: f32,
+) {
+    let Some(Value::Content(items)) = node.value("content") else {
+        return;
+    };
+    let elem = match node.node.node_type {
+        NodeType::Element(ref elem) => elem,
+        NodeType::Text(_) => unreachable!("Pseudo-elements originate from elements."),
+    };
+    let text = items
+        .iter()
+        .map(|item| match item {
+            ContentItem::String(s) => s.clone(),
+            ContentItem::Attr(name) => elem.attr(name).cloned().unwrap_or_default(),
+            ContentItem::Counter(name, style) => {
+                format_counter(*counters.innermost(name, depth), style)
+            }
+            ContentItem::Counters(name, separator, style) => {
+                counters.innermost(name, depth);
+                counters
+                    .values(name)
+                    .map(|value| format_counter(value, style))
+                    .collect::<Vec<_>>()
+                    .join(separator)
+            }
+        })
+        .collect();
+
+    // The generated text becomes an anonymous text child of the pseudo-element.
+    node.children = vec![StyledNode {
+        node: node.node,
+        pseudo_element: None,
+        generated_text: Some(text),
+        computed_values: Arc::new(properties::inherit(&node.computed_values, root_font_size)),
+        children: vec![],
+        marker: None,
+        before: None,
+        after: None,
+    }];
+}
+
+/// The counters in scope, outermost first.
+#[derive(Default)]
+struct Counters {
+    stack: Vec<Counter>,
+}
+
+struct Counter {
+    name: String,
+    value: i32,
+    /// Depth in the style tree of the node that instantiated this counter.
+    depth: usize,
+}
+
+impl Counters {
+    /// Instantiate a new counter on the node at `depth`. A counter of the same
+    /// name instantiated by a preceding sibling is replaced instead of nested.
+    fn reset(&mut self, name: &str, value: i32, depth: usize) {
+        match self.stack.iter_mut().rev().find(|c| c.name == name) {
+            Some(counter) if counter.depth == depth => counter.value = value,
+            _ => self.stack.push(Counter {
+                name: name.to_string(),
+                value,
+                depth,
+            }),
+        }
+    }
+
+    /// Return the innermost counter named `name`, instantiating it with 0 on
+    /// the node at `depth` if there is no such counter in scope.
+    fn innermost(&mut self, name: &str, depth: usize) -> &mut i32 {
+        if !self.stack.iter().any(|c| c.name == name) {
+            self.reset(name, 0, depth);
+        }
+        let counter = self.stack.iter_mut().rev().find(|c| c.name == name);
+        &mut counter.unwrap().value
+    }
+
+    /// Values of all the counters named `name` in scope, outermost first.
+    fn values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = i32> + 'a {
+        self.stack
+            .iter()
+            .filter(move |c| c.name == name)
+            .map(|c| c.value)
+    }
+}
+
+/// Represent a counter value in a counter style, e.g. `lower-roman`. Unknown
+/// styles, and values out of the range of a style, fall back to `decimal`.
+/// spec: https://www.w3.org/TR/css-counter-styles-3/#predefined-counters
+fn format_counter(value: i32, style: &str) -> String {
+    const LATIN: &str = "abcdefghijklmnopqrstuvwxyz";
+    const GREEK: &str = "αβγδεζηθικλμνξοπρστυφχψω";
+    match style {
+        "none" => String::new(),
+        "disc" => "•".to_string(),
+        "circle" => "◦".to_string(),
+        "square" => "▪".to_string(),
+        "decimal-leading-zero" if (0..10).contains(&value) => format!("0{}", value),
+        "decimal-leading-zero" if (-9..0).contains(&value) => format!("-0{}", -value),
+        "lower-alpha" | "lower-latin" if value >= 1 => alphabetic(value, LATIN),
+        "upper-alpha" | "upper-latin" if value >= 1 => alphabetic(value, LATIN).to_uppercase(),
+        "lower-greek" if value >= 1 => alphabetic(value, GREEK),
+        "lower-roman" if (1..4000).contains(&value) => roman(value).to_lowercase(),
+        "upper-roman" if (1..4000).contains(&value) => roman(value),
+        _ => value.to_string(),
+    }
+}
+
+/// spec: https://www.w3.org/TR/css-counter-styles-3/#alphabetic-system
+fn alphabetic(value: i32, symbols: &str) -> String {
+    let symbols: Vec<char> = symbols.chars().collect();
+    let mut value = value as usize;
+    let mut s = vec![];
+    while value > 0 {
+        value -= 1;
+        s.push(symbols[value % symbols.len()]);
+        value /= symbols.len();
+    }
+    s.iter().rev().collect()
+}
+
+fn roman(mut value: i32) -> String {
+    const NUMERALS: [(i32, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut s = String::new();
+    for (n, numeral) in NUMERALS {
+        while value >= n {
+            s.push_str(numeral);
+            value -= n;
+        }
+    }
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::style::style_tree;
+    use crate::{css, html};
+
+    /// Collect the generated text of every `::before` in the tree, in
+    /// document order.
+    fn before_texts(node: &StyledNode, texts: &mut Vec<String>) {
+        if let Some(before) = &node.before {
+            texts.push(before.children[0].text().unwrap().to_string());
+        }
+        for child in &node.children {
+            before_texts(child, texts);
+        }
+    }
+
+    #[test]
+    fn nested_heading_numbers() {
+        let dom = html::parse(
+            r#"<body><h1>a</h1><h2>b</h2><h2>c</h2><h1>d</h1><h2>e</h2></body>"#.to_string(),
+        );
+        let stylesheet = css::parse(
+            r#"
+            body { counter-reset: h1; }
+            h1 { counter-increment: h1; counter-reset: h2; }
+            h2 { counter-increment: h2; }
+            h1::before { content: counter(h1) " "; }
+            h2::before { content: counter(h1) "." counter(h2, lower-alpha) " "; }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &css::Device::new(800.0, 600.0));
+
+        let mut texts = vec![];
+        before_texts(&styled, &mut texts);
+        assert_eq!(texts, vec!["1 ", "1.a ", "1.b ", "2 ", "2.a "]);
+    }
+
+    #[test]
+    fn nested_counters() {
+        let dom = html::parse(
+            r#"<ol><li>a</li><li><ol><li>b</li><li>c</li></ol></li><li>d</li></ol>"#.to_string(),
+        );
+        let stylesheet = css::parse(
+            r#"
+            ol { counter-reset: item; }
+            li { counter-increment: item; }
+            li::before { content: counters(item, "."); }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &css::Device::new(800.0, 600.0));
+
+        let mut texts = vec![];
+        before_texts(&styled, &mut texts);
+        assert_eq!(texts, vec!["1", "2", "2.1", "2.2", "3"]);
+    }
+
+    #[test]
+    fn clamped_counters() {
+        let dom = html::parse(r#"<div><div></div></div>"#.to_string());
+        let stylesheet = css::parse(
+            r#"
+            div { counter-increment: a 2147483647; }
+            div::before { content: counter(a); }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &css::Device::new(800.0, 600.0));
+
+        let mut texts = vec![];
+        before_texts(&styled, &mut texts);
+        assert_eq!(texts, vec!["2147483647", "2147483647"]);
+    }
+
+    #[test]
+    fn list_item_markers() {
+        let dom = html::parse(
+            r#"<ul><li>a</li><li>b</li><li class="x">c</li><li class="n">d</li></ul>"#.to_string(),
+        );
+        let stylesheet = css::parse(
+            r#"
+            li { display: list-item; list-style-type: upper-roman; }
+            li.x::marker { content: "(" counter(list-item) ") "; }
+            li.n { list-style-type: none; }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &css::Device::new(800.0, 600.0));
+
+        let marker = |i: usize| {
+            let li: &StyledNode = &styled.children[i];
+            li.marker
+                .as_ref()
+                .map(|marker| marker.children[0].text().unwrap().to_string())
+        };
+        assert_eq!(marker(0).as_deref(), Some("I. "));
+        assert_eq!(marker(1).as_deref(), Some("II. "));
+        assert_eq!(marker(2).as_deref(), Some("(3) "));
+        assert_eq!(marker(3), None);
+        assert!(styled.children[0].display().is_list_item());
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(format_counter(7, "decimal"), "7");
+        assert_eq!(format_counter(-7, "decimal-leading-zero"), "-07");
+        assert_eq!(format_counter(28, "lower-alpha"), "ab");
+        assert_eq!(format_counter(2, "upper-latin"), "B");
+        assert_eq!(format_counter(0, "lower-alpha"), "0");
+        assert_eq!(format_counter(1994, "upper-roman"), "MCMXCIV");
+        assert_eq!(format_counter(4, "lower-roman"), "iv");
+        assert_eq!(format_counter(2, "lower-greek"), "β");
+        assert_eq!(format_counter(3, "disc"), "•");
+        assert_eq!(format_counter(3, "unknown-style"), "3");
+    }
+}
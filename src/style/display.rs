@@ -0,0 +1,383 @@
+//! The `display` property, which sets the kind of box an element generates,
+//! as an outer and an inner display type.
+//! spec: https://www.w3.org/TR/css-display-3/#the-display-properties
+
+use super::PropertyMap;
+use crate::css::Value;
+
+/// The computed value of `display`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Display {
+    /// A box, with the role it plays in the formatting context it is in
+    /// (`outside`) and the formatting context it establishes for its content
+    /// (`inside`). A list item also generates a `::marker`.
+    Box {
+        outside: DisplayOutside,
+        inside: DisplayInside,
+        list_item: bool,
+    },
+    /// A box that only has a meaning inside a table, e.g. `table-cell`.
+    Internal(DisplayInternal),
+    /// No box, but the boxes of its children and pseudo-elements take its
+    /// place.
+    Contents,
+    /// No box, nor any for its descendants.
+    None,
+}
+
+/// spec: https://www.w3.org/TR/css-display-3/#outer-role
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayOutside {
+    Block,
+    Inline,
+}
+
+/// spec: https://www.w3.org/TR/css-display-3/#inner-model
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayInside {
+    /// Lays out its content in flow layout: in a block formatting context if
+    /// it is block-level, or in the inline formatting context it is in.
+    Flow,
+    /// Establishes a new block formatting context.
+    FlowRoot,
+    Table,
+    Flex,
+    Grid,
+}
+
+/// spec: https://www.w3.org/TR/css-display-3/#layout-specific-display
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayInternal {
+    TableRowGroup,
+    TableHeaderGroup,
+    TableFooterGroup,
+    TableRow,
+    TableCell,
+    TableColumnGroup,
+    TableColumn,
+    TableCaption,
+}
+
+impl Display {
+    /// `display: block`.
+    pub const BLOCK: Display = Display::Box {
+        outside: DisplayOutside::Block,
+        inside: DisplayInside::Flow,
+        list_item: false,
+    };
+    /// `display: inline`, the initial value.
+    pub const INLINE: Display = Display::Box {
+        outside: DisplayOutside::Inline,
+        inside: DisplayInside::Flow,
+        list_item: false,
+    };
+
+    /// Return the display of the computed `values` of an element. Invalid
+    /// values act as the initial value.
+    pub(super) fn of(values: &PropertyMap) -> Display {
+        values
+            .get("display")
+            .and_then(Display::from_value)
+            .unwrap_or(Display::INLINE)
+    }
+
+    /// Parse a value of `display`: a single keyword, including the legacy
+    /// ones like `inline-block`, or the outer and inner display types and
+    /// `list-item` in any order, e.g. `inline flow-root`.
+    /// spec: https://www.w3.org/TR/css-display-3/#the-display-properties
+    pub(super) fn from_value(value: &Value) -> Option<Display> {
+        use DisplayInside::*;
+        use DisplayOutside::*;
+        let keywords: Vec<&str> = match value {
+            Value::Keyword(keyword) => vec![keyword],
+            Value::List(values) => values
+                .iter()
+                .map(|value| match value {
+                    Value::Keyword(keyword) => Some(&**keyword),
+                    _ => None,
+                })
+                .collect::<Option<_>>()?,
+            _ => return None,
+        };
+        let single = |outside, inside| {
+            Some(Display::Box {
+                outside,
+                inside,
+                list_item: false,
+            })
+        };
+        let internal = |internal| Some(Display::Internal(internal));
+        if let [keyword] = keywords[..] {
+            let display = match keyword {
+                "none" => Some(Display::None),
+                "contents" => Some(Display::Contents),
+                "inline-block" => single(Inline, FlowRoot),
+                "inline-table" => single(Inline, Table),
+                "inline-flex" => single(Inline, Flex),
+                "inline-grid" => single(Inline, Grid),
+                "table-row-group" => internal(DisplayInternal::TableRowGroup),
+                "table-header-group" => internal(DisplayInternal::TableHeaderGroup),
+                "table-footer-group" => internal(DisplayInternal::TableFooterGroup),
+                "table-row" => internal(DisplayInternal::TableRow),
+                "table-cell" => internal(DisplayInternal::TableCell),
+                "table-column-group" => internal(DisplayInternal::TableColumnGroup),
+                "table-column" => internal(DisplayInternal::TableColumn),
+                "table-caption" => internal(DisplayInternal::TableCaption),
+                _ => None,
+            };
+            if display.is_some() {
+                return display;
+            }
+        }
+
+        let (mut outside, mut inside, mut list_item) = (None, None, false);
+        for keyword in keywords {
+            match keyword {
+                "block" if outside.is_none() => outside = Some(Block),
+                "inline" if outside.is_none() => outside = Some(Inline),
+                "flow" if inside.is_none() => inside = Some(Flow),
+                "flow-root" if inside.is_none() => inside = Some(FlowRoot),
+                "table" if inside.is_none() => inside = Some(Table),
+                "flex" if inside.is_none() => inside = Some(Flex),
+                "grid" if inside.is_none() => inside = Some(Grid),
+                "list-item" if !list_item => list_item = true,
+                _ => return None,
+            }
+        }
+        // Only flow layout has list items.
+        if list_item && !matches!(inside, None | Some(Flow | FlowRoot)) {
+            return None;
+        }
+        // The outer display type defaults to block, and the inner one to
+        // flow.
+        Some(Display::Box {
+            outside: outside.unwrap_or(Block),
+            inside: inside.unwrap_or(Flow),
+            list_item,
+        })
+    }
+
+    /// Return the shortest value of `display` for this display.
+    fn to_value(self) -> Value {
+        use DisplayInside::*;
+        use DisplayOutside::*;
+        let keyword = |keyword: &str| Value::Keyword(keyword.to_string());
+        let (outside, inside, list_item) = match self {
+            Display::None => return keyword("none"),
+            Display::Contents => return keyword("contents"),
+            Display::Internal(internal) => {
+                return keyword(match internal {
+                    DisplayInternal::TableRowGroup => "table-row-group",
+                    DisplayInternal::TableHeaderGroup => "table-header-group",
+                    DisplayInternal::TableFooterGroup => "table-footer-group",
+                    DisplayInternal::TableRow => "table-row",
+                    DisplayInternal::TableCell => "table-cell",
+                    DisplayInternal::TableColumnGroup => "table-column-group",
+                    DisplayInternal::TableColumn => "table-column",
+                    DisplayInternal::TableCaption => "table-caption",
+                })
+            }
+            Display::Box {
+                outside,
+                inside,
+                list_item,
+            } => (outside, inside, list_item),
+        };
+        let inside_name = match inside {
+            Flow => "flow",
+            FlowRoot => "flow-root",
+            Table => "table",
+            Flex => "flex",
+            Grid => "grid",
+        };
+        if list_item {
+            let mut keywords = vec![];
+            if outside == Inline {
+                keywords.push(keyword("inline"));
+            }
+            if inside != Flow {
+                keywords.push(keyword(inside_name));
+            }
+            keywords.push(keyword("list-item"));
+            return match keywords.len() {
+                1 => keywords.remove(0),
+                _ => Value::List(keywords),
+            };
+        }
+        match (outside, inside) {
+            (Block, Flow) => keyword("block"),
+            (Inline, Flow) => keyword("inline"),
+            (Block, _) => keyword(inside_name),
+            (Inline, FlowRoot) => keyword("inline-block"),
+            (Inline, _) => keyword(&format!("inline-{}", inside_name)),
+        }
+    }
+
+    /// Return true if the element generates a block-level box. Boxes
+    /// internal to tables are laid out as blocks for now.
+    pub fn is_block_level(self) -> bool {
+        matches!(
+            self,
+            Display::Box {
+                outside: DisplayOutside::Block,
+                ..
+            } | Display::Internal(_)
+        )
+    }
+
+    /// Return true if the element generates a `::marker`.
+    pub fn is_list_item(self) -> bool {
+        matches!(
+            self,
+            Display::Box {
+                list_item: true,
+                ..
+            }
+        )
+    }
+
+    /// Return the block-level version of this display, for boxes which must
+    /// be block-level.
+    /// spec: https://www.w3.org/TR/css-display-3/#blockify
+    fn blockify(self) -> Display {
+        match self {
+            Display::Box {
+                inside, list_item, ..
+            } => Display::Box {
+                outside: DisplayOutside::Block,
+                inside,
+                list_item,
+            },
+            // Internal table boxes become block containers.
+            Display::Internal(_) => Display::BLOCK,
+            display => display,
+        }
+    }
+}
+
+/// Blockify the computed `display` in `values` if it must be block-level: for
+/// the root element, whose `parent` is `None`, and for the children of flex
+/// and grid containers.
+/// spec: https://www.w3.org/TR/css-display-3/#transformations
+pub(super) fn blockify(values: &mut PropertyMap, parent: Option<&PropertyMap>) {
+    let display = Display::of(values);
+    let blockified = match parent.map(Display::of) {
+        // The root element always generates a box.
+        None if display == Display::Contents => Display::BLOCK,
+        None
+        | Some(Display::Box {
+            inside: DisplayInside::Flex | DisplayInside::Grid,
+            ..
+        }) => display.blockify(),
+        Some(_) => display,
+    };
+    if blockified != display {
+        values.insert("display".to_string(), blockified.to_value());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{self, Device};
+    use crate::html;
+    use crate::style::style_tree;
+
+    fn parse(css: &str) -> Option<Display> {
+        Display::from_value(&css::parse_inline(&format!("display: {}", css))[0].value)
+    }
+
+    fn display(outside: DisplayOutside, inside: DisplayInside, list_item: bool) -> Display {
+        Display::Box {
+            outside,
+            inside,
+            list_item,
+        }
+    }
+
+    #[test]
+    fn parse_values() {
+        use DisplayInside::*;
+        use DisplayOutside::*;
+        assert_eq!(parse("block"), Some(Display::BLOCK));
+        assert_eq!(parse("inline flow"), Some(Display::INLINE));
+        assert_eq!(parse("inline-block"), parse("flow-root inline"));
+        assert_eq!(
+            parse("inline-block"),
+            Some(display(Inline, FlowRoot, false))
+        );
+        assert_eq!(parse("flex"), Some(display(Block, Flex, false)));
+        assert_eq!(parse("inline-grid"), Some(display(Inline, Grid, false)));
+        assert_eq!(parse("list-item"), Some(display(Block, Flow, true)));
+        assert_eq!(
+            parse("inline flow-root list-item"),
+            Some(display(Inline, FlowRoot, true))
+        );
+        assert_eq!(parse("contents"), Some(Display::Contents));
+        assert_eq!(
+            parse("table-cell"),
+            Some(Display::Internal(DisplayInternal::TableCell))
+        );
+        assert_eq!(parse("flex list-item"), None);
+        assert_eq!(parse("block inline"), None);
+        assert_eq!(parse("contents block"), None);
+        assert_eq!(parse("1px"), None);
+    }
+
+    #[test]
+    fn serialize() {
+        for css in [
+            "none",
+            "block",
+            "inline",
+            "flow-root",
+            "inline-block",
+            "inline-flex",
+            "grid",
+            "list-item",
+            "inline flow-root list-item",
+            "table-row",
+        ] {
+            let display = parse(css).unwrap();
+            assert_eq!(Display::from_value(&display.to_value()), Some(display));
+        }
+        assert_eq!(
+            parse("block flow list-item").unwrap().to_value(),
+            Value::Keyword("list-item".to_string())
+        );
+    }
+
+    #[test]
+    fn blockification() {
+        use DisplayInside::*;
+        use DisplayOutside::*;
+        let dom = html::parse(
+            r#"<html><div class="flex"><span>a</span><i></i><b></b></div><span></span></html>"#
+                .to_string(),
+        );
+        let stylesheet = css::parse(
+            r#"
+            html { display: contents; }
+            .flex { display: inline-flex; }
+            .flex::before { content: "x"; }
+            i { display: inline-block; }
+            b { display: table-cell; }
+            "#
+            .to_string(),
+        );
+        let styled = style_tree(&dom, &[stylesheet], &Device::new(800.0, 600.0));
+        // The root element always generates a block-level box.
+        assert_eq!(styled.display(), Display::BLOCK);
+        let flex = &styled.children[0];
+        assert_eq!(flex.display(), display(Inline, Flex, false));
+        // The children of a flex container are block-level, and so is its
+        // `::before`.
+        assert_eq!(flex.children[0].display(), Display::BLOCK);
+        assert_eq!(flex.children[1].display(), display(Block, FlowRoot, false));
+        assert_eq!(flex.children[2].display(), Display::BLOCK);
+        assert_eq!(flex.before.as_ref().unwrap().display(), Display::BLOCK);
+        // Text isn't.
+        assert_eq!(flex.children[0].children[0].display(), Display::INLINE);
+        assert_eq!(styled.children[1].display(), Display::INLINE);
+    }
+}
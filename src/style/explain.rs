@@ -0,0 +1,309 @@
+//! Explaining the cascade: which rules match an element, and which of their
+//! declarations win, for debugging styles.
+
+use super::{
+    active_stylesheets, applicable_declarations, cascade, cascade_level, inline_declarations,
+    matches, matching_rules, ActiveStylesheet, ElementContext, Precedence,
+};
+use crate::css::{
+    Declaration, Device, Format, Origin, PseudoElement, Selector, SourceLocation, Specificity,
+    Stylesheet, ToCss,
+};
+use crate::dom::{ElementData, Node, NodeType};
+use std::collections::HashSet;
+use std::fmt;
+
+/// A rule that matches an element, or the `style` attribute of the element.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchingRule {
+    /// The first selector of the rule that matches, or `None` for the `style`
+    /// attribute.
+    pub selector: Option<Selector>,
+    pub specificity: Specificity,
+    pub origin: Origin,
+    /// The index of the stylesheet the rule is in, among those given to
+    /// `explain_cascade`, or `None` for the user agent stylesheet and the
+    /// `style` attribute.
+    pub stylesheet: Option<usize>,
+    /// Where the rule is in the source of its stylesheet, or `None` for the
+    /// `style` attribute.
+    pub location: Option<SourceLocation>,
+    /// The declarations, each with whether its value won the cascade. The
+    /// others are overridden by declarations of higher precedence, or
+    /// reverted.
+    pub declarations: Vec<(Declaration, bool)>,
+}
+
+/// Return the rules that match `element`, or its `pseudo_element`, and its
+/// `style` attribute, from the highest precedence to the lowest, like the
+/// styles panel of developer tools. Returns `None` if `element` isn't an
+/// element of the tree at `root`.
+pub fn explain_cascade(
+    root: &Node,
+    element: &Node,
+    stylesheets: &[Stylesheet],
+    device: &Device,
+    pseudo_element: Option<PseudoElement>,
+) -> Option<Vec<MatchingRule>> {
+    let stylesheets = active_stylesheets(stylesheets, device);
+    find_element(root, None, &[], &mut |node, elem| {
+        std::ptr::eq(node, element).then(|| explain(elem, &stylesheets, pseudo_element))
+    })
+}
+
+/// Return the first element of the tree at `root` that matches one of
+/// `selectors`, in tree order. Pseudo-elements in the selectors are ignored.
+pub fn query_selector<'a>(root: &'a Node, selectors: &[Selector]) -> Option<&'a Node> {
+    find_element(root, None, &[], &mut |node, elem| {
+        selectors
+            .iter()
+            .any(|selector| matches(elem, selector))
+            .then_some(node)
+    })
+}
+
+/// Call `f` with each element of the tree at `node`, in tree order, given its
+/// parent element and the elements among its siblings before it, until it
+/// returns a result.
+fn find_element<'a, R>(
+    node: &'a Node,
+    parent: Option<&ElementContext>,
+    previous_siblings: &[&ElementData],
+    f: &mut dyn FnMut(&'a Node, &ElementContext) -> Option<R>,
+) -> Option<R> {
+    let NodeType::Element(ref elem) = node.node_type else {
+        return None;
+    };
+    let context = ElementContext {
+        elem,
+        previous_siblings,
+        parent,
+    };
+    if let Some(result) = f(node, &context) {
+        return Some(result);
+    }
+    let mut siblings = vec![];
+    for child in &node.children {
+        if let NodeType::Element(ref child_elem) = child.node_type {
+            if let Some(result) = find_element(child, Some(&context), &siblings, f) {
+                return Some(result);
+            }
+            siblings.push(child_elem);
+        }
+    }
+    None
+}
+
+fn explain(
+    elem: &ElementContext,
+    stylesheets: &[ActiveStylesheet],
+    pseudo_element: Option<PseudoElement>,
+) -> Vec<MatchingRule> {
+    let inline = inline_declarations(elem, pseudo_element);
+    let declarations = applicable_declarations(elem, stylesheets, pseudo_element, &inline);
+    let winners: HashSet<*const Declaration> = cascade(&declarations)
+        .into_values()
+        .map(|i| declarations[i].2 as *const Declaration)
+        .collect();
+    let explain_declarations = |declarations: &[Declaration]| {
+        declarations
+            .iter()
+            .map(|d| (d.clone(), winners.contains(&(d as *const Declaration))))
+            .collect()
+    };
+
+    // Rules are ordered by the precedence of their normal declarations, then
+    // in source order.
+    let mut rules = vec![];
+    for (i, stylesheet) in stylesheets.iter().enumerate() {
+        for (layer, (specificity, rule)) in matching_rules(elem, stylesheet, pseudo_element) {
+            let precedence = Precedence {
+                level: cascade_level(stylesheet.origin, false),
+                inline: false,
+                layer,
+                specificity,
+            };
+            let selector = rule
+                .selectors
+                .iter()
+                .find(|s| s.pseudo_element() == pseudo_element && matches(elem, s));
+            let rule = MatchingRule {
+                selector: selector.cloned(),
+                specificity,
+                origin: stylesheet.origin,
+                // The user agent stylesheet comes before the given ones.
+                stylesheet: i.checked_sub(1),
+                location: Some(rule.location),
+                declarations: explain_declarations(&rule.declarations),
+            };
+            rules.push((precedence, rule));
+        }
+    }
+    if !inline.is_empty() {
+        let precedence = Precedence {
+            level: cascade_level(Origin::Author, false),
+            inline: true,
+            layer: 0,
+            specificity: (0, 0, 0),
+        };
+        let rule = MatchingRule {
+            selector: None,
+            specificity: (0, 0, 0),
+            origin: Origin::Author,
+            stylesheet: None,
+            location: None,
+            declarations: explain_declarations(&inline),
+        };
+        rules.push((precedence, rule));
+    }
+    rules.sort_by_key(|&(precedence, _)| precedence);
+    rules.into_iter().rev().map(|(_, rule)| rule).collect()
+}
+
+impl fmt::Display for MatchingRule {
+    /// Write the rule as CSS, with where it comes from in a comment, and a
+    /// comment after each declaration that doesn't win. Stylesheets other
+    /// than the user agent one are told apart by their index.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let origin = match self.origin {
+            Origin::UserAgent => "user agent",
+            Origin::User => "user",
+            Origin::Author => "author",
+        };
+        let index = self.stylesheet.map_or(String::new(), |i| format!(" {}", i));
+        match (&self.selector, self.location) {
+            (Some(selector), Some(location)) => {
+                let (a, b, c) = self.specificity;
+                writeln!(
+                    f,
+                    "/* {} stylesheet{}, line {}, column {}, specificity ({}, {}, {}) */",
+                    origin, index, location.line, location.column, a, b, c
+                )?;
+                writeln!(f, "{} {{", selector.to_css(Format::Pretty))?;
+            }
+            _ => writeln!(f, "/* style attribute */\nelement.style {{")?,
+        }
+        for (declaration, won) in &self.declarations {
+            let overridden = if *won { "" } else { " /* overridden */" };
+            writeln!(f, "  {};{}", declaration.to_css(Format::Pretty), overridden)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{self, Unit, Value};
+    use crate::html;
+
+    #[test]
+    fn explain() {
+        let dom = html::parse(
+            r#"<div id="main"><p class="a" style="margin-left: 2px">x</p></div>"#.to_string(),
+        );
+        let stylesheet = css::parse(
+            r#"
+            p { color: #0000ff; margin-left: 1px; margin-top: 5px; }
+            #main .a, .a { color: #ff0000; }
+            @layer base {
+              p { color: #00ff00 !important; }
+            }
+            .a { margin-top: revert; }
+            "#
+            .to_string(),
+        );
+        let device = Device::new(800.0, 600.0);
+        let stylesheets = [stylesheet];
+        let p = query_selector(&dom, &css::parse_selectors("div > .a")).unwrap();
+        let rules = explain_cascade(&dom, p, &stylesheets, &device, None).unwrap();
+
+        // The style attribute, then the author rules by specificity and
+        // layer, then the user agent rules.
+        let selectors: Vec<String> = rules
+            .iter()
+            .map(|rule| match &rule.selector {
+                Some(selector) => selector.to_css(Format::Minified),
+                None => "style".to_string(),
+            })
+            .collect();
+        assert_eq!(selectors[..5], ["style", "#main .a", ".a", "p", "p"]);
+        assert_eq!(rules.last().unwrap().origin, Origin::UserAgent);
+        assert_eq!(rules[3].location.unwrap().line, 2);
+        assert_eq!(rules[3].stylesheet, Some(0));
+        assert_eq!(rules.last().unwrap().stylesheet, None);
+        assert_eq!(rules[1].specificity, (1, 1, 0));
+
+        let won = |rule: &MatchingRule, name: &str| {
+            let declaration = rule.declarations.iter().find(|(d, _)| d.name == name);
+            declaration.unwrap().1
+        };
+        // The important color in a layer wins over the more specific ones.
+        assert!(!won(&rules[1], "color"));
+        assert!(won(&rules[4], "color"));
+        assert!(!won(&rules[3], "margin-left"));
+        assert!(won(&rules[0], "margin-left"));
+        // `revert` rolls back to the value of the user agent stylesheet.
+        assert!(!won(&rules[2], "margin-top"));
+        assert!(!won(&rules[3], "margin-top"));
+        let margin = rules
+            .iter()
+            .flat_map(|rule| &rule.declarations)
+            .find(|(d, won)| d.name == "margin-top" && *won);
+        assert_eq!(margin.unwrap().0.value, Value::Length(1.0, Unit::Em));
+
+        assert!(explain_cascade(
+            &dom,
+            &dom.children[0].children[0],
+            &stylesheets,
+            &device,
+            None
+        )
+        .is_none());
+        assert!(query_selector(&dom, &css::parse_selectors("p.b")).is_none());
+    }
+
+    #[test]
+    fn display() {
+        let dom = html::parse(r#"<p class="a" style="width: 1px"></p>"#.to_string());
+        let stylesheets = [
+            css::parse("p { color: #0000ff }".to_string()),
+            css::parse(
+                ".a::before { content: \"x\"; color: #ff0000; }\n.a:before { color: #00ff00 }"
+                    .to_string(),
+            ),
+        ];
+        let device = Device::new(800.0, 600.0);
+        let rules = explain_cascade(
+            &dom,
+            &dom,
+            &stylesheets,
+            &device,
+            Some(PseudoElement::Before),
+        )
+        .unwrap();
+        let text: Vec<String> = rules.iter().map(|rule| rule.to_string()).collect();
+        assert_eq!(
+            text,
+            [
+                "/* author stylesheet 1, line 2, column 1, specificity (0, 1, 1) */\n\
+                 .a::before {\n  color: rgb(0, 255, 0);\n}",
+                "/* author stylesheet 1, line 1, column 1, specificity (0, 1, 1) */\n\
+                 .a::before {\n  content: \"x\";\n  color: rgb(255, 0, 0); /* overridden */\n}",
+            ]
+        );
+        let rules = explain_cascade(&dom, &dom, &stylesheets, &device, None).unwrap();
+        assert_eq!(
+            rules[0].to_string(),
+            "/* style attribute */\nelement.style {\n  width: 1px;\n}"
+        );
+        assert_eq!(
+            rules[1].to_string(),
+            "/* author stylesheet 0, line 1, column 1, specificity (0, 0, 1) */\n\
+             p {\n  color: rgb(0, 0, 255);\n}"
+        );
+        assert!(rules[2]
+            .to_string()
+            .starts_with("/* user agent stylesheet, line "));
+    }
+}
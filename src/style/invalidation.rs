@@ -0,0 +1,248 @@
+//! Finding the nodes to restyle after the DOM or the stylesheets change, from
+//! the ids, classes and tag names in selectors, like the invalidation sets of
+//! Blink.
+//!
+//! A change to an id or class of an element may change the style of:
+//! - the element itself, if a selector's subject has it, e.g. `.a` or
+//!   `p > .a`;
+//! - its descendants, if it comes before a descendant or child combinator,
+//!   e.g. `.a p`;
+//! - its later siblings and their descendants, if it comes before a sibling
+//!   combinator, e.g. `.a + p`.
+//!
+//! Likewise, a rule that changed in the stylesheets may change the style of
+//! the elements with the ids, classes and tag names of its selectors.
+
+use crate::css::{Combinator, Rule, Selector, SimpleSelector};
+use crate::dom::{Changes, ElementData, Node, NodeType};
+use std::collections::HashMap;
+
+/// The nodes to restyle around an element.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(super) struct Invalidation {
+    pub(super) element: bool,
+    pub(super) descendants: bool,
+    /// The later siblings of the element, and their descendants.
+    pub(super) siblings: bool,
+}
+
+impl Invalidation {
+    fn add(&mut self, other: Invalidation) {
+        self.element |= other.element;
+        self.descendants |= other.descendants;
+        self.siblings |= other.siblings;
+    }
+}
+
+/// What to restyle when an element has an id, class or tag name, or when it
+/// gets or loses it.
+#[derive(Debug, Default)]
+pub(super) struct InvalidationMap {
+    ids: HashMap<String, Invalidation>,
+    classes: HashMap<String, Invalidation>,
+    tags: HashMap<String, Invalidation>,
+    /// For simple selectors with no id, class nor tag name, e.g. `*`, which
+    /// every element has.
+    universal: Invalidation,
+}
+
+impl InvalidationMap {
+    /// Map the ids, classes and tag names in the selectors of `rules`.
+    pub(super) fn new<'r>(rules: impl IntoIterator<Item = &'r Rule>) -> InvalidationMap {
+        let mut map = InvalidationMap::default();
+        for selector in rules.into_iter().flat_map(|rule| &rule.selectors) {
+            let (first, rest) = match selector {
+                Selector::Simple(simple) => (simple, &[][..]),
+                Selector::Complex(first, rest) => (first, &rest[..]),
+            };
+            // Each simple selector, with the combinator after it, or `None`
+            // for the subject.
+            let simple_selectors = std::iter::once(first).chain(rest.iter().map(|(_, s)| s));
+            let combinators = rest.iter().map(|&(c, _)| Some(c)).chain([None]);
+            for (simple, combinator) in simple_selectors.zip(combinators) {
+                let invalidation = match combinator {
+                    None => Invalidation {
+                        element: true,
+                        ..Default::default()
+                    },
+                    Some(Combinator::Descendant | Combinator::Child) => Invalidation {
+                        descendants: true,
+                        ..Default::default()
+                    },
+                    Some(Combinator::NextSibling | Combinator::SubsequentSibling) => Invalidation {
+                        siblings: true,
+                        ..Default::default()
+                    },
+                };
+                map.add(simple, invalidation);
+            }
+        }
+        map
+    }
+
+    fn add(&mut self, simple: &SimpleSelector, invalidation: Invalidation) {
+        let mut features = 0;
+        for (bucket, name) in [
+            (&mut self.ids, &simple.id),
+            (&mut self.tags, &simple.tag_name),
+        ] {
+            if let Some(name) = name {
+                bucket.entry(name.clone()).or_default().add(invalidation);
+                features += 1;
+            }
+        }
+        for class in &simple.class {
+            self.classes
+                .entry(class.clone())
+                .or_default()
+                .add(invalidation);
+            features += 1;
+        }
+        if features == 0 {
+            self.universal.add(invalidation);
+        }
+    }
+
+    /// What to restyle after the `changes` to an element.
+    fn for_changes(&self, changes: &Changes) -> Invalidation {
+        let mut invalidation = Invalidation {
+            element: changes.style,
+            descendants: changes.children,
+            siblings: false,
+        };
+        let ids = changes.ids.iter().filter_map(|id| self.ids.get(id));
+        let classes = changes.classes.iter().filter_map(|c| self.classes.get(c));
+        ids.chain(classes).for_each(|&i| invalidation.add(i));
+        invalidation
+    }
+
+    /// What to restyle when the rules of this map change, around `elem`.
+    fn for_element(&self, elem: &ElementData) -> Invalidation {
+        let mut invalidation = self.universal;
+        let id = elem.id().and_then(|id| self.ids.get(id));
+        let tag = self.tags.get(&elem.tag_name);
+        let classes = elem
+            .classes()
+            .into_iter()
+            .filter_map(|c| self.classes.get(c));
+        id.into_iter()
+            .chain(tag)
+            .chain(classes)
+            .for_each(|&i| invalidation.add(i));
+        invalidation
+    }
+}
+
+/// Mark the nodes of the tree at `node` that have to be restyled as dirty,
+/// and the others as clean, given the selectors of all the rules (`rules`)
+/// and of those that changed (`changed_rules`). Every node is dirty if `all`
+/// is set. The changes to the nodes are cleared. Returns whether the later
+/// siblings of `node` are to be restyled.
+pub(super) fn invalidate(
+    node: &mut Node,
+    rules: &InvalidationMap,
+    changed_rules: &InvalidationMap,
+    all: bool,
+) -> bool {
+    let changes = std::mem::take(&mut node.changes);
+    let mut invalidation = rules.for_changes(&changes);
+    if let NodeType::Element(ref elem) = node.node_type {
+        invalidation.add(changed_rules.for_element(elem));
+    }
+    node.dirty = all || invalidation.element;
+
+    let mut siblings = false;
+    for child in &mut node.children {
+        let all = all || invalidation.descendants || siblings;
+        siblings |= invalidate(child, rules, changed_rules, all);
+    }
+    invalidation.siblings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css;
+    use crate::html;
+
+    fn map(css: &str) -> InvalidationMap {
+        let stylesheet = css::parse(css.to_string());
+        let rules = stylesheet.rules.iter().filter_map(|rule| match rule {
+            css::CssRule::Style(rule) => Some(rule),
+            _ => None,
+        });
+        InvalidationMap::new(rules)
+    }
+
+    /// The tag names of the dirty elements, in tree order.
+    fn dirty(node: &Node, names: &mut Vec<String>) {
+        if let (true, NodeType::Element(ref elem)) = (node.dirty, &node.node_type) {
+            names.push(elem.tag_name.clone());
+        }
+        node.children.iter().for_each(|child| dirty(child, names));
+    }
+
+    /// Change the class of the element at `path` to `class`, then return the
+    /// tag names of the elements to restyle.
+    fn restyled(html: &str, css: &str, path: &[usize], class: &str) -> Vec<String> {
+        let mut dom = html::parse(html.to_string());
+        let node = path.iter().fold(&mut dom, |node, &i| &mut node.children[i]);
+        node.set_attribute("class", Some(class.to_string()));
+        invalidate(&mut dom, &map(css), &InvalidationMap::default(), false);
+        let mut names = vec![];
+        dirty(&dom, &mut names);
+        names
+    }
+
+    #[test]
+    fn maps() {
+        let map = map("p.a, #b > .c, .d + * span, div {}");
+        let element = Invalidation {
+            element: true,
+            ..Default::default()
+        };
+        assert_eq!(map.classes["a"], element);
+        assert_eq!(map.tags["p"], element);
+        assert!(map.ids["b"].descendants && !map.ids["b"].element);
+        assert!(map.classes["c"].element);
+        assert!(map.classes["d"].siblings);
+        assert!(map.universal.descendants && !map.universal.element);
+        assert!(!map.classes.contains_key("x"));
+    }
+
+    #[test]
+    fn dom_changes() {
+        let html = "<div><p><b>x</b></p><p><i>y</i></p><hr></hr></div>";
+        // Classes no selector has don't restyle anything.
+        assert!(restyled(html, ".a {}", &[0], "b").is_empty());
+        assert_eq!(restyled(html, ".a {}", &[0], "a"), ["p"]);
+        assert_eq!(restyled(html, ".a b {}", &[0], "a"), ["b"]);
+        assert_eq!(restyled(html, ".a ~ hr {}", &[0], "a"), ["p", "i", "hr"]);
+        assert_eq!(restyled(html, ".a, .a * {}", &[1], "a"), ["p", "i"]);
+
+        let mut dom = html::parse(html.to_string());
+        dom.children[1].remove_child(0);
+        dom.children[0].set_attribute("title", Some("t".to_string()));
+        invalidate(&mut dom, &map(".a {}"), &InvalidationMap::default(), false);
+        assert!(!dom.children[0].dirty);
+        // The nodes are clean again once invalidated.
+        assert_eq!(dom.children[1].changes, Changes::default());
+    }
+
+    #[test]
+    fn stylesheet_changes() {
+        let mut dom = html::parse(
+            r#"<div><p class="a"><b>x</b></p><p><i>y</i></p><hr></hr></div>"#.to_string(),
+        );
+        let mut restyled = |css| {
+            invalidate(&mut dom, &InvalidationMap::default(), &map(css), false);
+            let mut names = vec![];
+            dirty(&dom, &mut names);
+            names
+        };
+        assert!(restyled(".x {}").is_empty());
+        assert_eq!(restyled("i {}"), ["i"]);
+        assert_eq!(restyled(".a > b {}"), ["b"]);
+        assert_eq!(restyled("* {}").len(), 6);
+    }
+}
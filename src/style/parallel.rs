@@ -0,0 +1,212 @@
+//! Styling on several threads. The children of an element are split into
+//! runs of consecutive siblings, which are styled on threads of their own,
+//! each with its own style sharing cache, and further split as long as
+//! threads are left.
+//!
+//! Only the way work is split depends on the number of threads, and every
+//! element is styled by the same code as on a single thread, so the result is
+//! the same.
+
+use crate::dom::Node;
+
+/// The fewest nodes worth styling on a thread of their own.
+const MIN_NODES_PER_THREAD: usize = 256;
+
+/// The number of nodes of a tree, and the sizes of the trees of its children,
+/// counted once up front rather than at every level.
+pub(super) struct Sizes {
+    pub(super) nodes: usize,
+    pub(super) children: Vec<Sizes>,
+}
+
+impl Sizes {
+    /// Count the nodes of the tree at `node` and of each of its subtrees.
+    pub(super) fn new(node: &Node) -> Sizes {
+        let children: Vec<Sizes> = node.children.iter().map(Sizes::new).collect();
+        Sizes {
+            nodes: 1 + children.iter().map(|child| child.nodes).sum::<usize>(),
+            children,
+        }
+    }
+}
+
+/// Split `items` into at most `threads` runs with about the same number of
+/// nodes each, given the number of nodes of each item. Runs have at least
+/// `MIN_NODES_PER_THREAD` nodes, so there is a single one for small trees.
+pub(super) fn partition<'a, T>(items: &'a [T], sizes: &[usize], threads: usize) -> Vec<&'a [T]> {
+    let total: usize = sizes.iter().sum();
+    let runs = threads.min(total / MIN_NODES_PER_THREAD).max(1);
+    let mut partition = vec![];
+    let (mut start, mut size) = (0, 0);
+    for (i, item_size) in sizes.iter().enumerate() {
+        size += item_size;
+        // End the run once the runs so far have their share of the nodes.
+        if size * runs >= total * (partition.len() + 1) {
+            partition.push(&items[start..=i]);
+            start = i + 1;
+        }
+    }
+    if start < items.len() {
+        partition.push(&items[start..]);
+    }
+    partition
+}
+
+/// Share `threads` between runs of `sizes` nodes each: a thread for each run,
+/// and the others in proportion to their sizes, with the threads left over
+/// from rounding going to the largest run.
+pub(super) fn share(threads: usize, sizes: &[usize]) -> Vec<usize> {
+    let total = sizes.iter().sum::<usize>().max(1);
+    let spare = threads.saturating_sub(sizes.len());
+    let mut shares: Vec<usize> = sizes.iter().map(|size| 1 + spare * size / total).collect();
+    if let Some(largest) = (0..sizes.len()).max_by_key(|&i| sizes[i]) {
+        shares[largest] += threads.saturating_sub(shares.iter().sum());
+    }
+    shares
+}
+
+/// Call `f` with each run on a thread of its own, returning their results in
+/// order. A panic on any thread is resumed on this one.
+pub(super) fn map<T: Sync, R: Send>(runs: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = runs.iter().map(|run| scope.spawn(|| f(run))).collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|e| std::panic::resume_unwind(e))
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{self, Device};
+    use crate::html;
+    use crate::style::{style_tree, style_tree_parallel, StyledNode};
+    use std::time::Instant;
+
+    #[test]
+    fn partitions() {
+        let items: Vec<usize> = (0..10).collect();
+        let sizes = [100, 100, 100, 100, 100, 100, 100, 100, 100, 100];
+        let runs = partition(&items, &sizes, 4);
+        assert_eq!(runs.len(), 3);
+        assert_eq!(runs.concat(), items);
+        assert!(runs.iter().all(|run| run.len() >= 3));
+
+        // One big item gets a run of its own.
+        let runs = partition(&items, &[1, 1, 1, 1, 1, 1, 1000, 1, 1, 1], 2);
+        assert_eq!(runs, [&items[..7], &items[7..]]);
+
+        assert_eq!(partition(&items, &sizes, 1).len(), 1);
+        assert_eq!(partition(&items[..0], &[], 8).len(), 0);
+    }
+
+    #[test]
+    fn shares() {
+        assert_eq!(share(8, &[300, 300]), [4, 4]);
+        assert_eq!(share(10, &[600, 200, 200]), [6, 2, 2]);
+        // A small run still gets a thread, and the largest run gets the
+        // threads left over.
+        assert_eq!(share(32, &[100000, 1]), [31, 1]);
+        assert_eq!(share(8, &[300, 500, 400]), [2, 4, 2]);
+        assert_eq!(share(3, &[1000]), [3]);
+    }
+
+    #[test]
+    fn sizes() {
+        let dom = html::parse("<div><p>a</p><p></p>b</div>".to_string());
+        let sizes = Sizes::new(&dom);
+        assert_eq!(sizes.nodes, 5);
+        let children: Vec<usize> = sizes.children.iter().map(|child| child.nodes).collect();
+        assert_eq!(children, [2, 1, 1]);
+    }
+
+    /// Return a description of the styled tree, with every computed value
+    /// sorted by property.
+    fn dump(node: &StyledNode, out: &mut String) {
+        let mut values: Vec<_> = node.computed_values.iter().collect();
+        values.sort_by(|a, b| a.0.cmp(b.0));
+        *out += &format!("{:?} {:?} {:?}\n", node.pseudo_element, node.text(), values);
+        node.box_children().for_each(|child| dump(child, out));
+    }
+
+    /// A page of `sections` sections, and a stylesheet for it.
+    fn page(sections: usize) -> (Node, css::Stylesheet) {
+        let mut html = String::from("<main>");
+        for i in 0..sections {
+            html += &format!(
+                r#"<section id="s{i}" class="c{}"><h1>t</h1><ol><li>a</li><li class="x">b</li></ol><p><a>x</a> <b>y</b></p></section>"#,
+                i % 7
+            );
+        }
+        html += "</main>";
+        let stylesheet = css::parse(
+            r#"
+            main { font-size: 20px; --gap: 3px; }
+            section { counter-increment: section; }
+            .c1 { font-size: 1.5em; }
+            .c2 > ol { list-style-type: lower-roman; }
+            li + li { margin-top: var(--gap); }
+            .x ~ li::before, #s42 h1::after { content: counter(section) "."; }
+            .c3 p + p a, .c4 b { color: red; width: 2rem; }
+            "#
+            .to_string(),
+        );
+        (html::parse(html), stylesheet)
+    }
+
+    #[test]
+    fn deterministic() {
+        let (dom, stylesheet) = page(100);
+        let device = Device::new(800.0, 600.0);
+        let stylesheets = [stylesheet];
+        let mut serial = String::new();
+        dump(&style_tree(&dom, &stylesheets, &device), &mut serial);
+        for threads in [2, 3, 8, 32] {
+            for _ in 0..2 {
+                let mut parallel = String::new();
+                let styled = style_tree_parallel(&dom, &stylesheets, &device, threads);
+                dump(&styled, &mut parallel);
+                assert!(
+                    serial == parallel,
+                    "different output with {threads} threads"
+                );
+            }
+        }
+    }
+
+    /// Compare the time it takes to style a page with about 130000 nodes on
+    /// one thread and on all of them. Run with
+    /// `cargo test --release parallel_style_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn parallel_style_benchmark() {
+        let (dom, stylesheet) = page(10000);
+        let device = Device::new(800.0, 600.0);
+        let stylesheets = [stylesheet];
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        // The first run also pays for growing the heap.
+        drop(style_tree(&dom, &stylesheets, &device));
+        let start = Instant::now();
+        let styled = style_tree(&dom, &stylesheets, &device);
+        let serial = start.elapsed();
+        drop(styled);
+        let start = Instant::now();
+        let styled = style_tree_parallel(&dom, &stylesheets, &device, threads);
+        let parallel = start.elapsed();
+        drop(styled);
+        println!(
+            "{} nodes: serial {:?}, {} threads {:?} ({:.1}x)",
+            Sizes::new(&dom).nodes,
+            serial,
+            threads,
+            parallel,
+            serial.as_secs_f64() / parallel.as_secs_f64()
+        );
+    }
+}
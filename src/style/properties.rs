@@ -0,0 +1,499 @@
+//! The property registry, and the computation of computed values from
+//! cascaded values.
+//! spec: https://www.w3.org/TR/css-cascade-4/#value-stages
+
+use super::{display::Display, variables, PropertyMap};
+use crate::css::{self, Calc, LengthContext, Unit, Value, DEFAULT_FONT_SIZE};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// The longhand properties the engine knows: their names, whether they are
+/// inherited, and their initial values.
+const PROPERTIES: &[(&str, Inherited, &str)] = &[
+    ("background-attachment", No, "scroll"),
+    ("background-color", No, "transparent"),
+    ("background-image", No, "none"),
+    ("background-position", No, "0% 0%"),
+    ("background-repeat", No, "repeat"),
+    ("border-bottom-color", No, "currentcolor"),
+    ("border-bottom-style", No, "none"),
+    ("border-bottom-width", No, "medium"),
+    ("border-left-color", No, "currentcolor"),
+    ("border-left-style", No, "none"),
+    ("border-left-width", No, "medium"),
+    ("border-right-color", No, "currentcolor"),
+    ("border-right-style", No, "none"),
+    ("border-right-width", No, "medium"),
+    ("border-top-color", No, "currentcolor"),
+    ("border-top-style", No, "none"),
+    ("border-top-width", No, "medium"),
+    ("bottom", No, "auto"),
+    ("color", Yes, "black"),
+    ("content", No, "normal"),
+    ("counter-increment", No, "none"),
+    ("counter-reset", No, "none"),
+    ("counter-set", No, "none"),
+    ("display", No, "inline"),
+    ("font-family", Yes, "serif"),
+    ("font-size", Yes, "medium"),
+    ("font-stretch", Yes, "normal"),
+    ("font-style", Yes, "normal"),
+    ("font-variant", Yes, "normal"),
+    ("font-weight", Yes, "normal"),
+    ("height", No, "auto"),
+    ("left", No, "auto"),
+    ("line-height", Yes, "normal"),
+    ("list-style-position", Yes, "outside"),
+    ("list-style-type", Yes, "disc"),
+    ("margin-bottom", No, "0"),
+    ("margin-left", No, "0"),
+    ("margin-right", No, "0"),
+    ("margin-top", No, "0"),
+    ("padding-bottom", No, "0"),
+    ("padding-left", No, "0"),
+    ("padding-right", No, "0"),
+    ("padding-top", No, "0"),
+    ("right", No, "auto"),
+    ("text-align", Yes, "start"),
+    ("top", No, "auto"),
+    ("visibility", Yes, "visible"),
+    ("white-space", Yes, "normal"),
+    ("width", No, "auto"),
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Inherited {
+    Yes,
+    No,
+}
+use Inherited::*;
+
+struct Property {
+    inherited: bool,
+    initial: Value,
+}
+
+/// Return the registered properties by name, with their initial values
+/// parsed on first use.
+fn registry() -> &'static HashMap<&'static str, Property> {
+    static REGISTRY: OnceLock<HashMap<&str, Property>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        PROPERTIES
+            .iter()
+            .map(|&(name, inherited, initial)| {
+                let declaration = css::parse_inline(&format!("{}: {}", name, initial)).remove(0);
+                let property = Property {
+                    inherited: inherited == Yes,
+                    initial: declaration.value,
+                };
+                (name, property)
+            })
+            .collect()
+    })
+}
+
+/// Return true if the engine supports a declaration of property `name` with
+/// `value`, as in `@supports (name: value)`: if it is a custom property, or
+/// the declaration parses to registered longhands with valid values.
+pub(super) fn supports_declaration(name: &str, value: &str) -> bool {
+    if variables::is_custom(name) {
+        return true;
+    }
+    let declarations = css::parse_inline(&format!("{}: {}", name, value));
+    !declarations.is_empty()
+        && declarations.iter().all(|declaration| {
+            !declaration.important
+                && registry().contains_key(&*declaration.name)
+                && is_valid(&declaration.name, &declaration.value)
+        })
+}
+
+/// Return true if `value` is valid for longhand `name`. The declaration
+/// parser validates shorthands and the values of `content` and the counter
+/// properties, but takes any component values for the other longhands.
+fn is_valid(name: &str, value: &Value) -> bool {
+    let is_keyword = |value: &Value, keywords: &[&str]| matches!(value, Value::Keyword(k) if keywords.contains(&&**k));
+    let is_length = |value: &Value| matches!(value, Value::Length(..) | Value::Calc(_));
+    let is_color = |value: &Value| {
+        matches!(value, Value::Colorvalue(_)) || is_keyword(value, &["currentcolor"])
+    };
+    if is_keyword(
+        value,
+        &["inherit", "initial", "unset", "revert", "revert-layer"],
+    ) {
+        return true;
+    }
+    match name {
+        _ if name.ends_with("-color") || name == "color" => is_color(value),
+        _ if name.ends_with("-style") && name.starts_with("border-") => is_keyword(
+            value,
+            &[
+                "none", "hidden", "dotted", "dashed", "solid", "double", "groove", "ridge",
+                "inset", "outset",
+            ],
+        ),
+        _ if name.ends_with("-width") && name.starts_with("border-") => {
+            is_length(value) || is_keyword(value, &["thin", "medium", "thick"])
+        }
+        "top" | "right" | "bottom" | "left" | "width" | "height" => {
+            is_length(value) || is_keyword(value, &["auto"])
+        }
+        _ if name.starts_with("margin-") => is_length(value) || is_keyword(value, &["auto"]),
+        _ if name.starts_with("padding-") => is_length(value),
+        "background-attachment" => is_keyword(value, &["scroll", "fixed", "local"]),
+        "background-image" => matches!(value, Value::Url(_)) || is_keyword(value, &["none"]),
+        "background-position" => {
+            let is_position = |value: &Value| {
+                is_length(value) || is_keyword(value, &["left", "right", "top", "bottom", "center"])
+            };
+            match value {
+                Value::List(values) => values.iter().all(is_position),
+                value => is_position(value),
+            }
+        }
+        "background-repeat" => is_keyword(
+            value,
+            &[
+                "repeat",
+                "repeat-x",
+                "repeat-y",
+                "no-repeat",
+                "space",
+                "round",
+            ],
+        ),
+        "display" => Display::from_value(value).is_some(),
+        "font-family" => {
+            let is_family = |value: &Value| matches!(value, Value::Keyword(_) | Value::Str(_));
+            match value {
+                Value::List(values) => values
+                    .iter()
+                    .all(|value| is_family(value) || *value == Value::Delim(',')),
+                value => is_family(value),
+            }
+        }
+        "font-size" => {
+            is_length(value)
+                || is_keyword(
+                    value,
+                    &[
+                        "xx-small",
+                        "x-small",
+                        "small",
+                        "medium",
+                        "large",
+                        "x-large",
+                        "xx-large",
+                        "xxx-large",
+                        "larger",
+                        "smaller",
+                    ],
+                )
+        }
+        "font-stretch" => match value {
+            Value::Keyword(k) => {
+                k == "normal" || k.ends_with("condensed") || k.ends_with("expanded")
+            }
+            value => matches!(value, Value::Length(_, Unit::Percent)),
+        },
+        "font-style" => is_keyword(value, &["normal", "italic", "oblique"]),
+        "font-variant" => is_keyword(value, &["normal", "small-caps"]),
+        "font-weight" => match value {
+            Value::Number(n) => (1.0..=1000.0).contains(n),
+            value => is_keyword(value, &["normal", "bold", "bolder", "lighter"]),
+        },
+        "line-height" => {
+            is_length(value) || matches!(value, Value::Number(_)) || is_keyword(value, &["normal"])
+        }
+        "list-style-position" => is_keyword(value, &["inside", "outside"]),
+        // Any counter style name, or a string.
+        "list-style-type" => matches!(value, Value::Keyword(_) | Value::Str(_)),
+        "text-align" => is_keyword(
+            value,
+            &[
+                "start",
+                "end",
+                "left",
+                "right",
+                "center",
+                "justify",
+                "match-parent",
+            ],
+        ),
+        "visibility" => is_keyword(value, &["visible", "hidden", "collapse"]),
+        "white-space" => is_keyword(
+            value,
+            &[
+                "normal",
+                "pre",
+                "nowrap",
+                "pre-wrap",
+                "pre-line",
+                "break-spaces",
+            ],
+        ),
+        _ => true,
+    }
+}
+
+/// Compute the values of an element from its cascaded values and the computed
+/// values of its parent, or `None` for the root element.
+///
+/// Properties without a cascaded value are inherited or take their initial
+/// value, as do the CSS-wide keywords. Variables are substituted, then font
+/// sizes and font-relative lengths are resolved to px; percentages and
+/// viewport units are left to layout.
+pub(super) fn compute_values(
+    mut cascaded: PropertyMap,
+    parent: Option<&PropertyMap>,
+    root_font_size: f32,
+) -> PropertyMap {
+    let custom_properties = variables::compute_custom_properties(&cascaded, parent);
+    cascaded.retain(|name, _| !variables::is_custom(name));
+    for (name, value) in cascaded.iter_mut() {
+        if let Value::PendingSubstitution(property, css) = value {
+            *value = substitute(name, property, css, &custom_properties);
+        }
+    }
+
+    let registry = registry();
+    let mut values: PropertyMap = registry
+        .keys()
+        .filter(|name| !cascaded.contains_key(**name))
+        .filter_map(|name| Some((name.to_string(), defaulted(name, "unset", parent)?)))
+        .collect();
+    for (name, value) in cascaded {
+        let value = match value {
+            Value::Keyword(ref k) if matches!(&**k, "inherit" | "initial" | "unset") => {
+                defaulted(&name, k, parent)
+            }
+            // `currentcolor` in `color` itself means the inherited color.
+            Value::Keyword(ref k) if name == "color" && k == "currentcolor" => {
+                defaulted(&name, "inherit", parent)
+            }
+            value => Some(value),
+        };
+        if let Some(value) = value {
+            values.insert(name, value);
+        }
+    }
+    values.extend(custom_properties);
+
+    let parent_font_size = match parent.and_then(|p| p.get("font-size")) {
+        Some(Value::Length(px, Unit::Px)) => Some(*px),
+        Some(_) => None,
+        None => Some(DEFAULT_FONT_SIZE),
+    };
+    if let (Some(parent_font_size), Some(font_size)) = (parent_font_size, values.get("font-size")) {
+        let font_size = compute_font_size(font_size, parent_font_size, root_font_size);
+        values.insert("font-size".to_string(), font_size);
+    }
+
+    // Lengths relative to the font can only be resolved once the font size is.
+    if let Some(&Value::Length(font_size, Unit::Px)) = values.get("font-size") {
+        let context = LengthContext {
+            font_size,
+            root_font_size,
+            ..LengthContext::new(0.0, 0.0)
+        };
+        for value in values.values_mut() {
+            resolve_font_relative(value, &context);
+        }
+    }
+    values
+}
+
+/// Substitute the variables of the pending-substitution value `css` of
+/// longhand `name`, declared with `property`, and parse the result. A value
+/// that is invalid at computed-value time acts as `unset`.
+/// spec: https://www.w3.org/TR/css-variables-1/#invalid-at-computed-value-time
+fn substitute(name: &str, property: &str, css: &str, custom_properties: &PropertyMap) -> Value {
+    let unset = Value::Keyword("unset".to_string());
+    let Some(css) = variables::substitute_pending(css, custom_properties) else {
+        return unset;
+    };
+    // An invalid value is dropped by the parser, leaving no declaration.
+    css::parse_inline(&format!("{}: {}", property, css))
+        .into_iter()
+        .find(|declaration| declaration.name == name)
+        .map_or(unset, |declaration| declaration.value)
+}
+
+/// Return the computed values of an anonymous box, such as a text node, which
+/// inherits everything it can from its parent. `root_font_size` is the font
+/// size of the root element.
+pub(super) fn inherit(parent: &PropertyMap, root_font_size: f32) -> PropertyMap {
+    compute_values(PropertyMap::new(), Some(parent), root_font_size)
+}
+
+/// Apply the CSS-wide `keyword` to property `name`. Unknown properties have no
+/// initial value, so they are only set by `inherit`.
+/// spec: https://www.w3.org/TR/css-cascade-4/#defaulting-keywords
+fn defaulted(name: &str, keyword: &str, parent: Option<&PropertyMap>) -> Option<Value> {
+    let property = registry().get(name);
+    let initial = property.map(|p| p.initial.clone());
+    let inherited = || parent.and_then(|p| p.get(name)).cloned();
+    match keyword {
+        "inherit" => inherited().or(initial),
+        "initial" => initial,
+        _ if property.is_some_and(|p| p.inherited) => inherited().or(initial),
+        _ => initial,
+    }
+}
+
+/// Resolve a specified `font-size` to px. `em` and percentages are relative to
+/// the parent's font size.
+/// spec: https://www.w3.org/TR/css-fonts-4/#font-size-prop
+fn compute_font_size(value: &Value, parent_font_size: f32, root_font_size: f32) -> Value {
+    let px = match value {
+        Value::Length(_, Unit::Vw | Unit::Vh | Unit::Vmin | Unit::Vmax) => return value.clone(),
+        Value::Calc(calc)
+            if calc.has_unit(&|unit| {
+                matches!(unit, Unit::Vw | Unit::Vh | Unit::Vmin | Unit::Vmax)
+            }) =>
+        {
+            return value.clone()
+        }
+        Value::Length(..) | Value::Calc(_) => {
+            let parent = LengthContext {
+                font_size: parent_font_size,
+                root_font_size,
+                ..LengthContext::new(0.0, 0.0)
+            };
+            value.to_px(&parent, parent_font_size)
+        }
+        // spec: https://www.w3.org/TR/css-fonts-4/#absolute-size-mapping
+        Value::Keyword(keyword) => match &**keyword {
+            "xx-small" => DEFAULT_FONT_SIZE * 3.0 / 5.0,
+            "x-small" => DEFAULT_FONT_SIZE * 3.0 / 4.0,
+            "small" => DEFAULT_FONT_SIZE * 8.0 / 9.0,
+            "medium" => DEFAULT_FONT_SIZE,
+            "large" => DEFAULT_FONT_SIZE * 6.0 / 5.0,
+            "x-large" => DEFAULT_FONT_SIZE * 3.0 / 2.0,
+            "xx-large" => DEFAULT_FONT_SIZE * 2.0,
+            "xxx-large" => DEFAULT_FONT_SIZE * 3.0,
+            "larger" => parent_font_size * 1.2,
+            "smaller" => parent_font_size / 1.2,
+            _ => parent_font_size,
+        },
+        _ => parent_font_size,
+    };
+    Value::Length(px, Unit::Px)
+}
+
+/// Resolve `em`, `rem`, `ex` and `ch` lengths in `value` to px, and simplify
+/// math functions, which become plain lengths unless they depend on layout.
+fn resolve_font_relative(value: &mut Value, context: &LengthContext) {
+    match value {
+        Value::Length(_, Unit::Em | Unit::Rem | Unit::Ex | Unit::Ch) => {
+            *value = Value::Length(value.to_px(context, 0.0), Unit::Px);
+        }
+        Value::Calc(calc) => {
+            *value = match calc.simplify(context) {
+                Calc::Leaf(leaf) => leaf,
+                calc => Value::Calc(Box::new(calc)),
+            };
+        }
+        Value::List(values) => {
+            for value in values {
+                resolve_font_relative(value, context);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cascaded(declarations: &str) -> PropertyMap {
+        css::parse_inline(declarations)
+            .into_iter()
+            .map(|d| (d.name, d.value))
+            .collect()
+    }
+
+    #[test]
+    fn initial_values_parse() {
+        let registry = registry();
+        assert_eq!(registry.len(), PROPERTIES.len());
+        assert_eq!(registry["margin-top"].initial, Value::Length(0.0, Unit::Px));
+        for (name, property) in registry {
+            assert!(is_valid(name, &property.initial), "{}", name);
+        }
+    }
+
+    #[test]
+    fn supported_declarations() {
+        assert!(supports_declaration("margin-top", "1px"));
+        assert!(supports_declaration("border", "1px solid red"));
+        assert!(supports_declaration("display", "inline flow-root"));
+        assert!(supports_declaration("color", "inherit"));
+        assert!(supports_declaration("--anything", "at all"));
+        assert!(!supports_declaration("grid-template-areas", "none"));
+        assert!(!supports_declaration("display", "bogus"));
+        assert!(!supports_declaration("color", "notacolor"));
+        assert!(!supports_declaration("width", "red"));
+        assert!(!supports_declaration("border", "1px bogus red"));
+        assert!(!supports_declaration("color", "red !important"));
+    }
+
+    #[test]
+    fn defaulting() {
+        let parent = compute_values(
+            cascaded("color: red; font-size: 20px; width: 10px; padding-top: 3px"),
+            None,
+            DEFAULT_FONT_SIZE,
+        );
+        let child = compute_values(
+            cascaded("width: inherit; padding-top: unset; list-style-type: initial"),
+            Some(&parent),
+            DEFAULT_FONT_SIZE,
+        );
+        assert_eq!(child["color"], parent["color"]);
+        assert_eq!(child["font-size"], Value::Length(20.0, Unit::Px));
+        assert_eq!(child["width"], Value::Length(10.0, Unit::Px));
+        assert_eq!(child["padding-top"], Value::Length(0.0, Unit::Px));
+        assert_eq!(child["display"], Value::Keyword("inline".to_string()));
+        assert_eq!(child["list-style-type"], Value::Keyword("disc".to_string()));
+    }
+
+    #[test]
+    fn font_relative_lengths() {
+        let root = compute_values(cascaded("font-size: 10px"), None, DEFAULT_FONT_SIZE);
+        let child = compute_values(
+            cascaded("font-size: 2em; margin-left: 1.5em; padding-left: 2rem; width: 50%"),
+            Some(&root),
+            10.0,
+        );
+        assert_eq!(child["font-size"], Value::Length(20.0, Unit::Px));
+        assert_eq!(child["margin-left"], Value::Length(30.0, Unit::Px));
+        assert_eq!(child["padding-left"], Value::Length(20.0, Unit::Px));
+        assert_eq!(child["width"], Value::Length(50.0, Unit::Percent));
+
+        let grandchild = compute_values(cascaded("font-size: larger"), Some(&child), 10.0);
+        assert_eq!(grandchild["font-size"], Value::Length(24.0, Unit::Px));
+    }
+
+    #[test]
+    fn invalid_substitutions() {
+        let parent = compute_values(cascaded("color: red"), None, DEFAULT_FONT_SIZE);
+        let child = compute_values(
+            cascaded(
+                "--x: @@@; --y: rgb(a b c); --z: 1qq; color: var(--x); \
+                 background-color: var(--y); width: var(--z); margin: 1px var(--y)",
+            ),
+            Some(&parent),
+            DEFAULT_FONT_SIZE,
+        );
+        // Values which don't parse once substituted act as `unset`.
+        assert_eq!(child["color"], parent["color"]);
+        assert_eq!(
+            child["background-color"],
+            registry()["background-color"].initial
+        );
+        assert_eq!(child["width"], Value::Keyword("auto".to_string()));
+        assert_eq!(child["margin-top"], Value::Length(0.0, Unit::Px));
+    }
+}
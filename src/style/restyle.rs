@@ -0,0 +1,275 @@
+//! Incremental restyling: styling a DOM tree again after it or the
+//! stylesheets changed, recomputing only the styles which the changes may
+//! affect, see `invalidation`.
+//!
+//! Other nodes keep the styles they had, unless the values they inherit
+//! changed.
+
+use super::invalidation::{self, InvalidationMap};
+use super::sharing::{ElementStyle, StyleSharingCache};
+use super::{
+    active_stylesheets, build_style_tree, counters, PropertyMap, StyleContext, StyledNode,
+};
+use crate::css::{Device, Origin, Rule, Stylesheet, DEFAULT_FONT_SIZE};
+use crate::dom::Node;
+use std::sync::Arc;
+
+/// Styles a DOM tree each time it changes, restyling only what the changes
+/// affect. Changes to the DOM are tracked when made with the methods of
+/// `dom::Node`, like `set_attribute`.
+#[derive(Default)]
+pub struct Restyler {
+    /// The style rules that applied the last time, with their origin and the
+    /// rank of their layer.
+    rules: Vec<(Origin, usize, Rule)>,
+    /// The styles of the tree the last time, if it was styled.
+    styles: Option<Styles>,
+}
+
+impl Restyler {
+    pub fn new() -> Restyler {
+        Restyler::default()
+    }
+
+    /// Style the tree at `root`, like `style_tree`. After the first time,
+    /// only the nodes affected by the changes to the tree or to the style
+    /// rules since the last time are restyled.
+    pub fn restyle<'a>(
+        &mut self,
+        root: &'a mut Node,
+        stylesheets: &[Stylesheet],
+        device: &Device,
+    ) -> StyledNode<'a> {
+        let active = active_stylesheets(stylesheets, device);
+        let rules: Vec<(Origin, usize, &Rule)> = active
+            .iter()
+            .flat_map(|stylesheet| {
+                let origin = stylesheet.origin;
+                stylesheet
+                    .rules
+                    .iter()
+                    .map(move |&(layer, rule)| (origin, layer, rule))
+            })
+            .collect();
+        let all_rules = InvalidationMap::new(rules.iter().map(|&(_, _, rule)| rule));
+        let changed_rules = InvalidationMap::new(changed_rules(&self.rules, &rules));
+        let all = self.styles.is_none();
+        invalidation::invalidate(root, &all_rules, &changed_rules, all);
+
+        let root: &'a Node = root;
+        let previous = self.styles.as_ref().map(|styles| super::Previous {
+            styles,
+            parent_changed: false,
+        });
+        let mut cache = StyleSharingCache::new(super::sharing::CACHE_SIZE);
+        let context = StyleContext {
+            stylesheets: &active,
+            cache: &mut cache,
+            threads: 1,
+            sizes: None,
+            previous,
+        };
+        let mut styled = build_style_tree(root, None, DEFAULT_FONT_SIZE, None, &[], context);
+        counters::generate_content(&mut styled);
+
+        self.styles = Some(Styles::new(&styled));
+        self.rules = rules
+            .into_iter()
+            .map(|(origin, layer, rule)| (origin, layer, rule.clone()))
+            .collect();
+        styled
+    }
+}
+
+/// The rules that differ between `old` and `new`: those after the rules
+/// they start with and before the rules they end with. The other rules keep
+/// their order, so the cascade only changes for elements which match one of
+/// them.
+fn changed_rules<'r>(
+    old: &'r [(Origin, usize, Rule)],
+    new: &[(Origin, usize, &'r Rule)],
+) -> impl Iterator<Item = &'r Rule> {
+    let same = |(a, b): (&(Origin, usize, Rule), &(Origin, usize, &Rule))| {
+        (a.0, a.1, &a.2) == (b.0, b.1, b.2)
+    };
+    let start = old.iter().zip(new).take_while(|&pair| same(pair)).count();
+    let end = old[start..]
+        .iter()
+        .rev()
+        .zip(new[start..].iter().rev())
+        .take_while(|&pair| same(pair))
+        .count();
+    let old = old[start..old.len() - end].iter().map(|(_, _, rule)| rule);
+    let new = new[start..new.len() - end].iter().map(|&(_, _, rule)| rule);
+    old.chain(new.collect::<Vec<_>>())
+}
+
+/// The styles of a node and its descendants, kept without the DOM.
+pub(super) struct Styles {
+    pub(super) style: ElementStyle,
+    pub(super) children: Vec<Styles>,
+}
+
+impl Styles {
+    fn new(node: &StyledNode) -> Styles {
+        let values = |node: &Option<Box<StyledNode>>| -> Option<Arc<PropertyMap>> {
+            Some(node.as_ref()?.computed_values.clone())
+        };
+        Styles {
+            style: ElementStyle {
+                values: node.computed_values.clone(),
+                marker: values(&node.marker),
+                before: values(&node.before),
+                after: values(&node.after),
+            },
+            children: node.children.iter().map(Styles::new).collect(),
+        }
+    }
+
+    /// Return true if `style` has the same values as this node had.
+    pub(super) fn same_values(&self, style: &ElementStyle) -> bool {
+        Arc::ptr_eq(&self.style.values, &style.values) || self.style.values == style.values
+    }
+}
+
+/// The styles a node had, given to restyle it.
+#[derive(Clone, Copy)]
+pub(super) struct Previous<'p> {
+    pub(super) styles: &'p Styles,
+    /// Set if the values its parent passes down changed since.
+    pub(super) parent_changed: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css;
+    use crate::html;
+    use crate::style::style_tree;
+
+    /// Return true if both trees have the same computed values everywhere.
+    fn same_values(a: &Styles, b: &Styles) -> bool {
+        let pseudo_elements = |styles: &Styles| {
+            let style = &styles.style;
+            [&style.marker, &style.before, &style.after].map(|values| values.as_deref().cloned())
+        };
+        a.style.values == b.style.values
+            && pseudo_elements(a) == pseudo_elements(b)
+            && a.children.len() == b.children.len()
+            && a.children
+                .iter()
+                .zip(&b.children)
+                .all(|(a, b)| same_values(a, b))
+    }
+
+    /// Return true if the node at `path` kept its values.
+    fn kept(before: &Styles, after: &Styles, path: &[usize]) -> bool {
+        let node = |root, path: &[usize]| {
+            path.iter()
+                .fold(root, |node: &Styles, &i| &node.children[i])
+        };
+        Arc::ptr_eq(
+            &node(before, path).style.values,
+            &node(after, path).style.values,
+        )
+    }
+
+    /// Restyle `dom`, and check the result is the same as styling it from
+    /// scratch.
+    fn restyle(restyler: &mut Restyler, dom: &mut Node, stylesheets: &[Stylesheet]) -> Styles {
+        let device = Device::new(800.0, 600.0);
+        let styles = Styles::new(&restyler.restyle(dom, stylesheets, &device));
+        assert!(same_values(
+            &styles,
+            &Styles::new(&style_tree(dom, stylesheets, &device))
+        ));
+        styles
+    }
+
+    #[test]
+    fn dom_changes() {
+        let mut dom = html::parse(
+            r#"<div><p class="a"><b>x</b></p><p><i>y</i></p><ol><li>1</li></ol></div>"#.to_string(),
+        );
+        let stylesheets = [css::parse(
+            r#"
+            p { font-size: 16px; }
+            b { margin-left: 1rem; }
+            .a { color: #ff0000; }
+            .b + p { margin-left: 3px; }
+            .big { font-size: 30px; }
+            li::before { content: counter(list-item) "."; }
+            "#
+            .to_string(),
+        )];
+        let mut restyler = Restyler::new();
+        let before = restyle(&mut restyler, &mut dom, &stylesheets);
+
+        dom.children[0].set_attribute("class", Some("b".to_string()));
+        dom.children[2].append_child(html::parse("<li>2</li>".to_string()));
+        let after = restyle(&mut restyler, &mut dom, &stylesheets);
+        // The first paragraph and its content lose the color.
+        assert!(!kept(&before, &after, &[0]));
+        assert!(!kept(&before, &after, &[0, 0]));
+        // The second one now follows a `.b`, but its content inherits the
+        // same values.
+        assert!(!kept(&before, &after, &[1]));
+        assert!(kept(&before, &after, &[1, 0]));
+        assert!(kept(&before, &after, &[2]));
+        assert!(after.children[2].children[1].style.before.is_some());
+
+        // `rem` lengths change everywhere with the font size of the root,
+        // even where the parent didn't change.
+        dom.set_attribute("class", Some("big".to_string()));
+        let after = restyle(&mut restyler, &mut dom, &stylesheets);
+        let margin = after.children[0].children[0]
+            .style
+            .values
+            .get("margin-left");
+        assert_eq!(margin, Some(&css::Value::Length(30.0, css::Unit::Px)));
+    }
+
+    #[test]
+    fn stylesheet_changes() {
+        let mut dom = html::parse(r#"<div><p class="a">x</p><p>y</p></div>"#.to_string());
+        let mut restyler = Restyler::new();
+        let mut stylesheets = [css::parse(
+            ".a { color: #ff0000; } p { margin: 1px; }".to_string(),
+        )];
+        let before = restyle(&mut restyler, &mut dom, &stylesheets);
+
+        // Editing a rule only restyles the elements it matches.
+        stylesheets[0] = css::parse(".a { color: #00ff00; } p { margin: 1px; }".to_string());
+        let after = restyle(&mut restyler, &mut dom, &stylesheets);
+        assert!(!kept(&before, &after, &[0]));
+        assert!(kept(&before, &after, &[1]));
+
+        // Rules that moved change the cascade of the elements they match.
+        stylesheets[0] = css::parse(
+            "p { margin: 1px; } .a { color: #00ff00; margin: 2px; } p { margin: 1px; }".to_string(),
+        );
+        restyle(&mut restyler, &mut dom, &stylesheets);
+    }
+
+    #[test]
+    fn changed() {
+        let rules = css::parse("a {} b {} c {} d {}".to_string());
+        let rule = |i: usize| match &rules.rules[i] {
+            css::CssRule::Style(rule) => rule,
+            _ => unreachable!(),
+        };
+        let old: Vec<_> = (0..4)
+            .map(|i| (Origin::Author, 0, rule(i).clone()))
+            .collect();
+        let names = |new: &[usize]| -> Vec<String> {
+            let new: Vec<_> = new.iter().map(|&i| (Origin::Author, 0, rule(i))).collect();
+            changed_rules(&old, &new)
+                .map(|rule| rule.selectors[0].subject().tag_name.clone().unwrap())
+                .collect()
+        };
+        assert!(names(&[0, 1, 2, 3]).is_empty());
+        assert_eq!(names(&[0, 1, 3]), ["c"]);
+        assert_eq!(names(&[0, 2, 1, 3]), ["b", "c", "c", "b"]);
+        assert_eq!(names(&[]), ["a", "b", "c", "d"]);
+    }
+}
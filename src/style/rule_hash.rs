@@ -0,0 +1,238 @@
+//! An index of the selectors of a stylesheet by their subject, so that an
+//! element is only matched against the selectors that may match it.
+//!
+//! Each selector goes in one bucket, by the id of its subject if it has one,
+//! else its first class, else its tag name. The rest are universal. An
+//! element's candidates are then the selectors in the buckets of its id, its
+//! classes and its tag name, and the universal ones.
+
+use crate::css::Rule;
+use crate::dom::ElementData;
+use std::collections::HashMap;
+
+/// A selector, as the index of its rule in the stylesheet and its index in
+/// the selectors of the rule.
+type Entry = (usize, usize);
+
+#[derive(Debug, Default)]
+pub(super) struct RuleHash {
+    ids: HashMap<String, Vec<Entry>>,
+    classes: HashMap<String, Vec<Entry>>,
+    tags: HashMap<String, Vec<Entry>>,
+    universal: Vec<Entry>,
+}
+
+impl RuleHash {
+    /// Index the selectors of `rules`.
+    pub(super) fn new(rules: &[(usize, &Rule)]) -> RuleHash {
+        let mut hash = RuleHash::default();
+        for (i, (_, rule)) in rules.iter().enumerate() {
+            for (j, selector) in rule.selectors.iter().enumerate() {
+                let subject = selector.subject();
+                let bucket = if let Some(id) = &subject.id {
+                    hash.ids.entry(id.clone()).or_default()
+                } else if let Some(class) = subject.class.first() {
+                    hash.classes.entry(class.clone()).or_default()
+                } else if let Some(tag_name) = &subject.tag_name {
+                    hash.tags.entry(tag_name.clone()).or_default()
+                } else {
+                    &mut hash.universal
+                };
+                bucket.push((i, j));
+            }
+        }
+        hash
+    }
+
+    /// The selectors which may match `elem`, in the order of their rules and
+    /// of the selectors in each rule.
+    pub(super) fn candidates(&self, elem: &ElementData) -> Vec<Entry> {
+        let mut candidates = self.universal.clone();
+        let mut extend =
+            |bucket: Option<&Vec<Entry>>| candidates.extend(bucket.into_iter().flatten());
+        extend(elem.id().and_then(|id| self.ids.get(id)));
+        for class in elem.classes() {
+            extend(self.classes.get(class));
+        }
+        extend(self.tags.get(&elem.tag_name));
+        candidates.sort_unstable();
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{self, Layers, PseudoElement};
+    use crate::dom::{Node, NodeType};
+    use crate::html;
+    use crate::style::{matches, matching_rules, ActiveStylesheet, ElementContext, MatchedRule};
+    use std::time::{Duration, Instant};
+
+    fn active_stylesheet<'a>(rules: &[(usize, &'a Rule)]) -> ActiveStylesheet<'a> {
+        ActiveStylesheet {
+            origin: css::Origin::Author,
+            rules: rules.to_vec(),
+            hash: RuleHash::new(rules),
+        }
+    }
+
+    /// Call `f` with every element of the tree at `node`, in tree order.
+    fn for_each_element(
+        node: &Node,
+        parent: Option<&ElementContext>,
+        previous_siblings: &[&ElementData],
+        f: &mut dyn FnMut(&ElementContext),
+    ) {
+        let NodeType::Element(ref elem) = node.node_type else {
+            return;
+        };
+        let context = ElementContext {
+            elem,
+            previous_siblings,
+            parent,
+        };
+        f(&context);
+        let mut siblings = vec![];
+        for child in &node.children {
+            for_each_element(child, Some(&context), &siblings, f);
+            if let NodeType::Element(ref elem) = child.node_type {
+                siblings.push(elem);
+            }
+        }
+    }
+
+    /// The rules matching every element of `dom`, by testing every rule
+    /// (`linear`) or with the rule hash, and the time it took.
+    fn match_all<'a>(
+        dom: &Node,
+        stylesheet: &ActiveStylesheet<'a>,
+        linear: bool,
+    ) -> (Vec<Vec<(usize, MatchedRule<'a>)>>, Duration) {
+        let start = Instant::now();
+        let mut matched = vec![];
+        for_each_element(dom, None, &[], &mut |elem| {
+            for pseudo_element in [None, Some(PseudoElement::Before)] {
+                matched.push(match linear {
+                    true => linear_matching_rules(elem, stylesheet, pseudo_element),
+                    false => matching_rules(elem, stylesheet, pseudo_element),
+                });
+            }
+        });
+        (matched, start.elapsed())
+    }
+
+    /// The rules matching `elem` found by testing every rule of the
+    /// stylesheet, as before there was a rule hash.
+    fn linear_matching_rules<'a>(
+        elem: &ElementContext,
+        stylesheet: &ActiveStylesheet<'a>,
+        pseudo_element: Option<PseudoElement>,
+    ) -> Vec<(usize, MatchedRule<'a>)> {
+        let match_rule = |&(layer, rule): &(usize, &'a Rule)| {
+            let selector = rule
+                .selectors
+                .iter()
+                .filter(|selector| selector.pseudo_element() == pseudo_element)
+                .find(|selector| matches(elem, selector))?;
+            Some((layer, (selector.specificity(), rule)))
+        };
+        stylesheet.rules.iter().filter_map(match_rule).collect()
+    }
+
+    /// A page of `sections` sections with 20 elements each, and a stylesheet
+    /// with two rules for each section, and a few for the whole page, in all
+    /// kinds of buckets.
+    fn page(sections: usize) -> (Node, css::Stylesheet) {
+        let mut html = String::from("<main id=\"main\">");
+        let mut css = String::from(
+            "* { margin: 0 } main * {} #main > section {} .c0.p1 {} p + .c3 ~ hr {} a.link {}",
+        );
+        for i in 0..sections {
+            html += &format!("<section id=\"s{i}\" class=\"section c{}\">", i % 7);
+            for j in 0..9 {
+                html += &format!(
+                    "<p class=\"p{j} s{i}-{j} c{}\"><a class=\"link\">x</a></p>",
+                    (i + j) % 7
+                );
+            }
+            html += "<hr></hr></section>";
+            css += &format!(
+                "#s{i} .s{i}-{j}, #s{i} > .s{i}-{k} {{}} .s{i}-{j}::before, section#s{i} {{}}",
+                j = i % 9,
+                k = (i + 4) % 9
+            );
+        }
+        html += "</main>";
+        (html::parse(html), css::parse(css))
+    }
+
+    fn same_rules(a: &[Vec<(usize, MatchedRule)>], b: &[Vec<(usize, MatchedRule)>]) -> bool {
+        a.len() == b.len()
+            && a.iter().zip(b).all(|(a, b)| {
+                a.len() == b.len()
+                    && a.iter().zip(b).all(|((l1, (s1, r1)), (l2, (s2, r2)))| {
+                        l1 == l2 && s1 == s2 && std::ptr::eq(*r1, *r2)
+                    })
+            })
+    }
+
+    fn style_rules(stylesheet: &css::Stylesheet) -> Vec<(usize, &Rule)> {
+        let device = css::Device::new(800.0, 600.0);
+        stylesheet.style_rules(&device, &|_, _| true, &mut Layers::default())
+    }
+
+    #[test]
+    fn same_as_linear_matching() {
+        let (dom, stylesheet) = page(30);
+        let rules = style_rules(&stylesheet);
+        let stylesheet = active_stylesheet(&rules);
+        let (linear, _) = match_all(&dom, &stylesheet, true);
+        let (hashed, _) = match_all(&dom, &stylesheet, false);
+        assert!(same_rules(&linear, &hashed));
+        assert!(linear.iter().any(|rules| rules.len() > 3));
+    }
+
+    #[test]
+    fn buckets() {
+        let stylesheet =
+            css::parse("#a.b, .c.d, p, *, p.e::before, div > .f, span * {}".to_string());
+        let rules = style_rules(&stylesheet);
+        let hash = RuleHash::new(&rules);
+        assert_eq!(hash.ids["a"].len(), 1);
+        assert_eq!(hash.classes["c"].len(), 1);
+        assert_eq!(hash.classes["e"].len(), 1);
+        assert_eq!(hash.classes["f"].len(), 1);
+        assert_eq!(hash.tags["p"].len(), 1);
+        assert_eq!(hash.universal.len(), 2);
+
+        let dom = html::parse("<p class=\"f e\"></p>".to_string());
+        let NodeType::Element(ref elem) = dom.node_type else {
+            unreachable!()
+        };
+        assert_eq!(hash.candidates(elem).len(), 5);
+    }
+
+    /// Compare the time it takes to match the rules of a stylesheet with
+    /// about 2000 rules against every element of a page with 20000 elements,
+    /// by testing every rule and with the rule hash. Run with
+    /// `cargo test --release rule_hash_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn rule_hash_benchmark() {
+        let (dom, stylesheet) = page(1000);
+        let rules = style_rules(&stylesheet);
+        let stylesheet = active_stylesheet(&rules);
+        let (linear, linear_time) = match_all(&dom, &stylesheet, true);
+        let (hashed, hashed_time) = match_all(&dom, &stylesheet, false);
+        assert!(same_rules(&linear, &hashed));
+        println!(
+            "{} rules, {} elements: linear {:?}, rule hash {:?} ({:.1}x)",
+            rules.len(),
+            linear.len() / 2,
+            linear_time,
+            hashed_time,
+            linear_time.as_secs_f64() / hashed_time.as_secs_f64()
+        );
+    }
+}
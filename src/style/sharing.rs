@@ -0,0 +1,253 @@
+//! A cache of the styles of recently styled elements, so that an element can
+//! reuse the style of a sibling or a cousin instead of cascading and
+//! computing its own, like the style sharing caches of Servo and Blink.
+//!
+//! An element gets the same style as another one when both:
+//! - have the same parent style. Siblings do, and cousins do when their
+//!   parents shared a style, i.e. the same `Arc` of computed values.
+//! - have the same tag name and attributes, so the same simple selectors and
+//!   `style` attribute apply to them.
+//! - match the same selectors with combinators, which also depend on their
+//!   ancestors and siblings. Those are matched again to revalidate a
+//!   candidate.
+
+use super::{matches, ActiveStylesheet, ElementContext, PropertyMap};
+use crate::css::Selector;
+use crate::dom::ElementData;
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+/// The number of recently styled elements to keep.
+pub(super) const CACHE_SIZE: usize = 31;
+
+/// The computed values of an element and of its pseudo-elements that
+/// generate boxes.
+#[derive(Clone)]
+pub(super) struct ElementStyle {
+    pub(super) values: Arc<PropertyMap>,
+    pub(super) marker: Option<Arc<PropertyMap>>,
+    pub(super) before: Option<Arc<PropertyMap>>,
+    pub(super) after: Option<Arc<PropertyMap>>,
+}
+
+struct Entry<'a> {
+    /// Kept alive so that no other parent style gets the same address.
+    parent: Arc<PropertyMap>,
+    elem: &'a ElementData,
+    revalidation: Vec<bool>,
+    style: ElementStyle,
+}
+
+/// The styles of the last elements styled, most recent first.
+pub(super) struct StyleSharingCache<'a> {
+    capacity: usize,
+    entries: VecDeque<Entry<'a>>,
+}
+
+impl<'a> StyleSharingCache<'a> {
+    /// A cache of `capacity` elements. Nothing is shared with a capacity of
+    /// 0.
+    pub(super) fn new(capacity: usize) -> StyleSharingCache<'a> {
+        StyleSharingCache {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// An empty cache of the same capacity, for another thread.
+    pub(super) fn fork(&self) -> StyleSharingCache<'a> {
+        StyleSharingCache::new(self.capacity)
+    }
+
+    /// Return the style of a recent element which `elem` can share, given
+    /// the computed values of its parent and the results of `revalidation`.
+    pub(super) fn lookup(
+        &mut self,
+        elem: &ElementData,
+        parent: &Arc<PropertyMap>,
+        revalidation: &[bool],
+    ) -> Option<ElementStyle> {
+        let i = self.entries.iter().position(|entry| {
+            Arc::ptr_eq(&entry.parent, parent)
+                && entry.elem == elem
+                && entry.revalidation == revalidation
+        })?;
+        let entry = self.entries.remove(i)?;
+        let style = entry.style.clone();
+        self.entries.push_front(entry);
+        Some(style)
+    }
+
+    /// Remember the style of `elem`, dropping the least recent one if the
+    /// cache is full.
+    pub(super) fn insert(
+        &mut self,
+        elem: &'a ElementData,
+        parent: &Arc<PropertyMap>,
+        revalidation: Vec<bool>,
+        style: ElementStyle,
+    ) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.entries.truncate(self.capacity - 1);
+        self.entries.push_front(Entry {
+            parent: parent.clone(),
+            elem,
+            revalidation,
+            style,
+        });
+    }
+}
+
+/// Match `elem` against the selectors with combinators that may match it or
+/// its pseudo-elements, in a fixed order. Elements with the same tag name
+/// and attributes have the same candidates, so their results line up.
+pub(super) fn revalidation(elem: &ElementContext, stylesheets: &[ActiveStylesheet]) -> Vec<bool> {
+    let mut results = vec![];
+    for stylesheet in stylesheets {
+        for (i, j) in stylesheet.hash.candidates(elem.elem) {
+            let selector = &stylesheet.rules[i].1.selectors[j];
+            if let Selector::Complex(..) = selector {
+                results.push(matches(elem, selector));
+            }
+        }
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::css::{self, Device};
+    use crate::html;
+    use crate::style::{style_tree, style_tree_with, StyledNode};
+
+    fn styled<'a>(dom: &'a crate::dom::Node, css: &str) -> StyledNode<'a> {
+        let stylesheet = css::parse(css.to_string());
+        style_tree(dom, &[stylesheet], &Device::new(800.0, 600.0))
+    }
+
+    fn shared(a: &StyledNode, b: &StyledNode) -> bool {
+        Arc::ptr_eq(&a.computed_values, &b.computed_values)
+    }
+
+    #[test]
+    fn siblings() {
+        let dom = html::parse(
+            r#"<ul><li class="a">1</li><li class="a">2</li><li class="b">3</li><li class="a" style="color: red">4</li></ul>"#
+                .to_string(),
+        );
+        let styled = styled(&dom, "li { color: blue } .b { margin: 1px }");
+        let li = &styled.children;
+        assert!(shared(&li[0], &li[1]));
+        // The pseudo-elements are shared too.
+        assert!(Arc::ptr_eq(
+            &li[0].marker.as_ref().unwrap().computed_values,
+            &li[1].marker.as_ref().unwrap().computed_values
+        ));
+        assert!(!shared(&li[0], &li[2]));
+        assert!(!shared(&li[0], &li[3]));
+        // Their text is generated for each one.
+        let marker = |i: usize| li[i].marker.as_ref().unwrap().children[0].text();
+        assert_eq!(marker(0), Some("• "));
+        assert_eq!(marker(1), Some("• "));
+    }
+
+    #[test]
+    fn combinators() {
+        let dom = html::parse(
+            "<div><p><b>x</b></p><p><b>y</b></p><hr></hr><p><b>z</b></p></div>".to_string(),
+        );
+        let styled = styled(&dom, "p ~ p { margin-left: 1px } hr ~ p > b { color: red }");
+        let p = &styled.children;
+        // The first paragraph doesn't follow another one.
+        assert!(!shared(&p[0], &p[1]));
+        // The second and last paragraphs have the same style, but the bold
+        // text in the last one matches another selector.
+        assert!(shared(&p[1], &p[3]));
+        assert!(!shared(&p[1].children[0], &p[3].children[0]));
+        let color = p[3].children[0].value("color");
+        assert!(matches!(color, Some(css::Value::Colorvalue(c)) if c.r == 255));
+    }
+
+    #[test]
+    fn cousins() {
+        let dom = html::parse(
+            r#"<div><p><b>x</b></p><p><b>y</b></p><p class="c"><b>z</b></p></div>"#.to_string(),
+        );
+        let styled = styled(&dom, "b { color: blue }");
+        let p = &styled.children;
+        assert!(shared(&p[0].children[0], &p[1].children[0]));
+        // The last paragraph has another class, so it doesn't share the same
+        // style, and neither does its content.
+        assert!(!shared(&p[0], &p[2]));
+        assert!(!shared(&p[0].children[0], &p[2].children[0]));
+    }
+
+    /// Return true if both trees have the same computed values everywhere.
+    fn same_values(a: &StyledNode, b: &StyledNode) -> bool {
+        let pseudo_elements = |node: &StyledNode| {
+            [
+                node.marker.is_some(),
+                node.before.is_some(),
+                node.after.is_some(),
+            ]
+        };
+        a.computed_values == b.computed_values
+            && a.text() == b.text()
+            && pseudo_elements(a) == pseudo_elements(b)
+            && a.children.len() == b.children.len()
+            && a.box_children()
+                .zip(b.box_children())
+                .all(|(a, b)| same_values(a, b))
+    }
+
+    /// Return the number of distinct computed values of the elements of the
+    /// tree.
+    fn count_styles(node: &StyledNode, styles: &mut Vec<*const PropertyMap>) -> usize {
+        if node.text().is_none() && !styles.contains(&Arc::as_ptr(&node.computed_values)) {
+            styles.push(Arc::as_ptr(&node.computed_values));
+        }
+        node.children.iter().for_each(|child| {
+            count_styles(child, styles);
+        });
+        styles.len()
+    }
+
+    #[test]
+    fn same_as_without_sharing() {
+        let mut html = String::from("<main>");
+        for i in 0..20 {
+            html += &format!(
+                r#"<section class="s{}"><h1>t</h1><ol><li>a</li><li class="x">b</li><li>c</li></ol><p><a>x</a> <a>y</a></p><p id="p{i}"><a>z</a></p></section>"#,
+                i % 2
+            );
+        }
+        html += "</main>";
+        let dom = html::parse(html);
+        let stylesheet = css::parse(
+            r#"
+            section { --gap: 2px; }
+            .s1 { font-size: 20px; }
+            .s0 > ol { list-style-type: lower-roman; }
+            li + li { margin-top: var(--gap); }
+            .x ~ li::before { content: "-"; }
+            .s1 p + p a { color: red; }
+            #p4 a::after { content: counter(list-item); }
+            "#
+            .to_string(),
+        );
+        let device = Device::new(800.0, 600.0);
+        let stylesheets = [stylesheet];
+        let with = style_tree_with(&dom, &stylesheets, &device, CACHE_SIZE, 1);
+        let without = style_tree_with(&dom, &stylesheets, &device, 0, 1);
+        assert!(same_values(&with, &without));
+        let shared = count_styles(&with, &mut vec![]);
+        let unshared = count_styles(&without, &mut vec![]);
+        assert!(
+            shared * 3 < unshared,
+            "{shared} styles, {unshared} without sharing"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! The user agent stylesheet, which gives HTML elements their default
+//! rendering. It is the lowest origin of the cascade.
+//! spec: https://html.spec.whatwg.org/multipage/rendering.html
+
+use crate::css::{self, Origin, Stylesheet};
+use std::sync::OnceLock;
+
+/// Return the built-in stylesheet, parsed on first use.
+pub(super) fn stylesheet() -> &'static Stylesheet {
+    static STYLESHEET: OnceLock<Stylesheet> = OnceLock::new();
+    STYLESHEET.get_or_init(|| {
+        let mut stylesheet = css::parse(include_str!("user_agent.css").to_string());
+        stylesheet.origin = Origin::UserAgent;
+        stylesheet
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::css::{self, Unit, Value};
+    use crate::html;
+    use crate::style::{style_tree, Display};
+
+    #[test]
+    fn default_rendering() {
+        let dom = html::parse(
+            r#"<html><head><title>t</title></head><body><p>a</p><ul><li>b</li></ul><h1 class="x">c</h1></body></html>"#
+                .to_string(),
+        );
+        let stylesheet = css::parse(".x { margin-top: 0; }".to_string());
+        let styled = style_tree(&dom, &[stylesheet], &css::Device::new(800.0, 600.0));
+
+        assert!(matches!(styled.display(), Display::BLOCK));
+        assert!(matches!(styled.children[0].display(), Display::None));
+        let body = &styled.children[1];
+        assert_eq!(
+            body.value("margin-left"),
+            Some(Value::Length(8.0, Unit::Px))
+        );
+        assert!(matches!(body.children[0].display(), Display::BLOCK));
+        assert!(body.children[1].children[0].marker.is_some());
+
+        // Author styles win over the user agent's.
+        let h1 = &body.children[2];
+        assert_eq!(h1.value("margin-top"), Some(Value::Length(0.0, Unit::Px)));
+        assert_eq!(h1.value("font-size"), Some(Value::Length(32.0, Unit::Px)));
+    }
+}
@@ -0,0 +1,195 @@
+//! Custom properties and `var()` substitution, done at computed-value time.
+//! spec: https://www.w3.org/TR/css-variables-1/
+
+use super::PropertyMap;
+use crate::css::Value;
+use std::collections::{HashMap, HashSet};
+
+/// Return true if `name` is a custom property, e.g. `--main-color`.
+pub(super) fn is_custom(name: &str) -> bool {
+    name.starts_with("--")
+}
+
+/// Compute the custom properties of an element from its cascaded custom
+/// properties and the computed values of its parent. Custom properties are
+/// inherited, and `var()` references in them are substituted.
+///
+/// Properties that are invalid at computed-value time, because they reference
+/// an undefined variable without a fallback or are part of a reference cycle,
+/// are left out, as they have the guaranteed-invalid initial value.
+pub(super) fn compute_custom_properties(
+    cascaded: &PropertyMap,
+    parent: Option<&PropertyMap>,
+) -> PropertyMap {
+    let mut specified: HashMap<&str, &str> = parent
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| match value {
+            Value::Raw(css) if is_custom(name) => Some((&**name, &**css)),
+            _ => None,
+        })
+        .collect();
+    for (name, value) in cascaded {
+        match value {
+            Value::Raw(css) => {
+                specified.insert(name, css);
+            }
+            Value::Keyword(k) if k == "initial" => {
+                specified.remove(&**name);
+            }
+            // `inherit` and `unset` keep the parent's value.
+            _ => {}
+        }
+    }
+
+    let mut resolver = Resolver {
+        specified: &specified,
+        computed: HashMap::new(),
+        stack: vec![],
+        cyclic: HashSet::new(),
+    };
+    specified
+        .keys()
+        .filter_map(|name| Some((name.to_string(), Value::Raw(resolver.resolve(name)?))))
+        .collect()
+}
+
+/// Substitute the variables in a pending-substitution value `css`, given the
+/// computed custom properties of the element. Return `None` if the value is
+/// invalid at computed-value time.
+pub(super) fn substitute_pending(css: &str, custom_properties: &PropertyMap) -> Option<String> {
+    substitute(css, &mut |name| match custom_properties.get(name) {
+        Some(Value::Raw(css)) => Some(css.clone()),
+        _ => None,
+    })
+}
+
+/// Resolves the `var()` references of custom properties, detecting cycles.
+struct Resolver<'a> {
+    specified: &'a HashMap<&'a str, &'a str>,
+    /// The resolved values, or `None` for invalid ones.
+    computed: HashMap<String, Option<String>>,
+    /// The properties being resolved, each referenced by the previous one.
+    stack: Vec<String>,
+    /// Properties found to be part of a cycle.
+    cyclic: HashSet<String>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, name: &str) -> Option<String> {
+        if let Some(result) = self.computed.get(name) {
+            return result.clone();
+        }
+        let css = *self.specified.get(name)?;
+        if let Some(i) = self.stack.iter().position(|n| n == name) {
+            // Every property in a cycle is invalid, even with fallbacks.
+            self.cyclic.extend(self.stack[i..].iter().cloned());
+            return None;
+        }
+
+        self.stack.push(name.to_string());
+        let result = substitute(css, &mut |name| self.resolve(name));
+        self.stack.pop();
+
+        let result = result.filter(|_| !self.cyclic.contains(name));
+        self.computed.insert(name.to_string(), result.clone());
+        result
+    }
+}
+
+/// Replace every `var(--name[, fallback])` in `css` with the value `lookup`
+/// returns for `--name`, or else with its fallback. Return `None` if there is
+/// neither.
+fn substitute(css: &str, lookup: &mut dyn FnMut(&str) -> Option<String>) -> Option<String> {
+    let mut result = String::new();
+    let mut rest = css;
+    while let Some(start) = find_var(rest) {
+        result.push_str(&rest[..start]);
+        let args_start = start + "var(".len();
+        let end = args_start + closing_paren(&rest[args_start..])?;
+        let args = &rest[args_start..end];
+        let (name, fallback) = match args.split_once(',') {
+            Some((name, fallback)) => (name.trim(), Some(fallback.trim())),
+            None => (args.trim(), None),
+        };
+        let value = match lookup(name) {
+            Some(value) => value,
+            None => substitute(fallback?, lookup)?,
+        };
+        result.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Some(result)
+}
+
+/// Find the start of the first `var(` function in `css`.
+fn find_var(css: &str) -> Option<usize> {
+    let lowercase = css.to_ascii_lowercase();
+    lowercase.match_indices("var(").map(|(i, _)| i).find(|&i| {
+        // Skip functions whose name merely ends in `var`.
+        !lowercase[..i].ends_with(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    })
+}
+
+/// Return the index of the `)` closing a function whose arguments start `css`.
+fn closing_paren(css: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in css.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Some(i),
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(declarations: &[(&str, &str)]) -> PropertyMap {
+        declarations
+            .iter()
+            .map(|&(name, css)| (name.to_string(), Value::Raw(css.to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn substitution() {
+        let values = custom(&[("--a", "1px"), ("--b", "var(--a) solid")]);
+        let lookup = |css| substitute_pending(css, &values);
+        assert_eq!(lookup("var(--a)").as_deref(), Some("1px"));
+        assert_eq!(lookup("0 VAR( --a ) 2px").as_deref(), Some("0 1px 2px"));
+        assert_eq!(lookup("var(--x, var(--a))").as_deref(), Some("1px"));
+        assert_eq!(lookup("var(--x, 1px, 2px)").as_deref(), Some("1px, 2px"));
+        assert_eq!(lookup("var(--x)"), None);
+        assert_eq!(lookup("somevar(--a)").as_deref(), Some("somevar(--a)"));
+    }
+
+    #[test]
+    fn inheritance() {
+        let parent = custom(&[("--a", "1px"), ("--b", "2px")]);
+        let mut cascaded = custom(&[("--c", "var(--a) var(--b)"), ("--b", "3px")]);
+        cascaded.insert("--a".to_string(), Value::Keyword("initial".to_string()));
+        cascaded.insert("--d".to_string(), Value::Raw("var(--b)".to_string()));
+        let computed = compute_custom_properties(&cascaded, Some(&parent));
+        // `--c` references `--a`, which has no value, so it's invalid.
+        assert_eq!(computed, custom(&[("--b", "3px"), ("--d", "3px")]));
+    }
+
+    #[test]
+    fn cycles() {
+        let cascaded = custom(&[
+            ("--a", "var(--b)"),
+            ("--b", "var(--a, 1px)"),
+            ("--c", "var(--a, 2px)"),
+            ("--d", "var(--d)"),
+            ("--e", "var(--c, 3px)"),
+        ]);
+        let computed = compute_custom_properties(&cascaded, None);
+        assert_eq!(computed, custom(&[("--c", "2px"), ("--e", "2px")]));
+    }
+}